@@ -17,7 +17,7 @@ pub(crate) mod known {
 use crate::{
     contexts::ScoopContext,
     git::{self, Repo},
-    packages::{self, CreateManifest, InstallManifest, Manifest, SearchMode},
+    packages::{self, BucketManifest, InstallManifest, NamedManifest, SearchMode},
 };
 
 #[derive(Debug, thiserror::Error)]
@@ -40,6 +40,8 @@ pub enum Error {
     InvalidTime,
     #[error("Invalid timezone provided. (where are you?)")]
     InvalidTimeZone,
+    #[error("Cloning bucket: {0}")]
+    Clone(#[from] git::clone::Error),
 }
 
 /// Bucket result type
@@ -137,11 +139,14 @@ impl Bucket {
     /// - The bucket is invalid
     /// - Any package has an invalid path or invalid contents
     /// - See more at [`packages::Error`]
-    pub fn list_packages(&self) -> packages::Result<Vec<Manifest>> {
+    pub fn list_packages(&self) -> packages::Result<Vec<BucketManifest>> {
         let packages = self.list_package_paths()?;
 
         // TODO: Use rayon here
-        packages.into_iter().map(Manifest::from_path).collect()
+        packages
+            .into_iter()
+            .map(|path| NamedManifest::from_path(path).map(|manifest| manifest.with_bucket(self)))
+            .collect()
     }
 
     /// List all packages contained within this bucket, ignoring invalid buckets
@@ -149,13 +154,14 @@ impl Bucket {
     /// # Errors
     /// - The bucket is invalid
     /// - See more at [`packages::Error`]
-    pub fn list_packages_unchecked(&self) -> packages::Result<Vec<Manifest>> {
+    pub fn list_packages_unchecked(&self) -> packages::Result<Vec<BucketManifest>> {
         let packages = self.list_package_paths()?;
 
         // TODO: Use rayon here
         Ok(packages
             .into_iter()
-            .filter_map(|path| Manifest::from_path(path).ok())
+            .filter_map(|path| NamedManifest::from_path(path).ok())
+            .map(|manifest| manifest.with_bucket(self))
             .collect())
     }
 
@@ -243,10 +249,10 @@ impl Bucket {
     ///
     /// # Errors
     /// - Could not load the manifest from the path
-    pub fn get_manifest(&self, name: impl AsRef<str>) -> packages::Result<Manifest> {
+    pub fn get_manifest(&self, name: impl AsRef<str>) -> packages::Result<BucketManifest> {
         let manifest_path = self.get_manifest_path(name);
 
-        Manifest::from_path(manifest_path).map(|manifest| manifest.with_bucket(self))
+        NamedManifest::from_path(manifest_path).map(|manifest| manifest.with_bucket(self))
     }
 
     /// List all matches for the given pattern
@@ -259,7 +265,7 @@ impl Bucket {
         installed_only: bool,
         search_regex: &Regex,
         search_mode: SearchMode,
-    ) -> packages::Result<Vec<Manifest>>
+    ) -> packages::Result<Vec<BucketManifest>>
     where
         &'a C: Send + Sync,
     {
@@ -362,19 +368,199 @@ impl Bucket {
     /// - The remote's url is not utf8
     /// - The remote's url is not set
     pub fn source(&self) -> Result<String> {
-        Ok(self
-            .open_repo()?
-            .origin()
-            .ok_or(git::Error::MissingRemote("origin".to_string()))?
-            .url(gix::remote::Direction::Fetch)
-            .map(std::string::ToString::to_string)
-            .ok_or(git::Error::NonUtf8)?)
+        Ok(self.open_repo()?.origin_url()?)
+    }
+
+    #[must_use]
+    /// Get the bucket's source url from the known bucket registry
+    ///
+    /// Returns `None` if the bucket is not a known bucket
+    pub fn known_source(&self, ctx: &impl ScoopContext) -> Option<&'static str> {
+        ctx.known_buckets().get(self.name().as_ref()).copied()
+    }
+
+    /// Compare the bucket's origin against the given url
+    ///
+    /// Returns `None` if the origin already points to the given url.
+    /// Trailing slashes, a trailing `.git` and casing are ignored in the comparison.
+    ///
+    /// # Errors
+    /// - The bucket's source could not be read
+    pub fn check_remote(&self, expected: &str) -> Result<Option<RemoteMismatch>> {
+        let current = self.source()?;
+
+        if git::urls_match(&current, expected) {
+            Ok(None)
+        } else {
+            Ok(Some(RemoteMismatch {
+                bucket: self.clone(),
+                current,
+                expected: expected.to_string(),
+            }))
+        }
+    }
+
+    /// Compare the bucket's origin against the known bucket registry
+    ///
+    /// Returns `None` if the bucket is not a known bucket,
+    /// or if its origin already matches the registry
+    ///
+    /// # Errors
+    /// - The bucket's source could not be read
+    pub fn check_known_remote(&self, ctx: &impl ScoopContext) -> Result<Option<RemoteMismatch>> {
+        match self.known_source(ctx) {
+            Some(expected) => self.check_remote(expected),
+            None => Ok(None),
+        }
+    }
+
+    /// List all known buckets whose origin does not match the known bucket registry
+    ///
+    /// # Errors
+    /// - Was unable to read the bucket directory
+    /// - Any bucket's source could not be read
+    pub fn moved(ctx: &impl ScoopContext) -> Result<Vec<RemoteMismatch>> {
+        let buckets = Self::list_all(ctx)?;
+
+        let mut mismatches = vec![];
+        for bucket in buckets {
+            if let Some(mismatch) = bucket.check_known_remote(ctx)? {
+                mismatches.push(mismatch);
+            }
+        }
+
+        Ok(mismatches)
+    }
+
+    /// Migrate every known bucket whose origin does not match the known bucket registry
+    ///
+    /// Returns the mismatches that were found alongside the result of migrating each.
+    /// See [`Bucket::migrate_all`]
+    ///
+    /// # Errors
+    /// - See [`Bucket::moved`]
+    pub fn migrate_moved(
+        ctx: &impl ScoopContext,
+    ) -> Result<Vec<(RemoteMismatch, Result<RemoteMigration>)>> {
+        Ok(Self::migrate_all(Self::moved(ctx)?))
+    }
+
+    /// Migrate each bucket to its expected url
+    ///
+    /// A failed migration does not stop the remaining buckets from being migrated.
+    /// Each mismatch is returned alongside the result of migrating it.
+    pub fn migrate_all(
+        mismatches: impl IntoIterator<Item = RemoteMismatch>,
+    ) -> Vec<(RemoteMismatch, Result<RemoteMigration>)> {
+        mismatches
+            .into_iter()
+            .map(|mismatch| {
+                let migration = mismatch.migrate();
+                (mismatch, migration)
+            })
+            .collect()
+    }
+
+    /// Point the bucket's origin at a new url
+    ///
+    /// If the new remote shares history with the local bucket, the origin url is rewritten in place.
+    /// Otherwise the bucket is re-cloned from the new url, keeping the same bucket name.
+    /// The existing bucket is only removed once the new clone has succeeded.
+    ///
+    /// # Errors
+    /// - The bucket could not be opened as a repository
+    /// - The new remote could not be fetched
+    /// - The bucket could not be re-cloned
+    pub fn migrate_remote(&self, url: &str) -> Result<RemoteMigration> {
+        let repo = self.open_repo()?;
+
+        if shares_history(repo.git2(), url)? {
+            repo.git2()
+                .remote_set_url("origin", url)
+                .map_err(git::Error::from)?;
+
+            debug!("Updated origin for bucket {} to {url}", self.name());
+
+            return Ok(RemoteMigration::RemoteUpdated);
+        }
+
+        drop(repo);
+
+        git::reclone(self.path(), url, "migrating")?;
+
+        debug!("Re-cloned bucket {} from {url}", self.name());
+
+        Ok(RemoteMigration::Recloned)
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A bucket whose origin does not match the expected url
+pub struct RemoteMismatch {
+    /// The bucket
+    pub bucket: Bucket,
+    /// The bucket's current origin url
+    pub current: String,
+    /// The url the bucket's origin should point to
+    pub expected: String,
+}
+
+impl RemoteMismatch {
+    /// Migrate the bucket to the expected url
+    ///
+    /// # Errors
+    /// - See [`Bucket::migrate_remote`]
+    pub fn migrate(&self) -> Result<RemoteMigration> {
+        self.bucket.migrate_remote(&self.expected)
+    }
+}
+
+impl std::fmt::Display for RemoteMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} -> {}",
+            self.bucket.name(),
+            self.current,
+            self.expected
+        )
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// How a bucket was migrated to a new remote
+pub enum RemoteMigration {
+    /// The origin url was rewritten in place
+    RemoteUpdated,
+    /// The bucket was re-cloned, as the new remote did not share any history with it
+    Recloned,
+}
+
+/// Check if the remote at the given url shares any history with the repository's head
+fn shares_history(repo: &git2::Repository, url: &str) -> Result<bool> {
+    let local = repo.head().and_then(|head| head.peel_to_commit());
+    let local = local.map_err(git::Error::from)?;
+
+    let mut remote = repo.remote_anonymous(url).map_err(git::Error::from)?;
+    remote
+        .fetch(&["HEAD"], None, None)
+        .map_err(git::Error::from)?;
+
+    let fetched = repo
+        .find_reference("FETCH_HEAD")
+        .and_then(|reference| reference.peel_to_commit())
+        .map_err(git::Error::from)?;
+
+    match repo.merge_base(local.id(), fetched.id()) {
+        Ok(_) => Ok(true),
+        Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(false),
+        Err(e) => Err(git::Error::from(e).into()),
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::contexts::User;
+    use crate::contexts::{testing::TestContext, User};
 
     use super::*;
 
@@ -411,4 +597,142 @@ mod tests {
             .pull(&User::new(), None)
             .unwrap();
     }
+
+    fn init_repo(path: &Path, file_name: &str) -> git2::Repository {
+        let repo = git2::Repository::init(path).unwrap();
+        std::fs::write(path.join(file_name), "{}").unwrap();
+
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(file_name)).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("sprinkles", "sprinkles@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+        drop(tree);
+
+        repo
+    }
+
+    #[test]
+    fn test_remote_urls_match() {
+        assert!(git::urls_match(
+            "https://github.com/ScoopInstaller/Main",
+            "https://github.com/scoopinstaller/main.git/"
+        ));
+        assert!(!git::urls_match(
+            "https://github.com/ScoopInstaller/Main",
+            "https://github.com/ScoopInstaller/Extras"
+        ));
+    }
+
+    #[test]
+    fn test_migrate_related_remote() {
+        let ctx = TestContext::new("related-remote");
+        let dir = ctx.path();
+
+        let upstream = dir.join("upstream");
+        init_repo(&upstream, "app.json");
+        let moved = dir.join("moved");
+        git2::Repository::clone(upstream.to_str().unwrap(), &moved).unwrap();
+
+        let bucket_path = ctx.buckets_path().join("main");
+        git2::Repository::clone(upstream.to_str().unwrap(), &bucket_path).unwrap();
+
+        let bucket = Bucket::from_path(&bucket_path).unwrap();
+        let moved_url = moved.to_str().unwrap();
+
+        let mismatch = bucket.check_remote(moved_url).unwrap().unwrap();
+        assert_eq!(mismatch.migrate().unwrap(), RemoteMigration::RemoteUpdated);
+
+        assert_eq!(bucket.source().unwrap(), moved_url);
+        assert!(bucket.check_remote(moved_url).unwrap().is_none());
+
+        ctx.cleanup();
+    }
+
+    #[test]
+    fn test_migrate_unrelated_remote() {
+        let ctx = TestContext::new("unrelated-remote");
+        let dir = ctx.path();
+
+        let upstream = dir.join("upstream");
+        init_repo(&upstream, "old.json");
+        let moved = dir.join("moved");
+        init_repo(&moved, "new.json");
+
+        let bucket_path = ctx.buckets_path().join("main");
+        git2::Repository::clone(upstream.to_str().unwrap(), &bucket_path).unwrap();
+
+        let bucket = Bucket::from_path(&bucket_path).unwrap();
+        let moved_url = moved.to_str().unwrap();
+
+        assert_eq!(
+            bucket.migrate_remote(moved_url).unwrap(),
+            RemoteMigration::Recloned
+        );
+
+        assert_eq!(bucket.name(), "main");
+        assert!(bucket_path.join("new.json").exists());
+        assert!(!bucket_path.join("old.json").exists());
+        assert!(bucket.check_remote(moved_url).unwrap().is_none());
+
+        ctx.cleanup();
+    }
+
+    #[test]
+    fn test_migrate_moved_buckets() {
+        let ctx = TestContext::new("moved-buckets");
+        let dir = ctx.path();
+
+        let upstream = dir.join("upstream");
+        init_repo(&upstream, "app.json");
+
+        let known = |name: &str| *ctx.known_buckets().get(name).unwrap();
+
+        // `main` matches the registry, `extras` has moved, and `custom` is not a known bucket
+        for name in ["main", "extras", "custom"] {
+            let repo =
+                git2::Repository::clone(upstream.to_str().unwrap(), ctx.buckets_path().join(name))
+                    .unwrap();
+            if name == "main" {
+                repo.remote_set_url("origin", known("main")).unwrap();
+            }
+        }
+
+        let moved = Bucket::moved(&ctx).unwrap();
+        let [extras] = moved.as_slice() else {
+            panic!("expected only extras to have moved, got {moved:?}");
+        };
+        assert_eq!(extras.bucket.name(), "extras");
+        assert_eq!(extras.current, upstream.to_str().unwrap());
+        assert_eq!(extras.expected, known("extras"));
+
+        // A failed migration does not stop the others
+        let related = dir.join("related");
+        git2::Repository::clone(upstream.to_str().unwrap(), &related).unwrap();
+
+        let mismatches = ["custom", "extras"].map(|name| RemoteMismatch {
+            bucket: Bucket::from_name(&ctx, name).unwrap(),
+            current: upstream.to_str().unwrap().to_string(),
+            expected: match name {
+                "custom" => dir.join("missing").to_str().unwrap().to_string(),
+                _ => related.to_str().unwrap().to_string(),
+            },
+        });
+
+        let results = Bucket::migrate_all(mismatches);
+        assert_eq!(results.len(), 2);
+
+        let (custom, result) = &results[0];
+        assert_eq!(custom.bucket.name(), "custom");
+        assert!(result.is_err());
+        assert_eq!(custom.bucket.source().unwrap(), upstream.to_str().unwrap());
+
+        let (extras, result) = &results[1];
+        assert_eq!(extras.bucket.name(), "extras");
+        assert_eq!(result.as_ref().unwrap(), &RemoteMigration::RemoteUpdated);
+        assert_eq!(extras.bucket.source().unwrap(), related.to_str().unwrap());
+
+        ctx.cleanup();
+    }
 }
@@ -16,7 +16,7 @@ use tokio_util::codec::{BytesCodec, FramedRead};
 use crate::{
     hacks::let_chain,
     hash::{url_ext::UrlExt, Hash, HashType},
-    packages::{downloading::DownloadUrl, models::manifest::TOrArrayOfTs, Manifest},
+    packages::{downloading::DownloadUrl, models::manifest::TOrArrayOfTs, NamedManifest},
     progress,
     requests::ClientLike,
     version::Version,
@@ -211,6 +211,43 @@ impl Handle {
         })
     }
 
+    /// Construct a cache handle for a url that has no hash to check against
+    ///
+    /// The downloaded file's hash is still computed, but [`DownloadResult::actual_hash`] will be empty
+    ///
+    /// # Errors
+    /// - If the file cannot be created
+    pub fn unverified(
+        cache_path: impl AsRef<Path>,
+        file_name: impl Into<PathBuf>,
+        url: String,
+    ) -> Result<Self, Error> {
+        Self::new(
+            cache_path,
+            file_name,
+            HashType::default(),
+            url,
+            Hash::from_hex(&[]),
+        )
+    }
+
+    /// Check if the file is already in the cache, and matches the expected hash
+    ///
+    /// # Errors
+    /// - If the cached file cannot be read
+    pub fn is_cached(&self) -> Result<bool, Error> {
+        if !self.cache_path.exists() {
+            return Ok(false);
+        }
+
+        let file = std::io::BufReader::new(std::fs::File::open(&self.cache_path)?);
+        let hash = Hash::compute(file, self.hash_type);
+
+        Ok(hash
+            .no_prefix()
+            .eq_ignore_ascii_case(self.actual_hash.no_prefix()))
+    }
+
     /// Open a manifest and return a cache handle
     ///
     /// # Errors
@@ -218,10 +255,10 @@ impl Handle {
     /// - Missing download URL
     pub fn open_manifest(
         cache_path: impl AsRef<Path>,
-        manifest: &Manifest,
+        manifest: &NamedManifest,
         arch: Architecture,
     ) -> Result<Vec<Self>, Error> {
-        let name = unsafe { manifest.name() };
+        let name = manifest.name();
         let version = &manifest.version;
 
         let download_urls = manifest
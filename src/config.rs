@@ -205,6 +205,19 @@ impl Scoop {
         Ok(())
     }
 
+    #[must_use]
+    /// Get the environment variable that apps' `PATH` entries are added to
+    ///
+    /// This is `PATH`, unless `use_isolated_path` is set, in which case it is `SCOOP_PATH`,
+    /// or the configured variable name in uppercase
+    pub fn path_variable(&self) -> String {
+        match &self.use_isolated_path {
+            isolated::IsolatedPath::Bool(true) => "SCOOP_PATH".to_string(),
+            isolated::IsolatedPath::Path(name) if !name.is_empty() => name.to_uppercase(),
+            _ => "PATH".to_string(),
+        }
+    }
+
     /// Make the config strict
     ///
     /// This will remove all fields that are not in the config struct
@@ -253,6 +266,23 @@ mod tests {
 
         assert_eq!(IsolatedPath::Bool(false), deserialized);
     }
+
+    #[test]
+    fn test_path_variable() {
+        let config = |isolated: Value| {
+            Scoop::from_object(serde_json::json!({
+                "root_path": "C:\\Scoop",
+                "global_path": "C:\\ProgramData\\Scoop",
+                "use_isolated_path": isolated,
+            }))
+            .unwrap()
+        };
+
+        assert_eq!(config(Value::Bool(false)).path_variable(), "PATH");
+        assert_eq!(config(Value::Bool(true)).path_variable(), "SCOOP_PATH");
+        assert_eq!(config("my_path".into()).path_variable(), "MY_PATH");
+        assert_eq!(config("".into()).path_variable(), "PATH");
+    }
 }
 
 // Scoop config output:
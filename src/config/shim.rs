@@ -15,3 +15,17 @@ pub enum ScoopShim {
     /// Use the 71 shim
     SeventyOne,
 }
+
+impl ScoopShim {
+    #[must_use]
+    /// Get the name of the directory this shim build is shipped in
+    ///
+    /// Scoop ships each build in `supporting/shims/<name>` of its app directory
+    pub fn directory_name(self) -> &'static str {
+        match self {
+            ScoopShim::Kiennq => "kiennq",
+            ScoopShim::Scoopcs => "scoopcs",
+            ScoopShim::SeventyOne => "71",
+        }
+    }
+}
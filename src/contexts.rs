@@ -11,6 +11,8 @@ use std::{
 use crate::{config, git, proxy::Proxy};
 
 mod global;
+#[cfg(test)]
+pub(crate) mod testing;
 mod user;
 
 use futures::Future;
@@ -45,6 +47,101 @@ impl Error {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The outcome of updating a context's app repository
+pub enum SelfUpdate {
+    /// The latest changes were pulled into the current branch
+    Pulled,
+    /// The repository was switched to another branch, and the latest changes were pulled
+    SwitchedBranch {
+        /// The branch that was previously checked out
+        from: String,
+        /// The branch that is now checked out
+        to: String,
+    },
+    /// The repository was re-cloned, as its remote url changed
+    Recloned,
+}
+
+/// Update the context's app repository from the given remote url and branch
+///
+/// If the repository's origin does not match the given url, the repository is re-cloned.
+/// Otherwise the given branch is checked out if needed, and the latest changes are pulled.
+///
+/// # Errors
+/// - The app repository could not be opened
+/// - The app repository could not be re-cloned
+/// - The branch could not be switched
+/// - The latest changes could not be pulled
+pub fn update_app_repo(
+    ctx: &impl ScoopContext,
+    repo_url: &str,
+    branch: &str,
+    stats_cb: Option<git::ProgressCallback<'_>>,
+) -> Result<SelfUpdate, Error> {
+    let app_path = ctx.context_app_path();
+    let repo = git::Repo::from_path(&app_path)?;
+
+    if !git::urls_match(&repo.origin_url()?, repo_url) {
+        drop(repo);
+
+        git::reclone(&app_path, repo_url, "cloning")?;
+        debug!("Re-cloned {} from {repo_url}", app_path.display());
+
+        let repo = git::Repo::from_path(&app_path)?;
+        if repo.current_branch()? != branch {
+            repo.switch_branch(ctx, branch)?;
+        }
+
+        return Ok(SelfUpdate::Recloned);
+    }
+
+    let current_branch = repo.current_branch()?;
+
+    let update = if current_branch == branch {
+        SelfUpdate::Pulled
+    } else {
+        repo.switch_branch(ctx, branch)?;
+        debug!("Switched to branch {branch}");
+
+        SelfUpdate::SwitchedBranch {
+            from: current_branch,
+            to: branch.to_string(),
+        }
+    };
+
+    repo.pull(ctx, stats_cb)?;
+
+    Ok(update)
+}
+
+/// Update Scoop itself
+///
+/// This updates the app repository to match the configured `scoop_repo` and `scoop_branch`
+/// (see [`update_app_repo`]), then updates `last_update` and saves the config.
+///
+/// # Errors
+/// - See [`update_app_repo`]
+/// - The config could not be saved
+pub fn self_update<C: ScoopContext<Config = config::Scoop>>(
+    ctx: &mut C,
+    stats_cb: Option<git::ProgressCallback<'_>>,
+) -> Result<SelfUpdate, Error> {
+    let config = ctx.config();
+    let update = update_app_repo(
+        ctx,
+        config.scoop_repo.url(),
+        config.scoop_branch.name(),
+        stats_cb,
+    )?;
+
+    let config = ctx.config_mut();
+    config.update_last_update_time();
+    config.save().map_err(Error::custom)?;
+
+    Ok(update)
+}
+
 #[derive(Debug, Copy, Clone)]
 /// An empty config struct for when your implementation does not have a config
 pub struct EmptyConfig;
@@ -95,6 +192,14 @@ pub trait ScoopContext: Clone + Send + Sync + 'static {
     /// and should be passed directly from the config.
     fn symlinks_enabled(&self) -> bool;
 
+    #[must_use]
+    /// Check if the context installs apps globally, for all users
+    ///
+    /// Defaults to `false`
+    fn is_global(&self) -> bool {
+        false
+    }
+
     /// Get the proxy for the context
     ///
     /// Generally this is an option in the config,
@@ -154,6 +259,12 @@ pub trait ScoopContext: Clone + Send + Sync + 'static {
         self.sub_path("shims")
     }
 
+    #[must_use]
+    /// Get the contexts's `PowerShell` modules path
+    fn modules_path(&self) -> PathBuf {
+        self.sub_path("modules")
+    }
+
     #[must_use]
     /// Get the contexts's workspace path
     fn workspace_path(&self) -> PathBuf {
@@ -169,6 +280,12 @@ pub trait ScoopContext: Clone + Send + Sync + 'static {
     /// Get the path to the log directory
     fn logging_dir(&self) -> std::io::Result<PathBuf>;
 
+    #[must_use]
+    /// Get the path to the context's Start Menu shortcuts folder
+    ///
+    /// For Scoop, this is the "Scoop Apps" folder in the user's or all users' Start Menu programs
+    fn shortcuts_path(&self) -> PathBuf;
+
     /// List all scoop apps and return their paths, except for the context's app
     fn installed_apps(&self) -> std::io::Result<Vec<PathBuf>> {
         #[cfg(feature = "rayon")]
@@ -280,6 +397,13 @@ impl ScoopContext for AnyContext {
         }
     }
 
+    fn is_global(&self) -> bool {
+        match self {
+            AnyContext::User(user) => user.is_global(),
+            AnyContext::Global(global) => global.is_global(),
+        }
+    }
+
     fn proxy(&self) -> Option<&Proxy> {
         match self {
             AnyContext::User(user) => user.proxy(),
@@ -337,6 +461,13 @@ impl ScoopContext for AnyContext {
         }
     }
 
+    fn modules_path(&self) -> PathBuf {
+        match self {
+            AnyContext::User(user) => user.modules_path(),
+            AnyContext::Global(global) => global.modules_path(),
+        }
+    }
+
     fn workspace_path(&self) -> PathBuf {
         match self {
             AnyContext::User(user) => user.workspace_path(),
@@ -358,6 +489,13 @@ impl ScoopContext for AnyContext {
         }
     }
 
+    fn shortcuts_path(&self) -> PathBuf {
+        match self {
+            AnyContext::User(user) => user.shortcuts_path(),
+            AnyContext::Global(global) => global.shortcuts_path(),
+        }
+    }
+
     fn app_installed(&self, name: impl AsRef<str>) -> std::io::Result<bool> {
         match self {
             AnyContext::User(user) => user.app_installed(name),
@@ -386,3 +524,110 @@ impl ScoopContext for AnyContext {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{testing::TestContext, *};
+
+    fn commit_file(repo: &git2::Repository, file_name: &str, parents: &[&git2::Commit<'_>]) {
+        let workdir = repo.workdir().unwrap();
+        std::fs::write(workdir.join(file_name), file_name).unwrap();
+
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(file_name)).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("sprinkles", "sprinkles@example.com").unwrap();
+
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            file_name,
+            &tree,
+            parents,
+        )
+        .unwrap();
+    }
+
+    fn init_upstream(path: &Path) -> git2::Repository {
+        let repo = git2::Repository::init(path).unwrap();
+        repo.set_head("refs/heads/master").unwrap();
+        commit_file(&repo, "core.ps1", &[]);
+
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.branch("develop", &head, false).unwrap();
+        drop(head);
+
+        repo
+    }
+
+    #[test]
+    fn test_update_app_repo_switches_branch() {
+        let ctx = TestContext::new("switch-branch");
+
+        let upstream_path = ctx.path().join("upstream");
+        let upstream = init_upstream(&upstream_path);
+        let upstream_url = upstream_path.to_str().unwrap();
+
+        let app_path = ctx.context_app_path();
+        git2::Repository::clone(upstream_url, &app_path).unwrap();
+
+        // Add a commit to develop, which should be pulled after switching
+        upstream.set_head("refs/heads/develop").unwrap();
+        let head = upstream.head().unwrap().peel_to_commit().unwrap();
+        commit_file(&upstream, "develop.ps1", &[&head]);
+        drop(head);
+        upstream.set_head("refs/heads/master").unwrap();
+
+        let update = update_app_repo(&ctx, upstream_url, "develop", None).unwrap();
+        assert_eq!(
+            update,
+            SelfUpdate::SwitchedBranch {
+                from: "master".to_string(),
+                to: "develop".to_string()
+            }
+        );
+
+        let repo = git::Repo::from_path(&app_path).unwrap();
+        assert_eq!(repo.current_branch().unwrap(), "develop");
+        assert!(app_path.join("develop.ps1").exists());
+
+        ctx.cleanup();
+    }
+
+    #[test]
+    fn test_update_app_repo_reclones_changed_repo() {
+        let ctx = TestContext::new("reclone");
+
+        let upstream_path = ctx.path().join("upstream");
+        init_upstream(&upstream_path);
+        let fork_path = ctx.path().join("fork");
+        let fork = git2::Repository::init(&fork_path).unwrap();
+        fork.set_head("refs/heads/master").unwrap();
+        commit_file(&fork, "fork.ps1", &[]);
+
+        let app_path = ctx.context_app_path();
+        git2::Repository::clone(upstream_path.to_str().unwrap(), &app_path).unwrap();
+
+        // Leave behind directories from an interrupted re-clone
+        let parent = app_path.parent().unwrap();
+        for stale in [".current.old", ".current.cloning"] {
+            std::fs::create_dir_all(parent.join(stale)).unwrap();
+            std::fs::write(parent.join(stale).join("stale.ps1"), "").unwrap();
+        }
+
+        let fork_url = fork_path.to_str().unwrap();
+        let update = update_app_repo(&ctx, fork_url, "master", None).unwrap();
+        assert_eq!(update, SelfUpdate::Recloned);
+
+        let repo = git::Repo::from_path(&app_path).unwrap();
+        assert!(git::urls_match(&repo.origin_url().unwrap(), fork_url));
+        assert!(app_path.join("fork.ps1").exists());
+        assert!(!app_path.join("core.ps1").exists());
+        assert!(!parent.join(".current.old").exists());
+        assert!(!parent.join(".current.cloning").exists());
+
+        ctx.cleanup();
+    }
+}
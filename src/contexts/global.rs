@@ -1,6 +1,6 @@
 use std::path::{Path, PathBuf};
 
-use crate::{config, git};
+use crate::{config, git, system::paths::WindowsPath};
 
 use super::{ScoopContext, User};
 
@@ -57,6 +57,10 @@ impl ScoopContext for Global {
         !self.config().no_junction
     }
 
+    fn is_global(&self) -> bool {
+        true
+    }
+
     fn proxy(&self) -> Option<&crate::proxy::Proxy> {
         self.config().proxy.as_ref()
     }
@@ -93,6 +97,15 @@ impl ScoopContext for Global {
         self.user_context.logging_dir()
     }
 
+    /// Get the path to all users' Start Menu "Scoop Apps" folder
+    fn shortcuts_path(&self) -> PathBuf {
+        WindowsPath::CommonAppData
+            .into_path()
+            .or_else(|| std::env::var("ProgramData").ok().map(Into::into))
+            .expect("either windows defined common app data or env var `ProgramData`")
+            .join(r"Microsoft\Windows\Start Menu\Programs\Scoop Apps")
+    }
+
     fn open_repo(&self) -> Option<git::Result<git::Repo>> {
         self.user_context.open_repo()
     }
@@ -0,0 +1,110 @@
+//! A context rooted in a temporary directory, for use in tests
+
+use std::path::{Path, PathBuf};
+
+use crate::{
+    config,
+    contexts::{Error, ScoopContext},
+    git,
+};
+
+#[derive(Debug, Clone)]
+/// A context rooted in a temporary directory
+pub struct TestContext {
+    config: config::Scoop,
+    path: PathBuf,
+}
+
+impl TestContext {
+    /// Construct a new context in a fresh temporary directory
+    ///
+    /// The directory is unique to the given name and the current process
+    pub fn new(name: &str) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("sprinkles-ctx-{name}-{}", std::process::id()));
+
+        _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).expect("created test context directory");
+
+        let config = serde_json::from_value(serde_json::json!({
+            "root_path": path,
+            "global_path": path.join("global"),
+        }))
+        .expect("valid config");
+
+        Self { config, path }
+    }
+
+    /// Write a manifest to `buckets/<bucket>/bucket/<name>.json`
+    pub fn write_bucket_manifest(&self, bucket: &str, name: &str, manifest: &serde_json::Value) {
+        let bucket = self.buckets_path().join(bucket).join("bucket");
+        std::fs::create_dir_all(&bucket).expect("created bucket directory");
+
+        std::fs::write(bucket.join(format!("{name}.json")), manifest.to_string())
+            .expect("wrote bucket manifest");
+    }
+
+    /// Write an installed app's manifest to `apps/<name>/current/manifest.json`
+    ///
+    /// Returns the app's `current` directory
+    pub fn write_installed_manifest(&self, name: &str, manifest: &serde_json::Value) -> PathBuf {
+        let current = self.apps_path().join(name).join("current");
+        std::fs::create_dir_all(&current).expect("created app directory");
+
+        std::fs::write(current.join("manifest.json"), manifest.to_string())
+            .expect("wrote installed manifest");
+
+        current
+    }
+
+    /// Remove the context's directory
+    pub fn cleanup(self) {
+        _ = std::fs::remove_dir_all(self.path);
+    }
+}
+
+impl ScoopContext for TestContext {
+    type Config = config::Scoop;
+
+    const APP_NAME: &'static str = "scoop";
+    const CONTEXT_NAME: &'static str = "test";
+
+    fn config(&self) -> &config::Scoop {
+        &self.config
+    }
+
+    fn config_mut(&mut self) -> &mut config::Scoop {
+        &mut self.config
+    }
+
+    fn symlinks_enabled(&self) -> bool {
+        !self.config.no_junction
+    }
+
+    fn proxy(&self) -> Option<&crate::proxy::Proxy> {
+        self.config.proxy.as_ref()
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn logging_dir(&self) -> std::io::Result<PathBuf> {
+        Ok(self.sub_path("logs"))
+    }
+
+    fn shortcuts_path(&self) -> PathBuf {
+        self.path
+            .join("Start Menu")
+            .join("Programs")
+            .join("Scoop Apps")
+    }
+
+    fn open_repo(&self) -> Option<git::Result<git::Repo>> {
+        Some(git::Repo::from_path(self.context_app_path()))
+    }
+
+    async fn outdated(&self) -> Result<bool, Error> {
+        Ok(false)
+    }
+}
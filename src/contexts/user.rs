@@ -124,6 +124,15 @@ impl super::ScoopContext for User {
         Ok(logs_path)
     }
 
+    /// Get the path to the user's Start Menu "Scoop Apps" folder
+    fn shortcuts_path(&self) -> PathBuf {
+        WindowsPath::AppData
+            .into_path()
+            .or_else(|| std::env::var("AppData").ok().map(Into::into))
+            .expect("either windows defined app data or env var `AppData`")
+            .join(r"Microsoft\Windows\Start Menu\Programs\Scoop Apps")
+    }
+
     /// Open Scoop app repository
     ///
     /// # Errors
@@ -134,6 +143,11 @@ impl super::ScoopContext for User {
 
     /// Check if Scoop is outdated
     ///
+    /// Scoop is considered outdated if its repository does not match the configured
+    /// repository or branch, or if there are newer commits in the remote.
+    ///
+    /// This does not modify the repository. See [`super::self_update`] to update Scoop.
+    ///
     /// # Errors
     /// - The Scoop app could not be opened as a repository
     /// - The Scoop app could not be checked for updates
@@ -141,12 +155,16 @@ impl super::ScoopContext for User {
         let config = self.config();
         let scoop_repo = self.open_repo().expect("scoop repo")?;
 
+        if !git::urls_match(&scoop_repo.origin_url()?, config.scoop_repo.url()) {
+            debug!("Scoop repo does not match {}", config.scoop_repo);
+            return Ok(true);
+        }
+
         let current_branch = scoop_repo.current_branch()?;
         let scoop_config_branch = config.scoop_branch.name();
 
         if current_branch != scoop_config_branch {
-            scoop_repo.checkout(scoop_config_branch)?;
-            debug!("Switched to branch {}", scoop_config_branch);
+            debug!("Scoop is not on branch {}", scoop_config_branch);
             return Ok(true);
         }
 
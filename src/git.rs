@@ -14,7 +14,7 @@ use gix::{
 
 use crate::{buckets::Bucket, contexts::ScoopContext};
 
-use pull::ProgressCallback;
+pub use pull::ProgressCallback;
 
 pub mod clone;
 pub mod errors;
@@ -59,11 +59,68 @@ pub enum Error {
     MissingHead,
     #[error("Invalid utf8")]
     NonUtf8,
+    #[error("Cloning repository: {0}")]
+    Clone(#[from] clone::Error),
+    #[error("IO error: {0}")]
+    IO(#[from] std::io::Error),
 }
 
 /// Repo result type
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
+/// Check if two remote urls point to the same repository
+///
+/// Trailing slashes, a trailing `.git` and casing are ignored in the comparison.
+pub(crate) fn urls_match(a: &str, b: &str) -> bool {
+    fn normalize(url: &str) -> String {
+        let url = url.trim().trim_end_matches('/');
+        let url = url.strip_suffix(".git").unwrap_or(url);
+
+        url.trim_end_matches('/').to_lowercase()
+    }
+
+    normalize(a) == normalize(b)
+}
+
+/// Replace the repository at the given path with a fresh clone of `url`
+///
+/// The new clone is made in a sibling `.{name}.{staging}` directory, and the existing
+/// repository is moved to `.{name}.old` and only removed once the new clone is in place.
+/// Stale staging and `.old` directories left behind by an interrupted run are removed first.
+///
+/// # Errors
+/// - The path has no parent or file name
+/// - The stale directories could not be removed
+/// - The repository could not be cloned
+/// - The directories could not be swapped
+pub fn reclone(path: &Path, url: &str, staging: &str) -> Result<()> {
+    let parent = path.parent().ok_or(Error::GitParent)?;
+    let name = path.file_name().ok_or(Error::GitParent)?.to_string_lossy();
+    let new_path = parent.join(format!(".{name}.{staging}"));
+    let old_path = parent.join(format!(".{name}.old"));
+
+    for stale in [&new_path, &old_path] {
+        if stale.exists() {
+            std::fs::remove_dir_all(stale)?;
+        }
+    }
+
+    if let Err(e) = clone::clone(url, &new_path, gix::progress::Discard) {
+        _ = std::fs::remove_dir_all(&new_path);
+        return Err(e.into());
+    }
+
+    std::fs::rename(path, &old_path)?;
+    if let Err(e) = std::fs::rename(&new_path, path) {
+        // Put the original repository back in place
+        std::fs::rename(&old_path, path)?;
+        return Err(e.into());
+    }
+    std::fs::remove_dir_all(&old_path)?;
+
+    Ok(())
+}
+
 /// A git repository
 pub struct Repo {
     git2: git2::Repository,
@@ -131,6 +188,20 @@ impl Repo {
         self.find_remote("origin")
     }
 
+    /// Get the fetch url of the origin remote
+    ///
+    /// # Errors
+    /// - No remote named "origin"
+    /// - The remote's url is not utf8
+    /// - The remote's url is not set
+    pub fn origin_url(&self) -> Result<String> {
+        self.origin()
+            .ok_or(Error::MissingRemote("origin".to_string()))?
+            .url(gix::remote::Direction::Fetch)
+            .map(std::string::ToString::to_string)
+            .ok_or(Error::NonUtf8)
+    }
+
     /// Checkout to another branch
     ///
     /// # Errors
@@ -152,6 +223,43 @@ impl Repo {
         Ok(())
     }
 
+    /// Fetch the given branch from origin and check it out
+    ///
+    /// If the branch does not exist locally, it will be created from the remote branch.
+    ///
+    /// # Errors
+    /// - No remote named "origin"
+    /// - The branch does not exist in the remote
+    /// - Git error
+    pub fn switch_branch(&self, ctx: &impl ScoopContext, branch: &str) -> Result<()> {
+        let mut remote = self.git2.find_remote("origin")?;
+        let mut fetch_options = options::fetch::FetchOptions::new(ctx);
+        remote.fetch(
+            &[&format!(
+                "+refs/heads/{branch}:refs/remotes/origin/{branch}"
+            )],
+            Some(fetch_options.as_git2_mut()),
+            None,
+        )?;
+
+        if self
+            .git2
+            .find_branch(branch, git2::BranchType::Local)
+            .is_err()
+        {
+            let commit = self
+                .git2
+                .find_branch(&format!("origin/{branch}"), git2::BranchType::Remote)?
+                .get()
+                .peel_to_commit()?;
+
+            let mut local = self.git2.branch(branch, &commit, false)?;
+            local.set_upstream(Some(&format!("origin/{branch}")))?;
+        }
+
+        self.checkout(branch)
+    }
+
     /// Get the current branch
     ///
     /// # Errors
@@ -22,6 +22,9 @@ use git2::Repository;
 
 use crate::contexts::ScoopContext;
 
+/// Callback for reporting fetch progress
+///
+/// The second argument is `true` once the fetch has completed
 pub type ProgressCallback<'a> = &'a dyn Fn(git2::Progress<'_>, bool) -> bool;
 
 fn do_fetch<'a>(
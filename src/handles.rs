@@ -1,4 +1,6 @@
 //! Handles for various different Scoop things
 
+pub mod environment;
 pub mod packages;
 pub mod shim;
+pub mod shortcut;
@@ -0,0 +1,717 @@
+//! Environment variable handles
+//!
+//! Applies and reverts the `env_add_path` and `env_set` fields of manifests,
+//! tracking which app owns each change so that uninstalling an app only removes what it added
+
+use std::{
+    collections::BTreeMap,
+    path::{Component, Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config, contexts::ScoopContext, packages::Manifest, Architecture};
+
+pub mod file;
+#[cfg(windows)]
+pub mod registry;
+
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+/// Environment errors
+pub enum Error {
+    #[error("Reading or writing the environment: {0}")]
+    IOError(#[from] std::io::Error),
+    #[error("Parsing the environment store: {0}")]
+    JSONError(#[from] serde_json::Error),
+    #[error("{} is not in the app directory", .0.display())]
+    OutsideAppDir(PathBuf),
+}
+
+/// Environment result type
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// A store of persistent environment variables
+///
+/// Values are stored unexpanded, so references to other variables (i.e `%SCOOP_PATH%`) are kept as is
+pub trait Backend {
+    /// Get the value of a variable
+    ///
+    /// # Errors
+    /// - Reading the variable failed
+    fn get(&self, name: &str) -> Result<Option<String>>;
+
+    /// Set the value of a variable
+    ///
+    /// # Errors
+    /// - Writing the variable failed
+    fn set(&self, name: &str, value: &str) -> Result<()>;
+
+    /// Remove a variable
+    ///
+    /// Removing a variable that does not exist is not an error
+    ///
+    /// # Errors
+    /// - Removing the variable failed
+    fn remove(&self, name: &str) -> Result<()>;
+}
+
+impl<B: Backend + ?Sized> Backend for std::sync::Arc<B> {
+    fn get(&self, name: &str) -> Result<Option<String>> {
+        (**self).get(name)
+    }
+
+    fn set(&self, name: &str, value: &str) -> Result<()> {
+        (**self).set(name, value)
+    }
+
+    fn remove(&self, name: &str) -> Result<()> {
+        (**self).remove(name)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+/// The environment changes owned by an app
+pub struct OwnedEnvironment {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    /// The `PATH` entries added by the app, by the uppercased variable they were added to
+    pub paths: BTreeMap<String, Vec<String>>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    /// The variables set by the app, by their uppercased name
+    pub variables: BTreeMap<String, OwnedVariable>,
+}
+
+impl OwnedEnvironment {
+    fn is_empty(&self) -> bool {
+        self.paths.is_empty() && self.variables.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// A variable set by an app
+pub struct OwnedVariable {
+    /// The value the app set
+    pub value: String,
+    /// The value before any app set it, restored when the app is uninstalled
+    pub previous: Option<String>,
+}
+
+/// Normalise a variable name for the ledger, as variable names are case-insensitive on Windows
+fn ledger_key(name: &str) -> String {
+    name.to_ascii_uppercase()
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Ledger {
+    #[serde(default)]
+    apps: BTreeMap<String, OwnedEnvironment>,
+}
+
+impl Ledger {
+    fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let mut ledger: Self = serde_json::from_slice(&std::fs::read(path)?)?;
+        ledger.normalize();
+
+        Ok(ledger)
+    }
+
+    /// Merge variables recorded under different casings into one entry
+    fn normalize(&mut self) {
+        for owned in self.apps.values_mut() {
+            let mut paths = BTreeMap::<String, Vec<String>>::new();
+            for (variable, entries) in std::mem::take(&mut owned.paths) {
+                let merged = paths.entry(ledger_key(&variable)).or_default();
+                for entry in entries {
+                    if !merged.iter().any(|existing| path_eq(existing, &entry)) {
+                        merged.push(entry);
+                    }
+                }
+            }
+            owned.paths = paths;
+
+            let mut variables = BTreeMap::new();
+            for (name, variable) in std::mem::take(&mut owned.variables) {
+                variables.entry(ledger_key(&name)).or_insert(variable);
+            }
+            owned.variables = variables;
+        }
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+
+    fn path_owned(&self, variable: &str, entry: &str) -> bool {
+        self.apps.values().any(|owned| {
+            owned
+                .paths
+                .get(&ledger_key(variable))
+                .is_some_and(|entries| entries.iter().any(|owned| path_eq(owned, entry)))
+        })
+    }
+}
+
+/// Compare `PATH` entries as Windows does, ignoring case and trailing separators
+fn path_eq(a: &str, b: &str) -> bool {
+    a.trim_end_matches(['\\', '/'])
+        .eq_ignore_ascii_case(b.trim_end_matches(['\\', '/']))
+}
+
+fn split_path(value: Option<&str>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .split(';')
+        .filter(|entry| !entry.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+/// Manages the environment variables of a context's apps
+///
+/// Every change is recorded in a store of the environment changes each app owns,
+/// keyed by the uppercased variable name, as variable names are case-insensitive on Windows.
+/// Changes are idempotent, and reverting an app only removes what it added:
+/// - `PATH` entries that already existed, and were not added by an app, are left in place
+/// - `PATH` entries added by multiple apps are removed once no app owns them
+/// - Variables are restored to their value before any app set them, unless they have been changed since
+pub struct EnvironmentManager<B> {
+    backend: B,
+    path_variable: String,
+    store_path: PathBuf,
+}
+
+impl<B: Backend> EnvironmentManager<B> {
+    #[must_use]
+    /// Create an environment manager for the given context
+    ///
+    /// `PATH` entries are added to the variable given by [`config::Scoop::path_variable`],
+    /// and ownership is stored in `environment.json` in the context's directory
+    pub fn new(ctx: &impl ScoopContext<Config = config::Scoop>, backend: B) -> Self {
+        Self::from_parts(
+            backend,
+            ctx.config().path_variable(),
+            ctx.path().join("environment.json"),
+        )
+    }
+
+    #[must_use]
+    /// Create an environment manager with the given `PATH` variable and ownership store
+    pub fn from_parts(
+        backend: B,
+        path_variable: impl Into<String>,
+        store_path: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            backend,
+            path_variable: path_variable.into(),
+            store_path: store_path.into(),
+        }
+    }
+
+    #[must_use]
+    /// Get the backend
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    #[must_use]
+    /// Get the variable that `PATH` entries are added to
+    pub fn path_variable(&self) -> &str {
+        &self.path_variable
+    }
+
+    #[must_use]
+    /// Check if `PATH` entries are added to an isolated variable, rather than `PATH`
+    pub fn is_isolated(&self) -> bool {
+        !self.path_variable.eq_ignore_ascii_case("PATH")
+    }
+
+    /// Get the environment changes owned by an app
+    ///
+    /// # Errors
+    /// - Reading the ownership store failed
+    pub fn owned(&self, app: &str) -> Result<OwnedEnvironment> {
+        Ok(Ledger::load(&self.store_path)?
+            .apps
+            .remove(app)
+            .unwrap_or_default())
+    }
+
+    /// Add entries to the front of the `PATH` variable on behalf of an app
+    ///
+    /// In isolated mode, `PATH` is made to reference the isolated variable (i.e `%SCOOP_PATH%`).
+    /// That reference is shared by all apps, and is never removed.
+    ///
+    /// # Errors
+    /// - Reading or writing the environment failed
+    /// - Reading or writing the ownership store failed
+    pub fn add_path(&self, app: &str, entries: &[impl AsRef<str>]) -> Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        if self.is_isolated() {
+            let reference = format!("%{}%", self.path_variable);
+            self.prepend_path("PATH", &[reference])?;
+        }
+
+        let mut ledger = Ledger::load(&self.store_path)?;
+
+        let current = split_path(self.backend.get(&self.path_variable)?.as_deref());
+        let mut added = vec![];
+        let mut owned = vec![];
+
+        for entry in entries.iter().map(AsRef::as_ref) {
+            let exists = current.iter().any(|existing| path_eq(existing, entry));
+
+            // Entries that exist without an owner were added by the user, so are left to them
+            if !exists || ledger.path_owned(&self.path_variable, entry) {
+                owned.push(entry.to_string());
+            }
+            if !exists {
+                added.push(entry.to_string());
+            }
+        }
+
+        self.prepend_path(&self.path_variable, &added)?;
+
+        if owned.is_empty() {
+            return Ok(());
+        }
+
+        let app_paths = ledger
+            .apps
+            .entry(app.to_string())
+            .or_default()
+            .paths
+            .entry(ledger_key(&self.path_variable))
+            .or_default();
+        for entry in owned {
+            if !app_paths.iter().any(|existing| path_eq(existing, &entry)) {
+                app_paths.push(entry);
+            }
+        }
+
+        ledger.save(&self.store_path)
+    }
+
+    fn prepend_path(&self, variable: &str, entries: &[String]) -> Result<()> {
+        let current = split_path(self.backend.get(variable)?.as_deref());
+
+        let missing = entries
+            .iter()
+            .filter(|entry| !current.iter().any(|existing| path_eq(existing, entry)))
+            .cloned()
+            .collect::<Vec<_>>();
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        self.backend
+            .set(variable, &[missing, current].concat().join(";"))
+    }
+
+    /// Set a variable on behalf of an app
+    ///
+    /// If another app set the variable before, this app takes ownership of it,
+    /// and the value from before either app set it is kept for restoring
+    ///
+    /// # Errors
+    /// - Reading or writing the environment failed
+    /// - Reading or writing the ownership store failed
+    pub fn set_variable(&self, app: &str, name: &str, value: &str) -> Result<()> {
+        let mut ledger = Ledger::load(&self.store_path)?;
+
+        let previous = match ledger
+            .apps
+            .values_mut()
+            .find_map(|owned| owned.variables.remove(&ledger_key(name)))
+        {
+            Some(owned) => owned.previous,
+            None => self.backend.get(name)?,
+        };
+
+        self.backend.set(name, value)?;
+
+        ledger
+            .apps
+            .entry(app.to_string())
+            .or_default()
+            .variables
+            .insert(
+                ledger_key(name),
+                OwnedVariable {
+                    value: value.to_string(),
+                    previous,
+                },
+            );
+        ledger.apps.retain(|_, owned| !owned.is_empty());
+
+        ledger.save(&self.store_path)
+    }
+
+    /// Apply a manifest's `env_add_path` and `env_set` fields for the given architecture
+    ///
+    /// `PATH` entries are resolved relative to the app directory, and must be inside it.
+    /// Install-time variables (i.e `$dir`) should already be substituted,
+    /// see [`InstallVariables`](crate::packages::variables::InstallVariables).
+    ///
+    /// # Errors
+    /// - A `PATH` entry is outside the app directory
+    /// - Reading or writing the environment failed
+    /// - Reading or writing the ownership store failed
+    pub fn apply(
+        &self,
+        app: &str,
+        manifest: &Manifest,
+        arch: Architecture,
+        app_dir: impl AsRef<Path>,
+    ) -> Result<()> {
+        let config = manifest.install_config(arch);
+        let app_dir = app_dir.as_ref();
+
+        if let Some(env_add_path) = config.env_add_path {
+            let entries = env_add_path
+                .into_iter()
+                .filter(|entry| !entry.is_empty())
+                .map(|entry| resolve_path(app_dir, &entry))
+                .collect::<Result<Vec<_>>>()?;
+
+            self.add_path(app, &entries)?;
+        }
+
+        if let Some(env_set) = config.env_set {
+            let mut variables = env_set
+                .into_iter()
+                .filter_map(|(name, value)| match value? {
+                    serde_json::Value::Null => None,
+                    serde_json::Value::String(value) => Some((name, value)),
+                    value => Some((name, value.to_string())),
+                })
+                .collect::<Vec<_>>();
+            variables.sort();
+
+            for (name, value) in variables {
+                self.set_variable(app, &name, &value)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Revert all environment changes owned by an app
+    ///
+    /// # Errors
+    /// - Reading or writing the environment failed
+    /// - Reading or writing the ownership store failed
+    pub fn revert(&self, app: &str) -> Result<()> {
+        let mut ledger = Ledger::load(&self.store_path)?;
+        let Some(owned) = ledger.apps.remove(app) else {
+            return Ok(());
+        };
+
+        for (variable, entries) in owned.paths {
+            let removed = entries
+                .into_iter()
+                .filter(|entry| !ledger.path_owned(&variable, entry))
+                .collect::<Vec<_>>();
+            if removed.is_empty() {
+                continue;
+            }
+
+            let current = split_path(self.backend.get(&variable)?.as_deref());
+            let remaining = current
+                .into_iter()
+                .filter(|existing| !removed.iter().any(|entry| path_eq(existing, entry)))
+                .collect::<Vec<_>>();
+
+            if remaining.is_empty() {
+                self.backend.remove(&variable)?;
+            } else {
+                self.backend.set(&variable, &remaining.join(";"))?;
+            }
+        }
+
+        for (name, variable) in owned.variables {
+            // Leave variables that have been changed since the app set them
+            if self.backend.get(&name)?.as_deref() != Some(variable.value.as_str()) {
+                continue;
+            }
+
+            match variable.previous {
+                Some(previous) => self.backend.set(&name, &previous)?,
+                None => self.backend.remove(&name)?,
+            }
+        }
+
+        ledger.save(&self.store_path)
+    }
+}
+
+/// Resolve a `PATH` entry relative to the app directory, making sure it stays inside it
+///
+/// Absolute entries (i.e after substituting `$dir`) are allowed if they are inside the app directory
+fn resolve_path(app_dir: &Path, entry: &str) -> Result<String> {
+    let normalize = |path: &str| path.replace('\\', "/").trim_end_matches('/').to_string();
+
+    let dir = normalize(&app_dir.display().to_string());
+    let mut relative = normalize(entry);
+    let inside_dir = relative
+        .get(..dir.len())
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(&dir))
+        && matches!(relative.as_bytes().get(dir.len()), None | Some(b'/'));
+    if inside_dir {
+        relative = relative[dir.len()..].trim_start_matches('/').to_string();
+    }
+
+    let mut path = app_dir.to_path_buf();
+
+    for component in Path::new(&relative).components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::ParentDir if path != app_dir => {
+                path.pop();
+            }
+            Component::CurDir => {}
+            _ => return Err(Error::OutsideAppDir(app_dir.join(entry))),
+        }
+    }
+
+    Ok(path
+        .display()
+        .to_string()
+        .trim_end_matches(['\\', '/'])
+        .to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{file::FileBackend, *};
+    use crate::contexts::testing::TestContext;
+
+    fn manager(ctx: &TestContext, path_variable: &str) -> EnvironmentManager<FileBackend> {
+        EnvironmentManager::from_parts(
+            FileBackend::new(ctx.path().join("env.json")),
+            path_variable,
+            ctx.path().join("environment.json"),
+        )
+    }
+
+    #[test]
+    fn test_path_ownership() {
+        let ctx = TestContext::new("env-path");
+        let env = manager(&ctx, "PATH");
+        let backend = env.backend();
+
+        backend.set("PATH", r"C:\Windows;C:\Tools\").unwrap();
+
+        env.add_path("app", &[r"C:\Scoop\apps\app\current", r"C:\Tools"])
+            .unwrap();
+        env.add_path("other", &[r"C:\Scoop\apps\app\current"])
+            .unwrap();
+        // Adding again is idempotent
+        env.add_path("app", &[r"C:\Scoop\apps\app\current"])
+            .unwrap();
+
+        assert_eq!(
+            backend.get("PATH").unwrap().as_deref(),
+            Some(r"C:\Scoop\apps\app\current;C:\Windows;C:\Tools\")
+        );
+        assert_eq!(
+            env.owned("app").unwrap().paths["PATH"],
+            [r"C:\Scoop\apps\app\current"]
+        );
+
+        // Shared entries stay until no app owns them, and the user's entries are never removed
+        env.revert("app").unwrap();
+        assert_eq!(
+            backend.get("PATH").unwrap().as_deref(),
+            Some(r"C:\Scoop\apps\app\current;C:\Windows;C:\Tools\")
+        );
+
+        env.revert("other").unwrap();
+        assert_eq!(
+            backend.get("PATH").unwrap().as_deref(),
+            Some(r"C:\Windows;C:\Tools\")
+        );
+        assert_eq!(env.owned("other").unwrap(), OwnedEnvironment::default());
+
+        ctx.cleanup();
+    }
+
+    #[test]
+    fn test_isolated_path() {
+        let ctx = TestContext::new("env-isolated");
+        let env = manager(&ctx, "SCOOP_PATH");
+        let backend = env.backend();
+
+        backend.set("PATH", r"C:\Windows").unwrap();
+
+        env.add_path("app", &[r"C:\Scoop\apps\app\current"])
+            .unwrap();
+
+        assert_eq!(
+            backend.get("PATH").unwrap().as_deref(),
+            Some(r"%SCOOP_PATH%;C:\Windows")
+        );
+        assert_eq!(
+            backend.get("SCOOP_PATH").unwrap().as_deref(),
+            Some(r"C:\Scoop\apps\app\current")
+        );
+
+        env.revert("app").unwrap();
+
+        assert_eq!(backend.get("SCOOP_PATH").unwrap(), None);
+        assert_eq!(
+            backend.get("PATH").unwrap().as_deref(),
+            Some(r"%SCOOP_PATH%;C:\Windows")
+        );
+
+        ctx.cleanup();
+    }
+
+    #[test]
+    fn test_apply_manifest() {
+        let ctx = TestContext::new("env-apply");
+        let env = manager(&ctx, "PATH");
+        let backend = env.backend();
+
+        backend.set("JAVA_HOME", r"C:\Java").unwrap();
+
+        let manifest: Manifest = serde_json::from_value(json!({
+            "version": "1.0.0",
+            "env_add_path": ["bin", ".\\tools\\..\\scripts\\", "C:\\Scoop\\apps\\jdk\\current\\lib", ""],
+            "env_set": { "JAVA_HOME": "C:\\Scoop\\apps\\jdk\\current", "JDK_DEBUG": 1, "UNSET": null },
+        }))
+        .unwrap();
+        let app_dir = Path::new("C:/Scoop/apps/jdk/current");
+
+        env.apply("jdk", &manifest, Architecture::X64, app_dir)
+            .unwrap();
+        env.apply("jdk", &manifest, Architecture::X64, app_dir)
+            .unwrap();
+
+        assert_eq!(
+            split_path(backend.get("PATH").unwrap().as_deref()),
+            [
+                app_dir.join("bin").display().to_string(),
+                app_dir.join("scripts").display().to_string(),
+                app_dir.join("lib").display().to_string(),
+            ]
+        );
+        assert_eq!(
+            backend.get("JAVA_HOME").unwrap().as_deref(),
+            Some(r"C:\Scoop\apps\jdk\current")
+        );
+        assert_eq!(backend.get("JDK_DEBUG").unwrap().as_deref(), Some("1"));
+        assert_eq!(backend.get("UNSET").unwrap(), None);
+
+        // Variables changed since the app set them are left in place
+        backend.set("JDK_DEBUG", "0").unwrap();
+        env.revert("jdk").unwrap();
+
+        assert_eq!(backend.get("PATH").unwrap(), None);
+        assert_eq!(
+            backend.get("JAVA_HOME").unwrap().as_deref(),
+            Some(r"C:\Java")
+        );
+        assert_eq!(backend.get("JDK_DEBUG").unwrap().as_deref(), Some("0"));
+
+        let outside: Manifest = serde_json::from_value(json!({
+            "version": "1.0.0",
+            "env_add_path": "..\\other",
+        }))
+        .unwrap();
+        assert!(matches!(
+            env.apply("jdk", &outside, Architecture::X64, app_dir),
+            Err(Error::OutsideAppDir(_))
+        ));
+
+        ctx.cleanup();
+    }
+
+    #[test]
+    fn test_case_insensitive_names() {
+        let ctx = TestContext::new("env-case");
+        let backend = FileBackend::new(ctx.path().join("env.json"));
+
+        backend.set("Foo_HOME", "user").unwrap();
+
+        let env = manager(&ctx, "PATH");
+        env.set_variable("app", "Foo_HOME", "app").unwrap();
+        env.set_variable("other", "FOO_HOME", "other").unwrap();
+
+        // The second app takes over the variable, keeping the user's value to restore
+        assert_eq!(env.owned("app").unwrap(), OwnedEnvironment::default());
+        assert_eq!(
+            env.owned("other").unwrap().variables["FOO_HOME"],
+            OwnedVariable {
+                value: "other".to_string(),
+                previous: Some("user".to_string()),
+            }
+        );
+
+        env.revert("other").unwrap();
+        assert_eq!(backend.get("FOO_HOME").unwrap().as_deref(), Some("user"));
+
+        // `Path` and `PATH` are the same variable, so shared entries stay until no app owns them
+        manager(&ctx, "Path")
+            .add_path("app", &[r"C:\Scoop\apps\app\current"])
+            .unwrap();
+        env.add_path("other", &[r"C:\Scoop\apps\app\current"])
+            .unwrap();
+        assert_eq!(
+            env.owned("app").unwrap().paths["PATH"],
+            [r"C:\Scoop\apps\app\current"]
+        );
+
+        env.revert("app").unwrap();
+        assert_eq!(
+            backend.get("PATH").unwrap().as_deref(),
+            Some(r"C:\Scoop\apps\app\current")
+        );
+        env.revert("other").unwrap();
+        assert_eq!(backend.get("PATH").unwrap(), None);
+
+        ctx.cleanup();
+    }
+
+    #[test]
+    fn test_normalize_ledger() {
+        let ctx = TestContext::new("env-normalize");
+        let env = manager(&ctx, "PATH");
+
+        std::fs::write(
+            ctx.path().join("environment.json"),
+            serde_json::to_string(&json!({
+                "apps": {
+                    "app": {
+                        "paths": { "Path": ["C:\\a"], "PATH": ["C:\\A\\", "C:\\b"] },
+                        "variables": { "Foo_HOME": { "value": "app", "previous": null } },
+                    },
+                },
+            }))
+            .unwrap(),
+        )
+        .unwrap();
+
+        let owned = env.owned("app").unwrap();
+        assert_eq!(owned.paths.keys().collect::<Vec<_>>(), ["PATH"]);
+        assert_eq!(owned.paths["PATH"].len(), 2);
+        assert_eq!(owned.variables.keys().collect::<Vec<_>>(), ["FOO_HOME"]);
+
+        ctx.cleanup();
+    }
+}
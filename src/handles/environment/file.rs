@@ -0,0 +1,83 @@
+//! A file-backed environment store
+//!
+//! Useful for testing, and on platforms without a persistent environment store
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use super::{Backend, Result};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// An environment store kept in a JSON file, mapping variable names to values
+///
+/// Variable names are case-insensitive, as they are on Windows
+pub struct FileBackend {
+    path: PathBuf,
+}
+
+impl FileBackend {
+    #[must_use]
+    /// Create a file backend for the given file
+    ///
+    /// The file is created when the first variable is set
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    #[must_use]
+    /// Get the path to the file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn load(&self) -> Result<BTreeMap<String, String>> {
+        if !self.path.exists() {
+            return Ok(BTreeMap::new());
+        }
+
+        Ok(serde_json::from_slice(&std::fs::read(&self.path)?)?)
+    }
+
+    fn save(&self, variables: &BTreeMap<String, String>) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(variables)?)?;
+
+        Ok(())
+    }
+}
+
+fn find<'a>(variables: &'a BTreeMap<String, String>, name: &str) -> Option<&'a String> {
+    variables.keys().find(|key| key.eq_ignore_ascii_case(name))
+}
+
+impl Backend for FileBackend {
+    fn get(&self, name: &str) -> Result<Option<String>> {
+        let variables = self.load()?;
+
+        Ok(find(&variables, name).map(|key| variables[key].clone()))
+    }
+
+    fn set(&self, name: &str, value: &str) -> Result<()> {
+        let mut variables = self.load()?;
+
+        let key = find(&variables, name).cloned().unwrap_or(name.to_string());
+        variables.insert(key, value.to_string());
+
+        self.save(&variables)
+    }
+
+    fn remove(&self, name: &str) -> Result<()> {
+        let mut variables = self.load()?;
+
+        if let Some(key) = find(&variables, name).cloned() {
+            variables.remove(&key);
+            self.save(&variables)?;
+        }
+
+        Ok(())
+    }
+}
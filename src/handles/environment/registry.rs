@@ -0,0 +1,122 @@
+//! The Windows registry environment store
+
+use winreg::{
+    enums::{RegType, HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE, KEY_READ, KEY_WRITE},
+    RegKey, RegValue,
+};
+
+use crate::contexts::ScoopContext;
+
+use super::{Backend, Result};
+
+const USER_ENVIRONMENT: &str = "Environment";
+const MACHINE_ENVIRONMENT: &str = r"SYSTEM\CurrentControlSet\Control\Session Manager\Environment";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The user or machine environment in the Windows registry
+///
+/// Changes are broadcast to running applications (i.e Explorer), so new processes pick them up
+pub struct Registry {
+    global: bool,
+}
+
+impl Registry {
+    #[must_use]
+    /// The current user's environment
+    pub fn user() -> Self {
+        Self { global: false }
+    }
+
+    #[must_use]
+    /// The machine environment
+    ///
+    /// Writing to the machine environment requires administrator privileges
+    pub fn machine() -> Self {
+        Self { global: true }
+    }
+
+    #[must_use]
+    /// The environment for the given context
+    pub fn for_context(ctx: &impl ScoopContext) -> Self {
+        Self {
+            global: ctx.is_global(),
+        }
+    }
+
+    fn key(&self) -> std::io::Result<RegKey> {
+        if self.global {
+            RegKey::predef(HKEY_LOCAL_MACHINE)
+                .open_subkey_with_flags(MACHINE_ENVIRONMENT, KEY_READ | KEY_WRITE)
+        } else {
+            RegKey::predef(HKEY_CURRENT_USER)
+                .open_subkey_with_flags(USER_ENVIRONMENT, KEY_READ | KEY_WRITE)
+        }
+    }
+}
+
+/// Notify running applications that the environment changed
+fn broadcast_change() {
+    use windows::{
+        core::w,
+        Win32::{
+            Foundation::{LPARAM, WPARAM},
+            UI::WindowsAndMessaging::{
+                SendMessageTimeoutW, HWND_BROADCAST, SMTO_ABORTIFHUNG, WM_SETTINGCHANGE,
+            },
+        },
+    };
+
+    // SAFETY: The message only borrows the static "Environment" string for the duration of the call
+    unsafe {
+        SendMessageTimeoutW(
+            HWND_BROADCAST,
+            WM_SETTINGCHANGE,
+            WPARAM(0),
+            LPARAM(w!("Environment").as_ptr() as isize),
+            SMTO_ABORTIFHUNG,
+            5000,
+            None,
+        );
+    }
+}
+
+impl Backend for Registry {
+    fn get(&self, name: &str) -> Result<Option<String>> {
+        match self.key()?.get_value::<String, _>(name) {
+            Ok(value) => Ok(Some(value)),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    fn set(&self, name: &str, value: &str) -> Result<()> {
+        // Values referencing other variables must be expandable, as Scoop does for `%SCOOP_PATH%`
+        let vtype = if value.contains('%') {
+            RegType::REG_EXPAND_SZ
+        } else {
+            RegType::REG_SZ
+        };
+        let bytes = value
+            .encode_utf16()
+            .chain([0])
+            .flat_map(u16::to_le_bytes)
+            .collect();
+
+        self.key()?
+            .set_raw_value(name, &RegValue { bytes, vtype })?;
+        broadcast_change();
+
+        Ok(())
+    }
+
+    fn remove(&self, name: &str) -> Result<()> {
+        match self.key()?.delete_value(name) {
+            Ok(()) => {}
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(error) => return Err(error.into()),
+        }
+        broadcast_change();
+
+        Ok(())
+    }
+}
@@ -1,13 +1,17 @@
 //! Package handles
 
-use std::{path::PathBuf, rc::Rc};
+use std::{
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
 use crate::{
     contexts::ScoopContext,
     packages::{
         reference::{self, package},
-        CreateManifest, InstallManifest, Manifest,
+        CreateManifest, InstallManifest, Manifest, NamedManifest,
     },
+    scripts::prelude::Prelude,
 };
 
 #[derive(Debug, thiserror::Error)]
@@ -36,7 +40,7 @@ pub type Result<T, E = Error> = std::result::Result<T, E>;
 pub struct PackageHandle<'a, C> {
     ctx: &'a C,
     reference: package::Reference,
-    remote_manifest: Manifest,
+    remote_manifest: NamedManifest,
     path: PathBuf,
 }
 
@@ -75,6 +79,24 @@ impl<'a, C: ScoopContext> PackageHandle<'a, C> {
         })
     }
 
+    /// Create a package handle from the package's manifest, whether or not it is installed
+    ///
+    /// This is used while the package is being installed, before `current` is linked
+    pub fn from_manifest(
+        ctx: &'a C,
+        reference: package::Reference,
+        manifest: NamedManifest,
+    ) -> Self {
+        let path = ctx.apps_path().join(manifest.name());
+
+        Self {
+            ctx,
+            reference,
+            remote_manifest: manifest,
+            path,
+        }
+    }
+
     #[must_use]
     /// Get the package's install path
     ///
@@ -92,7 +114,7 @@ impl<'a, C: ScoopContext> PackageHandle<'a, C> {
 
     #[must_use]
     /// Get the package's remote manifest
-    pub fn remote_manifest(&self) -> &Manifest {
+    pub fn remote_manifest(&self) -> &NamedManifest {
         &self.remote_manifest
     }
 
@@ -119,7 +141,35 @@ impl<'a, C: ScoopContext> PackageHandle<'a, C> {
     #[must_use]
     /// Get the package's persist directory
     pub fn persist_dir(&self) -> PathBuf {
-        self.ctx.persist_path().join(unsafe { self.name() })
+        self.ctx.persist_path().join(self.name())
+    }
+
+    /// Persist the package's data, and link it into the version directory
+    ///
+    /// See [`PersistEntry::persist`](crate::packages::persist::PersistEntry::persist) for details
+    ///
+    /// # Errors
+    /// - Loading and parsing the manifest failed
+    /// - Persisting the data failed
+    pub fn persist(&self) -> Result<()> {
+        self.local_manifest()?
+            .persist_data(self.version_dir(), self.persist_dir())?;
+
+        Ok(())
+    }
+
+    /// Remove the links to the package's persisted data from the version directory
+    ///
+    /// If `purge` is `true`, the package's persist directory is removed as well
+    ///
+    /// # Errors
+    /// - Loading and parsing the manifest failed
+    /// - Removing the links or the persist directory failed
+    pub fn unpersist(&self, purge: bool) -> Result<()> {
+        self.local_manifest()?
+            .unpersist_data(self.version_dir(), self.persist_dir(), purge)?;
+
+        Ok(())
     }
 
     #[must_use]
@@ -128,13 +178,64 @@ impl<'a, C: ScoopContext> PackageHandle<'a, C> {
     /// This will return the version of the remote manifest,
     /// or the version of the package reference, if specified
     pub fn version_dir(&self) -> PathBuf {
-        let version = if let Some(ref version) = self.reference.version {
+        self.path.join(self.version())
+    }
+
+    /// Get the version of the remote manifest, or the version of the package reference, if specified
+    fn version(&self) -> &str {
+        if let Some(ref version) = self.reference.version {
             version
         } else {
             self.remote_manifest.version.as_str()
-        };
+        }
+    }
 
-        self.path.join(version)
+    #[cfg(feature = "manifest-hashes")]
+    #[must_use]
+    /// Get the install-time variables for the package
+    ///
+    /// `cmd` is the Scoop command being run (i.e `install`, `update` or `uninstall`).
+    /// `dir` is the directory `$dir` refers to. Like Scoop, this is the version directory
+    /// until `current` is linked to it, and [`PackageHandle::current`] afterwards.
+    pub fn install_variables(
+        &self,
+        cmd: impl Into<String>,
+        arch: crate::Architecture,
+        dir: impl AsRef<Path>,
+    ) -> crate::packages::variables::InstallVariables {
+        crate::packages::variables::InstallVariables::new(self.name(), self.version(), arch)
+            .with_dir(dir)
+            .with_original_dir(self.version_dir())
+            .with_persist_dir(self.persist_dir())
+            .with_global(self.ctx.is_global())
+            .with_buckets_dir(self.ctx.buckets_path())
+            .with_cmd(cmd)
+    }
+
+    /// Get the prelude defining Scoop's variables and helper functions for the package's manifest scripts
+    ///
+    /// Defines `$app`, `$version`, `$dir`, `$original_dir`, `$persist_dir`, `$global`, `$architecture`,
+    /// `$bucketsdir` and `$manifest` (the remote manifest), along with the helper function shim.
+    /// `$dir` is the given directory, as in [`PackageHandle::install_variables`].
+    ///
+    /// # Errors
+    /// - Serializing the manifest failed
+    pub fn script_prelude(
+        &self,
+        arch: crate::Architecture,
+        dir: impl AsRef<Path>,
+    ) -> serde_json::Result<Prelude> {
+        Prelude::new()
+            .with_string("app", self.name())
+            .with_string("version", self.version())
+            .with_string("dir", dir.as_ref().display().to_string())
+            .with_string("original_dir", self.version_dir().display().to_string())
+            .with_string("persist_dir", self.persist_dir().display().to_string())
+            .with_bool("global", self.ctx.is_global())
+            .with_string("architecture", arch.to_string())
+            .with_string("bucketsdir", self.ctx.buckets_path().display().to_string())
+            .with_json("manifest", self.remote_manifest.inner())
+            .map(|prelude| prelude.with_helpers(true))
     }
 
     /// Unlink the current folder
@@ -190,15 +291,8 @@ impl<'a, C: ScoopContext> PackageHandle<'a, C> {
 
     #[must_use]
     /// Get the package's name
-    ///
-    /// # Safety
-    /// This field is manually set in the remote manifest, and by default is uninitialized. This may cause undefined behavior.
-    ///
-    /// Use [`Manifest::name_opt`] or,
-    /// to ensure that this function returns properly, use the [`CreateManifest`] trait to set the name,
-    /// or create the manifest, rather than other methods that might fail to set the name.
-    pub unsafe fn name(&self) -> &str {
-        unsafe { self.remote_manifest.name() }
+    pub fn name(&self) -> &str {
+        self.remote_manifest.name()
     }
 
     #[must_use]
@@ -2,12 +2,16 @@
 
 use std::path::{Path, PathBuf};
 
+use crate::{config, contexts::ScoopContext, packages::bin::BinEntry};
+
 #[derive(Debug, thiserror::Error)]
 #[allow(missing_docs)]
 /// Shim errors
 pub enum Error {
-    #[error("Deleting shims: {0}")]
+    #[error("Writing/deleting shims: {0}")]
     IOError(#[from] std::io::Error),
+    #[error("Could not find the shim executable. Is Scoop installed?")]
+    MissingShimExecutable,
 }
 
 /// Shim result type
@@ -57,13 +61,71 @@ impl std::ops::BitAnd for DeleteFlags {
 pub struct ShimHandle {
     executable: PathBuf,
     shim: PathBuf,
+    wrappers: Vec<PathBuf>,
+    shim_executable: Option<PathBuf>,
 }
 
 impl ShimHandle {
     #[must_use]
     /// Create a new shim handle
     pub fn new(executable: PathBuf, shim: PathBuf) -> Self {
-        Self { executable, shim }
+        Self {
+            executable,
+            shim,
+            wrappers: vec![],
+            shim_executable: None,
+        }
+    }
+
+    #[must_use]
+    /// Get the shim handle for a binary entry, in the given context's shims directory
+    ///
+    /// Executable targets are shimmed with a copy of the shim executable configured in the context.
+    /// Script targets are shimmed with a `.cmd` wrapper, plus a `.ps1` wrapper for Powershell scripts
+    /// and an extensionless wrapper for POSIX shells
+    pub fn from_bin(ctx: &impl ScoopContext<Config = config::Scoop>, entry: &BinEntry) -> Self {
+        let shims_path = ctx.shims_path();
+        let alias = &entry.alias;
+        let shim = shims_path.join(format!("{alias}.shim"));
+
+        match Target::of(&entry.target) {
+            Target::Executable => Self::new(shims_path.join(format!("{alias}.exe")), shim)
+                .with_shim_executable(
+                    ctx.context_app_path()
+                        .join("supporting")
+                        .join("shims")
+                        .join(ctx.config().shim.directory_name())
+                        .join("shim.exe"),
+                ),
+            Target::Powershell => Self::new(shims_path.join(format!("{alias}.cmd")), shim)
+                .with_wrappers(vec![
+                    shims_path.join(format!("{alias}.ps1")),
+                    shims_path.join(alias),
+                ]),
+            Target::Script => Self::new(shims_path.join(format!("{alias}.cmd")), shim)
+                .with_wrappers(vec![shims_path.join(alias)]),
+        }
+    }
+
+    #[must_use]
+    /// Set the shim executable that will be copied for executable targets
+    pub fn with_shim_executable(mut self, shim_executable: PathBuf) -> Self {
+        self.shim_executable = Some(shim_executable);
+        self
+    }
+
+    #[must_use]
+    /// Set the additional wrapper scripts written alongside the executable
+    pub fn with_wrappers(mut self, wrappers: Vec<PathBuf>) -> Self {
+        self.wrappers = wrappers;
+        self
+    }
+
+    /// Get every file this handle manages, whether or not it exists
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        [self.executable.as_path(), self.shim.as_path()]
+            .into_iter()
+            .chain(self.wrappers.iter().map(PathBuf::as_path))
     }
 
     #[must_use]
@@ -90,6 +152,57 @@ impl ShimHandle {
         }
     }
 
+    /// Write the shim pointing to a binary entry installed in the given app directory
+    ///
+    /// For executable targets this copies the shim executable, and writes the `.shim` file it reads.
+    /// For script targets this writes the `.cmd` executable and any other wrappers.
+    ///
+    /// # Errors
+    /// - Creating the shims directory failed
+    /// - The shim executable could not be found
+    /// - Writing the shim files failed
+    pub fn write_shim(&self, entry: &BinEntry, app_dir: impl AsRef<Path>) -> Result<()> {
+        let app_dir = app_dir.as_ref();
+
+        if let Some(parent) = self.shim.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let target = app_dir.join(&entry.target);
+        let target_kind = Target::of(&entry.target);
+        let args = entry
+            .args
+            .iter()
+            .flat_map(|arg| [" ", arg.as_str()])
+            .collect::<String>();
+
+        if is_extension(&self.executable, "exe") {
+            let shim_executable = self
+                .shim_executable
+                .as_ref()
+                .filter(|path| path.exists())
+                .ok_or(Error::MissingShimExecutable)?;
+
+            std::fs::copy(shim_executable, &self.executable)?;
+            // Like Scoop, only the shim executable reads a `.shim` file
+            std::fs::write(&self.shim, entry.shim_contents(app_dir))?;
+        } else {
+            std::fs::write(&self.executable, cmd_wrapper(&target, target_kind, &args))?;
+        }
+
+        for wrapper in &self.wrappers {
+            let contents = if is_extension(wrapper, "ps1") {
+                ps1_wrapper(&target, &args)
+            } else {
+                sh_wrapper(&target, target_kind, &args)
+            };
+
+            std::fs::write(wrapper, contents)?;
+        }
+
+        Ok(())
+    }
+
     /// Delete the shim and executable
     ///
     /// # Errors
@@ -117,6 +230,10 @@ impl ShimHandle {
             if let Some(executable) = self.executable() {
                 std::fs::remove_file(executable)?;
             }
+
+            for wrapper in self.wrappers.iter().filter(|wrapper| wrapper.exists()) {
+                std::fs::remove_file(wrapper)?;
+            }
         }
 
         if flags.is_shim() {
@@ -129,9 +246,200 @@ impl ShimHandle {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Target {
+    Executable,
+    Powershell,
+    Script,
+}
+
+impl Target {
+    fn of(target: &str) -> Self {
+        let target = Path::new(target);
+
+        if is_extension(target, "exe") || is_extension(target, "com") {
+            Self::Executable
+        } else if is_extension(target, "ps1") {
+            Self::Powershell
+        } else {
+            Self::Script
+        }
+    }
+}
+
+fn is_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+}
+
+fn cmd_wrapper(target: &Path, kind: Target, args: &str) -> String {
+    let path = target.display();
+
+    if kind == Target::Powershell {
+        format!(
+            "@rem {path}\r\n\
+            @where /q pwsh.exe\r\n\
+            @if %errorlevel% equ 0 (\r\n\
+            \x20 @pwsh -noprofile -ex unrestricted -file \"{path}\"{args} %*\r\n\
+            ) else (\r\n\
+            \x20 @powershell -noprofile -ex unrestricted -file \"{path}\"{args} %*\r\n\
+            )\r\n"
+        )
+    } else {
+        format!("@rem {path}\r\n@\"{path}\"{args} %*\r\n")
+    }
+}
+
+fn ps1_wrapper(target: &Path, args: &str) -> String {
+    let path = target.display().to_string().replace('\'', "''");
+
+    format!(
+        "# {path}\n\
+        $path = '{path}'\n\
+        if ($MyInvocation.ExpectingInput) {{ $input | & $path{args} @args }} else {{ & $path{args} @args }}\n\
+        exit $LASTEXITCODE\n"
+    )
+}
+
+fn sh_wrapper(target: &Path, kind: Target, args: &str) -> String {
+    let path = target.display();
+
+    if kind == Target::Powershell {
+        format!(
+            "#!/bin/sh\n\
+            # {path}\n\
+            if command -v pwsh.exe > /dev/null 2>&1; then\n\
+            \x20 pwsh.exe -noprofile -ex unrestricted -file \"{path}\"{args} \"$@\"\n\
+            else\n\
+            \x20 powershell.exe -noprofile -ex unrestricted -file \"{path}\"{args} \"$@\"\n\
+            fi\n"
+        )
+    } else {
+        format!(
+            "#!/bin/sh\n\
+            # {path}\n\
+            MSYS2_ARG_CONV_EXCL=/C cmd.exe /C \"{path}\"{args} \"$@\"\n"
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::contexts::testing::TestContext;
+
+    #[test]
+    fn test_from_bin() {
+        let ctx = TestContext::new("shim-from-bin");
+        let entry = BinEntry {
+            target: "bin/app.exe".to_string(),
+            alias: "app-alias".to_string(),
+            args: vec!["--flag".to_string()],
+        };
+
+        let handle = ShimHandle::from_bin(&ctx, &entry);
+        assert_eq!(handle.shim(), None);
+
+        let app_dir = ctx.apps_path().join("app").join("current");
+        assert!(matches!(
+            handle.write_shim(&entry, &app_dir),
+            Err(Error::MissingShimExecutable)
+        ));
+
+        let shim_executable = ctx
+            .context_app_path()
+            .join("supporting")
+            .join("shims")
+            .join("kiennq")
+            .join("shim.exe");
+        std::fs::create_dir_all(shim_executable.parent().unwrap()).unwrap();
+        std::fs::write(&shim_executable, b"MZ shim").unwrap();
+
+        handle.write_shim(&entry, &app_dir).unwrap();
+
+        let shim = ctx.shims_path().join("app-alias.shim");
+        let executable = ctx.shims_path().join("app-alias.exe");
+        assert_eq!(handle.shim(), Some(shim.as_path()));
+        assert_eq!(handle.executable(), Some(executable.as_path()));
+        assert_eq!(
+            std::fs::read_to_string(&shim).unwrap(),
+            entry.shim_contents(&app_dir)
+        );
+        assert_eq!(std::fs::read(&executable).unwrap(), b"MZ shim");
+
+        handle.delete(DeleteFlags::SHIM).unwrap();
+        assert!(!shim.exists());
+        assert!(executable.exists());
+
+        handle.delete_all().unwrap();
+        assert!(!executable.exists());
+
+        ctx.cleanup();
+    }
+
+    #[test]
+    fn test_script_shims() {
+        let ctx = TestContext::new("shim-scripts");
+        let app_dir = ctx.apps_path().join("app").join("current");
+
+        let entry = BinEntry {
+            target: "app.ps1".to_string(),
+            alias: "app".to_string(),
+            args: vec!["--flag".to_string()],
+        };
+        let handle = ShimHandle::from_bin(&ctx, &entry);
+        handle.write_shim(&entry, &app_dir).unwrap();
+
+        let target = app_dir.join("app.ps1").display().to_string();
+        let files = ["app.cmd", "app.ps1", "app"].map(|name| ctx.shims_path().join(name));
+        assert!(files
+            .iter()
+            .all(|file| handle.files().any(|path| path == file)));
+        assert!(!ctx.shims_path().join("app.shim").exists());
+
+        for file in &files {
+            let contents = std::fs::read_to_string(file).unwrap();
+            assert!(contents.contains(&target), "{}", file.display());
+        }
+
+        let cmd = std::fs::read_to_string(&files[0]).unwrap();
+        assert!(cmd.contains(&format!("-file \"{target}\" --flag %*")));
+
+        handle.delete_all().unwrap();
+        assert!(files.iter().all(|file| !file.exists()));
+
+        let entry = BinEntry {
+            target: "tool.cmd".to_string(),
+            alias: "tool".to_string(),
+            args: vec![],
+        };
+        let handle = ShimHandle::from_bin(&ctx, &entry);
+        handle.write_shim(&entry, &app_dir).unwrap();
+
+        let target = app_dir.join("tool.cmd").display().to_string();
+        assert_eq!(
+            std::fs::read_to_string(ctx.shims_path().join("tool.cmd")).unwrap(),
+            format!("@rem {target}\r\n@\"{target}\" %*\r\n")
+        );
+        assert!(std::fs::read_to_string(ctx.shims_path().join("tool"))
+            .unwrap()
+            .starts_with("#!/bin/sh\n"));
+        assert!(!ctx.shims_path().join("tool.ps1").exists());
+
+        #[cfg(windows)]
+        {
+            std::fs::create_dir_all(&app_dir).unwrap();
+            std::fs::write(app_dir.join("tool.cmd"), "@echo tool %*\r\n").unwrap();
+
+            let output = std::process::Command::new(ctx.shims_path().join("tool.cmd"))
+                .arg("hello")
+                .output()
+                .unwrap();
+            assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "tool hello");
+        }
+
+        ctx.cleanup();
+    }
 
     #[test]
     fn test_delete_flags() {
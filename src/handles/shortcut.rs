@@ -0,0 +1,236 @@
+//! Start Menu shortcut handles
+
+use std::path::{Path, PathBuf};
+
+use crate::{
+    contexts::ScoopContext,
+    packages::{extract, shortcuts::Shortcut},
+};
+
+pub mod lnk;
+
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+/// Shortcut errors
+pub enum Error {
+    #[error("Writing shortcuts: {0}")]
+    IOError(#[from] std::io::Error),
+    #[error("Shortcut name {0:?} must stay within the shortcuts folder")]
+    InvalidName(String),
+}
+
+/// Shortcut result type
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Manages the shortcuts in a context's Start Menu "Scoop Apps" folder
+pub struct ShortcutManager {
+    path: PathBuf,
+}
+
+impl ShortcutManager {
+    #[must_use]
+    /// Create a shortcut manager for the given context
+    pub fn new(ctx: &impl ScoopContext) -> Self {
+        Self::from_path(ctx.shortcuts_path())
+    }
+
+    #[must_use]
+    /// Create a shortcut manager for the given shortcuts folder
+    pub fn from_path(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    #[must_use]
+    /// Get the shortcuts folder
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get the path to a shortcut's `.lnk` file
+    ///
+    /// # Errors
+    /// - The shortcut name is absolute, or contains `..` components
+    pub fn link_path(&self, shortcut: &Shortcut) -> Result<PathBuf> {
+        // Shortcut names may include subdirectories (i.e `Tools\Tool`)
+        let name = extract::relative_path(&shortcut.name)
+            .ok()
+            .filter(|name| name.file_name().is_some())
+            .ok_or_else(|| Error::InvalidName(shortcut.name.clone()))?;
+
+        let mut path = self.path.join(name).into_os_string();
+        path.push(".lnk");
+
+        Ok(path.into())
+    }
+
+    /// Create a shortcut to an app installed in the given directory
+    ///
+    /// Like Scoop, the icon is only used if it exists
+    ///
+    /// # Errors
+    /// - The shortcut name is invalid
+    /// - Creating the shortcut's directory failed
+    /// - Writing the shortcut failed
+    pub fn create(&self, shortcut: &Shortcut, app_dir: impl AsRef<Path>) -> Result<PathBuf> {
+        let app_dir = app_dir.as_ref();
+        let path = self.link_path(shortcut)?;
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut link = lnk::ShellLink::new(shortcut.target_path(app_dir));
+        if let Some(args) = &shortcut.args {
+            link = link.with_arguments(args);
+        }
+        if let Some(icon) = shortcut.icon_path(app_dir).filter(|icon| icon.exists()) {
+            link = link.with_icon(icon);
+        }
+
+        link.write_to(&path)?;
+
+        Ok(path)
+    }
+
+    /// Create the given shortcuts to an app installed in the given directory
+    ///
+    /// # Errors
+    /// - Creating any shortcut failed
+    pub fn create_all(
+        &self,
+        shortcuts: &[Shortcut],
+        app_dir: impl AsRef<Path>,
+    ) -> Result<Vec<PathBuf>> {
+        let app_dir = app_dir.as_ref();
+
+        shortcuts
+            .iter()
+            .map(|shortcut| self.create(shortcut, app_dir))
+            .collect()
+    }
+
+    /// Remove a shortcut, if it exists
+    ///
+    /// Empty subdirectories created for the shortcut are removed as well
+    ///
+    /// # Errors
+    /// - The shortcut name is invalid
+    /// - Removing the shortcut failed
+    pub fn remove(&self, shortcut: &Shortcut) -> Result<()> {
+        let path = self.link_path(shortcut)?;
+
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+
+        let mut parent = path.parent();
+        while let Some(dir) = parent.filter(|dir| *dir != self.path && dir.starts_with(&self.path))
+        {
+            if std::fs::remove_dir(dir).is_err() {
+                // The directory is not empty
+                break;
+            }
+            parent = dir.parent();
+        }
+
+        Ok(())
+    }
+
+    /// Remove the given shortcuts, if they exist
+    ///
+    /// # Errors
+    /// - Removing any shortcut failed
+    pub fn remove_all(&self, shortcuts: &[Shortcut]) -> Result<()> {
+        shortcuts
+            .iter()
+            .try_for_each(|shortcut| self.remove(shortcut))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contexts::testing::TestContext;
+
+    #[test]
+    fn test_shortcut_manager() {
+        let ctx = TestContext::new("shortcuts");
+        let manager = ShortcutManager::new(&ctx);
+        let app_dir = ctx.apps_path().join("app").join("current");
+
+        std::fs::create_dir_all(&app_dir).unwrap();
+        std::fs::write(app_dir.join("app.ico"), b"").unwrap();
+
+        let shortcuts = [
+            Shortcut {
+                target: "app.exe".to_string(),
+                name: "App".to_string(),
+                args: Some("--flag".to_string()),
+                icon: Some("app.ico".to_string()),
+            },
+            Shortcut {
+                target: "tool.exe".to_string(),
+                name: "Tools\\Tool".to_string(),
+                args: None,
+                icon: Some("missing.ico".to_string()),
+            },
+        ];
+
+        let paths = manager.create_all(&shortcuts, &app_dir).unwrap();
+        assert_eq!(
+            paths,
+            [
+                ctx.shortcuts_path().join("App.lnk"),
+                ctx.shortcuts_path().join("Tools").join("Tool.lnk"),
+            ]
+        );
+
+        let app = lnk::ShellLink::new(app_dir.join("app.exe"))
+            .with_arguments("--flag")
+            .with_icon(app_dir.join("app.ico"));
+        assert_eq!(std::fs::read(&paths[0]).unwrap(), app.to_bytes());
+
+        // The missing icon is skipped
+        let tool = lnk::ShellLink::new(app_dir.join("tool.exe"));
+        assert_eq!(std::fs::read(&paths[1]).unwrap(), tool.to_bytes());
+
+        manager.remove_all(&shortcuts).unwrap();
+        assert!(paths.iter().all(|path| !path.exists()));
+        assert!(!ctx.shortcuts_path().join("Tools").exists());
+        assert!(ctx.shortcuts_path().exists());
+
+        ctx.cleanup();
+    }
+
+    #[test]
+    fn test_invalid_names() {
+        let ctx = TestContext::new("shortcuts-invalid");
+        let manager = ShortcutManager::new(&ctx);
+        let app_dir = ctx.apps_path().join("app").join("current");
+
+        for name in [
+            "..\\..\\Startup\\App",
+            "Tools/../../App",
+            "/tmp/App",
+            "",
+            ".",
+        ] {
+            let shortcut = Shortcut {
+                target: "app.exe".to_string(),
+                name: name.to_string(),
+                args: None,
+                icon: None,
+            };
+
+            assert!(
+                matches!(manager.link_path(&shortcut), Err(Error::InvalidName(invalid)) if invalid == name),
+                "{name:?} should be rejected"
+            );
+            assert!(manager.create(&shortcut, &app_dir).is_err());
+            assert!(manager.remove(&shortcut).is_err());
+        }
+
+        ctx.cleanup();
+    }
+}
@@ -0,0 +1,268 @@
+//! Shell link (`.lnk`) writer
+//!
+//! Writes the subset of the [Shell Link Binary File Format](https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-shllink/16cb4ca1-9339-4d0c-a68d-bf1d6cc0f943)
+//! that `WScript.Shell` writes for Scoop's shortcuts, without relying on COM.
+
+use std::path::{Path, PathBuf};
+
+/// The size of the shell link header
+const HEADER_SIZE: u32 = 0x4C;
+/// The shell link class identifier (`00021401-0000-0000-C000-000000000046`)
+const LINK_CLSID: [u8; 16] = [
+    0x01, 0x14, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0xC0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46,
+];
+
+const HAS_LINK_INFO: u32 = 0x0000_0002;
+const HAS_WORKING_DIR: u32 = 0x0000_0010;
+const HAS_ARGUMENTS: u32 = 0x0000_0020;
+const HAS_ICON_LOCATION: u32 = 0x0000_0040;
+const IS_UNICODE: u32 = 0x0000_0080;
+
+const FILE_ATTRIBUTE_NORMAL: u32 = 0x80;
+const SW_SHOWNORMAL: u32 = 1;
+
+/// The size of the link info header, including the unicode path offsets
+const LINK_INFO_HEADER_SIZE: u32 = 0x24;
+const VOLUME_ID_AND_LOCAL_BASE_PATH: u32 = 0x1;
+/// The size of a volume id with an empty ANSI label
+const VOLUME_ID_SIZE: u32 = 0x11;
+const DRIVE_FIXED: u32 = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A shell link pointing to a local file
+pub struct ShellLink {
+    /// The absolute path to the link target
+    pub target: PathBuf,
+    /// The arguments passed to the target
+    pub arguments: Option<String>,
+    /// The working directory the target is started in
+    pub working_dir: Option<PathBuf>,
+    /// The path to the link's icon
+    pub icon: Option<PathBuf>,
+}
+
+impl ShellLink {
+    #[must_use]
+    /// Create a shell link to the given target, started in the target's directory
+    pub fn new(target: impl Into<PathBuf>) -> Self {
+        let target = target.into();
+        // Split on both separators, so Windows paths are handled on any platform
+        let working_dir = path_string(&target)
+            .rsplit_once(['\\', '/'])
+            .map(|(parent, _)| PathBuf::from(parent));
+
+        Self {
+            target,
+            arguments: None,
+            working_dir,
+            icon: None,
+        }
+    }
+
+    #[must_use]
+    /// Set the arguments passed to the target
+    pub fn with_arguments(mut self, arguments: impl Into<String>) -> Self {
+        self.arguments = Some(arguments.into());
+        self
+    }
+
+    #[must_use]
+    /// Set the path to the link's icon
+    pub fn with_icon(mut self, icon: impl Into<PathBuf>) -> Self {
+        self.icon = Some(icon.into());
+        self
+    }
+
+    #[must_use]
+    /// Serialize the shell link
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut flags = HAS_LINK_INFO | IS_UNICODE;
+        if self.working_dir.is_some() {
+            flags |= HAS_WORKING_DIR;
+        }
+        if self.arguments.is_some() {
+            flags |= HAS_ARGUMENTS;
+        }
+        if self.icon.is_some() {
+            flags |= HAS_ICON_LOCATION;
+        }
+
+        let mut bytes = Vec::new();
+
+        // Shell link header
+        bytes.extend(HEADER_SIZE.to_le_bytes());
+        bytes.extend(LINK_CLSID);
+        bytes.extend(flags.to_le_bytes());
+        bytes.extend(FILE_ATTRIBUTE_NORMAL.to_le_bytes());
+        // Creation, access and write times, and the target's file size
+        bytes.extend([0; 28]);
+        // Icon index
+        bytes.extend(0i32.to_le_bytes());
+        bytes.extend(SW_SHOWNORMAL.to_le_bytes());
+        // Hotkey and reserved fields
+        bytes.extend([0; 12]);
+
+        bytes.extend(link_info(&path_string(&self.target)));
+
+        let strings = [
+            self.working_dir.as_deref().map(path_string),
+            self.arguments.clone(),
+            self.icon.as_deref().map(path_string),
+        ];
+        for string in strings.into_iter().flatten() {
+            let units = string.encode_utf16().collect::<Vec<_>>();
+            let count = u16::try_from(units.len()).unwrap_or(u16::MAX);
+
+            bytes.extend(count.to_le_bytes());
+            bytes.extend(
+                units
+                    .into_iter()
+                    .take(usize::from(count))
+                    .flat_map(u16::to_le_bytes),
+            );
+        }
+
+        // Terminal block
+        bytes.extend(0u32.to_le_bytes());
+
+        bytes
+    }
+
+    /// Write the shell link to the given path
+    ///
+    /// # Errors
+    /// - Writing the file failed
+    pub fn write_to(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_bytes())
+    }
+}
+
+fn path_string(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+/// Build the link info structure for a local base path
+fn link_info(base_path: &str) -> Vec<u8> {
+    // Non-ASCII characters cannot be represented in every code page, so the unicode path is authoritative
+    let ansi_path = base_path
+        .chars()
+        .map(|c| if c.is_ascii() { c as u8 } else { b'?' })
+        .chain([0])
+        .collect::<Vec<_>>();
+    let unicode_path = base_path
+        .encode_utf16()
+        .chain([0])
+        .flat_map(u16::to_le_bytes)
+        .collect::<Vec<_>>();
+
+    let as_u32 = |len: usize| u32::try_from(len).expect("link info fits in u32");
+
+    let volume_id_offset = LINK_INFO_HEADER_SIZE;
+    let local_base_path_offset = volume_id_offset + VOLUME_ID_SIZE;
+    let common_path_suffix_offset = local_base_path_offset + as_u32(ansi_path.len());
+    let local_base_path_offset_unicode = common_path_suffix_offset + 1;
+    let common_path_suffix_offset_unicode =
+        local_base_path_offset_unicode + as_u32(unicode_path.len());
+    let size = common_path_suffix_offset_unicode + 2;
+
+    let mut bytes = Vec::with_capacity(size as usize);
+
+    for field in [
+        size,
+        LINK_INFO_HEADER_SIZE,
+        VOLUME_ID_AND_LOCAL_BASE_PATH,
+        volume_id_offset,
+        local_base_path_offset,
+        // Common network relative link offset
+        0,
+        common_path_suffix_offset,
+        local_base_path_offset_unicode,
+        common_path_suffix_offset_unicode,
+    ] {
+        bytes.extend(field.to_le_bytes());
+    }
+
+    // Volume id, with no serial number and an empty label
+    bytes.extend(VOLUME_ID_SIZE.to_le_bytes());
+    bytes.extend(DRIVE_FIXED.to_le_bytes());
+    bytes.extend(0u32.to_le_bytes());
+    bytes.extend(0x10u32.to_le_bytes());
+    bytes.push(0);
+
+    bytes.extend(ansi_path);
+    // Empty common path suffix
+    bytes.push(0);
+    bytes.extend(unicode_path);
+    bytes.extend([0, 0]);
+
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn read_utf16(bytes: &[u8], offset: usize, len: usize) -> String {
+        String::from_utf16(
+            &bytes[offset..offset + len * 2]
+                .chunks_exact(2)
+                .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                .collect::<Vec<_>>(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_shell_link_bytes() {
+        let target = r"C:\Scoop\apps\app\current\app.exe";
+        let link = ShellLink::new(target)
+            .with_arguments("--flag")
+            .with_icon(r"C:\Scoop\apps\app\current\app.ico");
+        let bytes = link.to_bytes();
+
+        assert_eq!(read_u32(&bytes, 0), HEADER_SIZE);
+        assert_eq!(bytes[4..20], LINK_CLSID);
+        assert_eq!(
+            read_u32(&bytes, 20),
+            HAS_LINK_INFO | IS_UNICODE | HAS_WORKING_DIR | HAS_ARGUMENTS | HAS_ICON_LOCATION
+        );
+
+        let link_info = HEADER_SIZE as usize;
+        let link_info_size = read_u32(&bytes, link_info) as usize;
+        assert_eq!(read_u32(&bytes, link_info + 4), LINK_INFO_HEADER_SIZE);
+
+        let ansi_offset = link_info + read_u32(&bytes, link_info + 16) as usize;
+        assert_eq!(
+            &bytes[ansi_offset..ansi_offset + target.len()],
+            target.as_bytes()
+        );
+        assert_eq!(bytes[ansi_offset + target.len()], 0);
+
+        let unicode_offset = link_info + read_u32(&bytes, link_info + 28) as usize;
+        assert_eq!(read_utf16(&bytes, unicode_offset, target.len()), target);
+
+        // String data, in order: working directory, arguments, icon location
+        let mut offset = link_info + link_info_size;
+        let mut strings = vec![];
+        for _ in 0..3 {
+            let len = usize::from(u16::from_le_bytes([bytes[offset], bytes[offset + 1]]));
+            strings.push(read_utf16(&bytes, offset + 2, len));
+            offset += 2 + len * 2;
+        }
+        assert_eq!(
+            strings,
+            [
+                r"C:\Scoop\apps\app\current".to_string(),
+                "--flag".to_string(),
+                r"C:\Scoop\apps\app\current\app.ico".to_string(),
+            ]
+        );
+
+        assert_eq!(read_u32(&bytes, offset), 0);
+        assert_eq!(bytes.len(), offset + 4);
+    }
+}
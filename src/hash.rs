@@ -12,15 +12,16 @@ use substitutions::SubstitutionMap;
 use url::Url;
 
 use crate::{
-    cache::{self, Downloader, Handle},
+    cache::{self, CacheFile, Downloader, Handle},
     contexts::ScoopContext,
     hash::url_ext::UrlExt,
     packages::{
+        downloading::DownloadUrl,
         models::manifest::{
             AutoupdateConfig, HashExtractionOrArrayOfHashExtractions, HashMode as ManifestHashMode,
             StringArray,
         },
-        Manifest, MergeDefaults,
+        Manifest, MergeDefaults, NamedManifest,
     },
     requests::{AsyncClient, Client},
     version::Version,
@@ -258,12 +259,8 @@ impl HashMode {
             .merge_default(manifest.install_config.clone(), arch);
 
         if let Some(StringArray::Single(url)) = install_config.url {
-            if Self::fosshub_regex().is_match(&url) {
-                return Some(Self::Fosshub);
-            }
-
-            if Self::sourceforge_regex().is_match(&url) {
-                return Some(Self::Sourceforge);
+            if let Some(mode) = Self::from_url(&url) {
+                return Some(mode);
             }
         }
 
@@ -279,6 +276,17 @@ impl HashMode {
         Self::from_autoupdate_config(&autoupdate_config)
     }
 
+    /// Get the [`HashMode`] for urls that have a dedicated hash source (Fosshub and Sourceforge)
+    fn from_url(url: &str) -> Option<Self> {
+        if Self::fosshub_regex().is_match(url) {
+            Some(Self::Fosshub)
+        } else if Self::sourceforge_regex().is_match(url) {
+            Some(Self::Sourceforge)
+        } else {
+            None
+        }
+    }
+
     #[must_use]
     #[deprecated(note = "Does not handle Sourceforge or Fosshub. Use `from_manifest` instead.")]
     /// Get a [`HashMode`] from an [`AutoupdateConfig`]
@@ -359,7 +367,7 @@ impl Hash {
     /// - If the hash is not found in the JSON
     pub async fn get_for_app(
         ctx: &impl ScoopContext,
-        manifest: &Manifest,
+        manifest: &NamedManifest,
         arch: Architecture,
     ) -> Result<Vec<Hash>, Error> {
         let autoupdate_config = manifest
@@ -408,25 +416,66 @@ impl Hash {
             .collect::<Result<Vec<_>, _>>()?;
 
         let hashes = manifest_urls.into_iter().map(|manifest_url| {
-            Self::get_for_url(
-                hash_mode.clone(),
-                manifest_url,
-                &manifest.version,
-                &autoupdate_config,
-            )
+            let submap = SubstitutionMap::from_all(&manifest.version, &manifest_url);
+
+            Self::get_for_url(hash_mode.clone(), manifest_url, submap, &autoupdate_config)
         });
 
         futures::future::try_join_all(hashes).await
     }
 
+    /// Get the hash for a single url, as part of an autoupdate
+    ///
+    /// `substitutions` should contain the version and `checkver` substitutions.
+    /// The url substitutions are added here.
+    /// If the autoupdate config has no hash extraction, the file is downloaded and hashed.
+    pub(crate) async fn get_for_autoupdate_url(
+        ctx: &impl ScoopContext,
+        name: &str,
+        version: &Version,
+        url: &str,
+        autoupdate_config: &AutoupdateConfig,
+        substitutions: &SubstitutionMap,
+    ) -> Result<Hash, Error> {
+        #[allow(deprecated)]
+        let hash_mode = HashMode::from_url(url)
+            .or_else(|| HashMode::from_autoupdate_config(autoupdate_config))
+            .unwrap_or_default();
+
+        if hash_mode == HashMode::Download {
+            let download_url = DownloadUrl::from_string(url);
+            let file_name = CacheFile::new(name, version, &download_url).filename();
+
+            let handle = Handle::new(
+                ctx.cache_path(),
+                file_name,
+                HashType::SHA256,
+                download_url.url,
+                Hash::from_hex(&[]),
+            )?;
+
+            let result = Downloader::new::<AsyncClient>(handle, None)
+                .await?
+                .download()
+                .await?;
+
+            return Ok(result.computed_hash);
+        }
+
+        let manifest_url = Url::parse(url)?;
+
+        let mut submap = substitutions.clone();
+        submap.append_url(&manifest_url);
+
+        Self::get_for_url(hash_mode, manifest_url, submap, autoupdate_config).await
+    }
+
     async fn get_for_url(
         mut hash_mode: HashMode,
         manifest_url: Url,
-        version: &Version,
+        submap: SubstitutionMap,
         autoupdate_config: &AutoupdateConfig,
     ) -> Result<Hash, Error> {
-        let submap = SubstitutionMap::from_all(version, &manifest_url);
-
         let url = if matches!(hash_mode, HashMode::Fosshub | HashMode::Sourceforge) {
             let (url, regex): (Url, String) = match hash_mode {
                 HashMode::Fosshub => {
@@ -499,8 +548,16 @@ impl Hash {
 
         let source = Client::asynchronous().get(url.as_str()).send().await?;
 
+        if !source.status().is_success() {
+            return Err(Error::ErrorStatus(source.status()));
+        }
+
         if hash_mode == HashMode::HashUrl {
-            let hash = source.text().await?;
+            let hash = source.text().await?.trim().to_string();
+            if hash.is_empty() {
+                return Err(Error::NotFound);
+            }
+
             let hash_type = HashType::try_from(&hash).unwrap_or_default();
 
             return Ok(Hash { hash, hash_type });
@@ -678,6 +735,7 @@ mod tests {
 
         let autoupdate = manifest
             .autoupdate
+            .clone()
             .unwrap()
             .architecture
             .unwrap()
@@ -707,7 +765,14 @@ mod tests {
 
         let hash = Hash::find_hash_in_xml(source, &submap, xpath).unwrap();
 
-        let actual_hash = manifest.architecture.unwrap().x64.unwrap().hash.unwrap();
+        let actual_hash = manifest
+            .into_inner()
+            .architecture
+            .unwrap()
+            .x64
+            .unwrap()
+            .hash
+            .unwrap();
 
         assert_eq!(actual_hash.single().unwrap(), hash);
     }
@@ -724,7 +789,14 @@ mod tests {
             .await
             .unwrap();
 
-        let actual_hash = manifest.architecture.unwrap().x64.unwrap().hash.unwrap();
+        let actual_hash = manifest
+            .into_inner()
+            .architecture
+            .unwrap()
+            .x64
+            .unwrap()
+            .hash
+            .unwrap();
 
         assert_eq!(actual_hash, TOrArrayOfTs::from_vec_or_default(hash));
     }
@@ -748,7 +820,7 @@ mod tests {
 
             let actual_hash = manifest
                 .architecture
-                .merge_default(manifest.install_config, Architecture::ARCH)
+                .merge_default(manifest.install_config.clone(), Architecture::ARCH)
                 .hash
                 .unwrap();
 
@@ -865,6 +937,7 @@ mod tests {
 
         assert_eq!(
             manifest
+                .into_inner()
                 .architecture
                 .unwrap()
                 .x64
@@ -892,6 +965,7 @@ mod tests {
 
         assert_eq!(
             manifest
+                .into_inner()
                 .architecture
                 .unwrap()
                 .x64
@@ -187,7 +187,8 @@ mod tests {
         let manifest = Bucket::from_name(&ctx, "extras")
             .unwrap()
             .get_manifest("vcredist-aio")
-            .unwrap();
+            .unwrap()
+            .into_inner();
 
         let (text_url, regex) =
             if let HashExtractionOrArrayOfHashExtractions::HashExtraction(extraction) =
@@ -265,6 +266,7 @@ mod tests {
                 .unwrap()
                 .get_manifest("mysql")
                 .unwrap()
+                .into_inner()
                 .architecture
                 .unwrap()
                 .x64
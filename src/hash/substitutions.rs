@@ -5,13 +5,15 @@ use url::Url;
 
 use crate::{
     hash::url_ext::UrlExt,
-    packages::models::manifest::{AliasArray, Installer, TOrArrayOfTs},
+    packages::models::manifest::{
+        AliasArray, AutoupdateLicense, AutoupdatePsmodule, Installer, License, TOrArrayOfTs,
+    },
     version::Version,
 };
 
 fn replace_in_place(string: &mut String, from: &str, to: &str) {
-    for (start, part) in string.clone().match_indices(from) {
-        string.replace_range(start..start + part.len(), to);
+    if string.contains(from) {
+        *string = string.replace(from, to);
     }
 }
 
@@ -44,6 +46,28 @@ impl SubstitutionMap {
     pub fn append_url(&mut self, url: &Url) {
         self.extend(url.submap().0);
     }
+
+    /// Append regex captures from `checkver` to the map
+    ///
+    /// Like Scoop, each capture is available as `$match` followed by the capitalized capture name
+    /// (i.e `$matchBuild` or `$match1`), and the whole match (`0`) is ignored
+    pub fn append_matches(&mut self, matches: &HashMap<String, String>) {
+        for (name, value) in matches {
+            if name == "0" {
+                continue;
+            }
+
+            let mut chars = name.chars();
+            let Some(first) = chars.next() else {
+                continue;
+            };
+
+            self.insert(
+                format!("$match{}{}", first.to_uppercase(), chars.as_str()),
+                value.clone(),
+            );
+        }
+    }
 }
 
 impl Default for SubstitutionMap {
@@ -60,7 +84,11 @@ impl<'a> SubstituteBuilder<'a> {
     pub fn substitute(self, params: &SubstitutionMap, regex_escape: bool) {
         match self {
             SubstituteBuilder::String(new_entity) => {
-                for key in params.keys() {
+                // Substitute longer keys first, so that `$basename` does not replace part of `$basenameNoExt`
+                let mut keys = params.keys().collect::<Vec<_>>();
+                keys.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+
+                for key in keys {
                     let value = params.get(key).unwrap();
 
                     if regex_escape {
@@ -127,6 +155,56 @@ impl<T: Substitute> Substitute for AliasArray<T> {
     }
 }
 
+impl<T: Substitute> Substitute for Option<T> {
+    fn substitute(&mut self, params: &SubstitutionMap, regex_escape: bool) {
+        if let Some(s) = self {
+            s.substitute(params, regex_escape);
+        }
+    }
+}
+
+impl<T: Substitute> Substitute for HashMap<String, T> {
+    fn substitute(&mut self, params: &SubstitutionMap, regex_escape: bool) {
+        self.values_mut()
+            .for_each(|s| s.substitute(params, regex_escape));
+    }
+}
+
+impl Substitute for serde_json::Value {
+    fn substitute(&mut self, params: &SubstitutionMap, regex_escape: bool) {
+        match self {
+            serde_json::Value::String(s) => s.substitute(params, regex_escape),
+            serde_json::Value::Array(a) => a.substitute(params, regex_escape),
+            serde_json::Value::Object(o) => o
+                .values_mut()
+                .for_each(|s| s.substitute(params, regex_escape)),
+            _ => {}
+        }
+    }
+}
+
+impl Substitute for License {
+    fn substitute(&mut self, params: &SubstitutionMap, regex_escape: bool) {
+        self.identifier.substitute(params, regex_escape);
+        self.url.substitute(params, regex_escape);
+    }
+}
+
+impl Substitute for AutoupdateLicense {
+    fn substitute(&mut self, params: &SubstitutionMap, regex_escape: bool) {
+        match self {
+            AutoupdateLicense::License(license) => license.substitute(params, regex_escape),
+            AutoupdateLicense::String(s) => s.substitute(params, regex_escape),
+        }
+    }
+}
+
+impl Substitute for AutoupdatePsmodule {
+    fn substitute(&mut self, params: &SubstitutionMap, regex_escape: bool) {
+        self.name.substitute(params, regex_escape);
+    }
+}
+
 impl Substitute for Installer {
     fn substitute(&mut self, params: &SubstitutionMap, regex_escape: bool) {
         if let Some(s) = self.file.as_mut() {
@@ -149,7 +227,7 @@ impl Substitute for Installer {
 
 #[cfg(test)]
 mod tests {
-    use super::replace_in_place;
+    use super::*;
 
     #[test]
     fn test_replace_in_place() {
@@ -160,4 +238,27 @@ mod tests {
 
         assert_eq!(string, should_be);
     }
+
+    #[test]
+    fn test_substitute_repeated_and_prefixed_keys() {
+        let mut map = SubstitutionMap::new();
+        map.insert("$version".into(), "1.2.3".into());
+        map.insert("$basename".into(), "app-1.2.3.zip".into());
+        map.insert("$basenameNoExt".into(), "app-1.2.3".into());
+        map.append_matches(&HashMap::from([
+            ("0".to_string(), "ignored".to_string()),
+            ("build".to_string(), "456".to_string()),
+        ]));
+
+        let substituted =
+            "https://example.com/$version/app-$version.zip#/$basenameNoExt-$matchBuild"
+                .to_string()
+                .into_substituted(&map, false);
+
+        assert_eq!(
+            substituted,
+            "https://example.com/1.2.3/app-1.2.3.zip#/app-1.2.3-456"
+        );
+        assert!(!map.contains_key("$match0"));
+    }
 }
@@ -19,29 +19,47 @@ use crate::{
         parity::Signature,
         Repo,
     },
-    hacks::let_chain,
     Architecture,
 };
 
 #[cfg(feature = "manifest-hashes")]
-use crate::{
-    hash::{
-        self,
-        substitutions::{Substitute, SubstitutionMap},
-    },
-    packages::manifest::TOrArrayOfTs,
-};
+use std::collections::HashMap;
+
+#[cfg(feature = "manifest-hashes")]
+use crate::hash;
 
 pub(crate) mod array;
+#[cfg(feature = "manifest-hashes")]
+pub mod autoupdate;
+pub mod bin;
+#[cfg(feature = "manifest-hashes")]
+pub mod checkver;
+pub mod dependencies;
 pub mod downloading;
+pub mod extract;
+pub mod format;
+#[cfg(feature = "manifest-hashes")]
+pub mod install;
 pub mod installer;
+pub mod license;
 pub mod models;
+pub mod named;
+pub mod persist;
+pub mod psmodule;
 pub mod reference;
+pub mod shortcuts;
+pub mod status;
+pub mod suggestions;
+pub mod validation;
+#[cfg(feature = "manifest-hashes")]
+pub mod variables;
 
 pub use models::{install::Manifest as InstallManifest, manifest::Manifest};
+pub use named::{BucketManifest, Named, NamedInstallManifest, NamedManifest};
 
+use bin::BinEntry;
 use downloading::DownloadUrl;
-use models::manifest::{InstallConfig, StringArray};
+use models::manifest::InstallConfig;
 
 #[macro_use]
 mod macros {
@@ -75,80 +93,12 @@ mod macros {
         //     arch_config!($field.$crate::Architecture::ARCH).unwrap_or($default)
         // };
     }
-
-    /// Get a field from a manifest based on the architecture
-    macro_rules! arch_field {
-        // ($self:ident.$field:ident) => {
-        //     arch_field!($self.$field).clone()
-        // };
-
-        // ($arch:expr => ref $self:ident.$field:ident) => {{
-        //     if let Some(cfg) = match $arch {
-        //         $crate::Architecture::Arm64 => &$self.arm64,
-        //         $crate::Architecture::X64 => &$self.x64,
-        //         $crate::Architecture::X86 => &$self.x86,
-        //     } {
-        //         &cfg.$field
-        //     } else {
-        //         &None
-        //     }
-        // }};
-
-        // (ref $self:ident.$field:ident) => {
-        //     arch_field!($crate::Architecture::ARCH => ref $self.$field)
-        // };
-
-        // ($arch:expr => ref mut $self:ident.$field:ident) => {{
-        //     match $arch {
-        //         $crate::Architecture::Arm64 => $self.arm64.as_mut(),
-        //         $crate::Architecture::X64 => $self.x64.as_mut(),
-        //         $crate::Architecture::X86 => $self.x86.as_mut(),
-        //     }.and_then(|cfg| cfg.$field.as_mut())
-        // }};
-
-        // (ref mut $self:ident.$field:ident) => {
-        //     arch_field!($crate::Architecture::ARCH => ref mut $self.$field)
-        // };
-
-        ($self:ident.$field:ident as cloned) => {
-            arch_field!($crate::Architecture::ARCH => $self.$field as ref).cloned()
-        };
-
-        ($arch:expr => $self:ident.$field:ident as cloned) => {
-            arch_field!($arch => $self.$field as ref).cloned()
-        };
-
-        ($self:ident.$field:ident as ref) => {
-            arch_field!($crate::Architecture::ARCH => $self.$field as ref)
-        };
-
-        ($arch:expr => $self:ident.$field:ident as ref) => {{
-            match $arch {
-                $crate::Architecture::Arm64 => $self.arm64.as_ref(),
-                $crate::Architecture::X64 => $self.x64.as_ref(),
-                $crate::Architecture::X86 => $self.x86.as_ref(),
-            }.and_then(|cfg| cfg.$field.as_ref())
-        }};
-
-        ($self:ident.$field:ident as mut) => {
-            arch_field!($crate::Architecture::ARCH => $self.$field as mut)
-        };
-
-        ($arch:expr => $self:ident.$field:ident as mut) => {{
-            match $arch {
-                $crate::Architecture::Arm64 => $self.arm64.as_mut(),
-                $crate::Architecture::X64 => $self.x64.as_mut(),
-                $crate::Architecture::X86 => $self.x86.as_mut(),
-            }.and_then(|cfg| cfg.$field.as_mut())
-        }};
-    }
 }
 
 pub(crate) use arch_config;
-pub(crate) use arch_field;
 
 use self::models::manifest::{
-    self, AliasArray, AutoupdateArchitecture, AutoupdateConfig, HashExtraction,
+    self, AutoupdateArchitecture, AutoupdateConfig, HashExtraction,
     HashExtractionOrArrayOfHashExtractions, ManifestArchitecture,
 };
 
@@ -165,7 +115,7 @@ pub enum Error {
     #[error("{0}")]
     IO(#[from] std::io::Error),
     #[error("Could not parse manifest \"{0}\". Failed with error: {1}")]
-    ParsingManifest(String, serde_json::Error),
+    ParsingManifest(String, validation::ParseError),
     #[error("Interacting with buckets: {0}")]
     BucketError(#[from] buckets::Error),
     #[error("Interacting with git2: {0}")]
@@ -195,6 +145,12 @@ pub enum Error {
     HashError(#[from] hash::Error),
     #[error("Manifest does not have `autoupdate` field")]
     MissingAutoUpdate,
+    #[cfg(feature = "manifest-hashes")]
+    #[error("Autoupdating manifest: {0}")]
+    Autoupdate(#[from] autoupdate::Error),
+    #[cfg(feature = "manifest-hashes")]
+    #[error("Checking for updates: {0}")]
+    Checkver(#[from] checkver::Error),
     #[error("Manifest architecture section does not have `autoupdate` field")]
     MissingArchAutoUpdate,
     #[error("Commit did not have a parent")]
@@ -298,84 +254,30 @@ where
         let path = path.as_ref();
         let contents = std::fs::read_to_string(path)?;
 
-        Self::from_str(contents)
-            // TODO: Maybe figure out a better approach to this, but it works for now
-            .map(|s| s.with_name(path).with_bucket(path))
-            .map_err(|e| Error::ParsingManifest(path.display().to_string(), e))
+        Self::from_str(contents).map_err(|e| Error::ParsingManifest(path.display().to_string(), e))
     }
 
+    /// Parse a manifest from a string
+    ///
+    /// The returned error includes the JSON path to the value that failed to parse
+    ///
     /// # Errors
     /// - The contents are not a valid manifest
-    fn from_str(contents: String) -> serde_json::Result<Self> {
-        let trimmed = contents.trim_start_matches('\u{feff}');
-
-        serde_json::from_str(trimmed)
+    fn from_str(contents: String) -> Result<Self, validation::ParseError> {
+        validation::parse(&contents)
     }
-
-    /// Set the name of the manifest. Not meant to be used directly.
-    #[must_use]
-    fn with_name(self, path: impl AsRef<Path>) -> Self;
-
-    /// Set the bucket of the manifest. Not meant to be used directly.
-    #[must_use]
-    fn with_bucket(self, path: impl AsRef<Path>) -> Self;
 }
 
 impl CreateManifest for Manifest {
-    fn with_name(mut self, path: impl AsRef<Path>) -> Self {
-        let ext_stripped = path.as_ref().with_extension("");
-
-        let name = ext_stripped.file_name().map(|f| f.to_string_lossy());
-
-        if let Some(name) = name {
-            if name == "manifest" || name == "install" {
-                let mut path_buf = path.as_ref().to_path_buf();
-
-                if path_buf.pop() && path_buf.pop() {
-                    if let Some(name) = path_buf.file_name() {
-                        self.set_name(name.to_string_lossy());
-                    }
-                }
-            }
-
-            self.set_name(name);
-        }
-
-        self
-    }
-
-    fn with_bucket(mut self, path: impl AsRef<Path>) -> Self {
-        if let Some(bucket) = path
-            .as_ref()
-            .parent()
-            .and_then(|p| p.parent())
-            .and_then(|bucket| bucket.file_name().map(|f| f.to_string_lossy().to_string()))
-        {
-            self.set_bucket(bucket);
-        }
-
-        self
+    fn from_str(contents: String) -> Result<Self, validation::ParseError> {
+        validation::parse(&contents).map_err(|e| {
+            e.locate_flattened::<InstallConfig>(&contents, None)
+                .locate_flattened::<AutoupdateConfig>(&contents, Some("autoupdate"))
+        })
     }
 }
 
-impl CreateManifest for InstallManifest {
-    fn with_name(mut self, path: impl AsRef<Path>) -> Self {
-        if let Some(name) = path
-            .as_ref()
-            .with_extension("")
-            .file_name()
-            .map(|f| f.to_string_lossy())
-        {
-            self.set_name(name);
-        }
-
-        self
-    }
-
-    fn with_bucket(self, _path: impl AsRef<Path>) -> Self {
-        self
-    }
-}
+impl CreateManifest for InstallManifest {}
 
 impl InstallManifest {
     /// List all install manifests
@@ -383,7 +285,7 @@ impl InstallManifest {
     /// # Errors
     /// - Invalid install manifest
     /// - Reading directories fails
-    pub fn list_all(ctx: &impl ScoopContext) -> Result<Vec<Self>> {
+    pub fn list_all(ctx: &impl ScoopContext) -> Result<Vec<NamedInstallManifest>> {
         let installed_apps = ctx.installed_apps()?;
         {
             cfg_if::cfg_if! {
@@ -394,7 +296,7 @@ impl InstallManifest {
                 }
             }
         }
-        .map(|path| Self::from_path(path.join("current/install.json")))
+        .map(|path| Named::from_path(path.join("current/install.json")))
         .collect::<Result<Vec<_>>>()
     }
 
@@ -402,7 +304,7 @@ impl InstallManifest {
     ///
     /// # Errors
     /// - Reading directories fails
-    pub fn list_all_unchecked(ctx: &impl ScoopContext) -> Result<Vec<Self>> {
+    pub fn list_all_unchecked(ctx: &impl ScoopContext) -> Result<Vec<NamedInstallManifest>> {
         let installed_apps = ctx.installed_apps()?;
 
         Ok({
@@ -414,12 +316,7 @@ impl InstallManifest {
                 }
             }
         }
-        .filter_map(
-            |path| match Self::from_path(path.join("current/install.json")) {
-                Ok(v) => Some(v.with_name(path)),
-                Err(_) => None,
-            },
-        )
+        .filter_map(|path| Named::from_path(path.join("current/install.json")).ok())
         .collect::<Vec<_>>())
     }
 }
@@ -459,12 +356,63 @@ impl Manifest {
         )
     }
 
-    #[must_use]
-    /// Apply a bucket to a manifest
-    pub fn with_bucket(mut self, bucket: &Bucket) -> Self {
-        self.set_bucket(bucket.name());
+    /// Serialize the manifest in the same format as Scoop's `formatjson` script
+    ///
+    /// If `original` is provided, it should be the contents of the file this manifest was read from.
+    /// Unknown keys, key order, unchanged values and line endings will be preserved from it.
+    ///
+    /// # Errors
+    /// - The manifest could not be serialized
+    /// - The original manifest is not valid JSON
+    pub fn to_scoop_string(&self, original: Option<&str>) -> Result<String> {
+        const NAME: &str = "manifest";
+
+        let to_value = |manifest: &Manifest| {
+            serde_json::to_value(manifest)
+                .map_err(|e| Error::ParsingManifest(NAME.to_string(), e.into()))
+        };
+
+        let updated = to_value(self)?;
+
+        let (mut value, line_ending) = if let Some(original) = original {
+            let original_manifest = Self::from_str(original.to_string())
+                .map_err(|e| Error::ParsingManifest(NAME.to_string(), e))?;
+            let original_value = serde_json::from_str(original.trim_start_matches('\u{feff}'))
+                .map_err(|e| Error::ParsingManifest(NAME.to_string(), e.into()))?;
+
+            (
+                format::merge(&original_value, &to_value(&original_manifest)?, &updated),
+                format::LineEnding::detect(original),
+            )
+        } else {
+            (updated, format::LineEnding::default())
+        };
+
+        format::normalize(&mut value);
+
+        Ok(format::to_string(&value, line_ending))
+    }
+
+    /// Write the manifest to the given path in the same format as Scoop's `formatjson` script
+    ///
+    /// If a manifest already exists at the path, its unknown keys, key order and line endings are preserved.
+    ///
+    /// # Errors
+    /// - The existing manifest could not be read
+    /// - The manifest could not be serialized
+    /// - The manifest could not be written
+    pub fn write_to(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+
+        let original = if path.exists() {
+            Some(std::fs::read_to_string(path)?)
+        } else {
+            None
+        };
+
+        std::fs::write(path, self.to_scoop_string(original.as_deref())?)?;
 
-        self
+        Ok(())
     }
 
     #[must_use]
@@ -478,58 +426,29 @@ impl Manifest {
             .unwrap_or_default()
     }
 
-    /// Gets the manifest from a bucket and manifest name
-    ///
-    /// # Errors
-    /// - If the manifest doesn't exist or bucket is invalid
-    pub fn from_reference(
-        ctx: &impl ScoopContext,
-        (bucket, name): (String, String),
-    ) -> Result<Self> {
-        Bucket::from_name(ctx, bucket)?.get_manifest(name)
-    }
-
     #[must_use]
-    /// Check if the manifest binaries matche the given regex
-    pub fn binary_matches(&self, regex: &Regex, arch: Architecture) -> Option<Vec<String>> {
-        match self
-            .architecture
-            .as_ref()
-            .merge_default(self.install_config.clone(), arch)
-            .bin
-        {
-            Some(AliasArray::NestedArray(StringArray::Single(ref binary))) => {
-                if regex.is_match(binary) {
-                    Some(vec![binary.to_string()])
-                } else {
-                    None
-                }
-            }
-            Some(AliasArray::NestedArray(StringArray::Array(ref binaries))) => {
-                let matched: Vec<_> = binaries
-                    .iter()
-                    .filter(|binary| regex.is_match(binary))
-                    .cloned()
-                    .collect();
-
-                if matched.is_empty() {
-                    None
-                } else {
-                    Some(matched)
-                }
-            }
-            _ => None,
+    /// Get the binaries whose file name or alias matches the given regex
+    pub fn binary_matches(&self, regex: &Regex, arch: Architecture) -> Option<Vec<BinEntry>> {
+        let matched: Vec<_> = self
+            .bin_entries(arch)
+            .into_iter()
+            .filter(|entry| entry.matches(regex))
+            .collect();
+
+        if matched.is_empty() {
+            None
+        } else {
+            Some(matched)
         }
     }
+}
 
+impl NamedManifest {
     /// List all installed app manifests
     ///
     /// # Errors
     /// - Invalid install manifest
     /// - Reading directories fails
-    ///
-    /// # Panics
-    /// - If the file name is invalid
     pub fn list_installed(ctx: &impl ScoopContext) -> Result<Vec<Result<Self>>> {
         let installed_apps = ctx.installed_apps()?;
 
@@ -542,70 +461,21 @@ impl Manifest {
                 }
             }
         }
-        .map(|path| {
-            Self::from_path(path.join("current/manifest.json")).and_then(|mut manifest| {
-                manifest.set_name(
-                    path.file_name()
-                        .map(|f| f.to_string_lossy().to_string())
-                        .ok_or(Error::MissingFileName)?,
-                );
-
-                Ok(manifest)
-            })
-        })
+        .map(|path| Self::from_path(path.join("current/manifest.json")))
         .collect::<Vec<_>>())
     }
 
     #[must_use]
     /// Check if the manifest is installed
     pub fn is_installed(&self, ctx: &impl ScoopContext, bucket: Option<&str>) -> bool {
-        is_installed(ctx, unsafe { self.name() }, bucket)
-    }
-
-    fn update_field<T>(
-        arch_field: Option<&mut T>,
-        default_field: &mut Option<T>,
-        value: Option<T>,
-    ) {
-        let_chain!(let Some(arch_field) = arch_field; let Some(value) = value; {
-            *arch_field = value;
-        }; else {
-            *default_field = value;
-        });
-        // if let Some(arch_field) = arch_field
-        //     && let Some(value) = value
-        // {
-        //     *arch_field = value;
-        // } else {
-        //     *default_field = value;
-        // }
-    }
-
-    #[cfg(feature = "manifest-hashes")]
-    fn get_new_urls(&self, autoupdate: &AutoupdateConfig) -> Option<TOrArrayOfTs<String>> {
-        use crate::hash::substitutions::Substitute;
-
-        if let Some(autoupdate_urls) = &autoupdate.url {
-            debug!("Autoupdate Urls: {autoupdate_urls}");
-
-            let mut submap = SubstitutionMap::new();
-            submap.append_version(&self.version);
-
-            let new_urls = autoupdate_urls
-                .clone()
-                .to_vec()
-                .into_iter()
-                .map(|url| url.into_substituted(&submap, false));
-
-            Some(new_urls.collect())
-        } else {
-            None
-        }
+        is_installed(ctx, self.name(), bucket)
     }
 
     #[cfg(feature = "manifest-hashes")]
     /// Set the manifest version and get the hash for the manifest
     ///
+    /// The updated manifest is written to the workspace directory
+    ///
     /// # Errors
     /// - Missing autoupdate field
     /// - Hash error
@@ -614,10 +484,16 @@ impl Manifest {
         ctx: &impl ScoopContext,
         version: String,
     ) -> Result<(), Error> {
-        use quork::traits::list::ListVariants;
-
-        use crate::hash::Hash;
+        self.set_version_with_original(ctx, version, None).await
+    }
 
+    #[cfg(feature = "manifest-hashes")]
+    async fn set_version_with_original(
+        &mut self,
+        ctx: &impl ScoopContext,
+        version: String,
+        original: Option<String>,
+    ) -> Result<(), Error> {
         let version = version.into();
 
         if self.version == version {
@@ -625,89 +501,14 @@ impl Manifest {
             return Ok(());
         }
 
-        self.version = version;
-
-        let autoupdate = self.autoupdate.as_ref().ok_or(Error::MissingAutoUpdate)?;
-
-        // TODO: This sets the same hash and url for all architectures
-        for arch in crate::Architecture::VARIANTS {
-            let arch_autoupdate = autoupdate
-                .architecture
-                .merge_default(autoupdate.default_config.clone(), arch);
-
-            let arch_url = self.get_new_urls(&arch_autoupdate);
-
-            if let Some(arch_config) = &mut self.architecture {
-                Self::update_field(
-                    arch_field!(arch => arch_config.url as mut),
-                    &mut self.install_config.url,
-                    arch_url,
-                );
-            } else {
-                self.install_config.url = self.get_new_urls(&autoupdate.default_config);
-            }
-        }
-
-        macro_rules! update_field {
-            ($field:ident) => {{
-                let mut submap = SubstitutionMap::new();
-                submap.append_version(&self.version);
-
-                for arch in crate::Architecture::VARIANTS {
-                    if let Some(config) = &mut self.architecture {
-                        let default = arch_field!(arch => config.$field as cloned).map(|s| s.into_substituted(&submap, false));
-
-                        Self::update_field(
-                            arch_field!(arch => config.$field as mut),
-                            &mut self.install_config.$field,
-                            default,
-                        );
-                    } else {
-                        self.install_config.$field = autoupdate.default_config.$field.clone().map(|s| s.into_substituted(&submap, false));
-                    }
-                }
-            }}
-        }
+        self.autoupdate(ctx, version, &HashMap::new()).await?;
 
-        update_field!(bin);
-        update_field!(extract_dir);
-        update_field!(installer);
-        update_field!(shortcuts);
-
-        for arch in crate::Architecture::VARIANTS {
-            let Ok(hashes) = Hash::get_for_app(ctx, self, arch).await else {
-                continue;
-            };
-
-            if let Some(arch_config) = &mut self.architecture {
-                // TODO: This sets the same hash and url for all architectures
-                Self::update_field(
-                    arch_field!(arch => arch_config.hash as mut),
-                    &mut self.install_config.hash,
-                    TOrArrayOfTs::from_vec(hashes),
-                );
-            } else {
-                self.install_config.hash = TOrArrayOfTs::from_vec(hashes);
-            }
-        }
-
-        // TODO: Handle other autoupdate fields
-        // TODO: Autoupdate fields in all architectures
-        // todo!("Handle urls and other autoupdate fields");
-
-        // TODO: Figure out hash extraction
-        // autoupdate_arch.hash
-
-        // todo!()
-
-        let workspace_manifest_path = ctx
-            .workspace_path()
-            .join(format!("{}.json", unsafe { self.name() }));
-        serde_json::to_writer_pretty(std::fs::File::create(workspace_manifest_path)?, &self)
-            .map_err(|e| {
-                error!("Failed to write workspace manifest: {e}");
-                Error::ParsingManifest(unsafe { self.name() }.to_string(), e)
-            })?;
+        let workspace_manifest_path = ctx.workspace_path().join(format!("{}.json", self.name()));
+        let contents = self.to_scoop_string(original.as_deref()).map_err(|e| {
+            error!("Failed to write workspace manifest: {e}");
+            e
+        })?;
+        std::fs::write(workspace_manifest_path, contents)?;
 
         Ok(())
     }
@@ -716,10 +517,7 @@ impl Manifest {
     /// Check if the commit's message matches the name of the manifest
     pub fn commit_message_matches(&self, commit: &gix::Commit<'_>) -> bool {
         if let Ok(message) = commit.message() {
-            message
-                .summary()
-                .to_string()
-                .starts_with(unsafe { self.name() })
+            message.summary().to_string().starts_with(self.name())
         } else {
             false
         }
@@ -748,11 +546,7 @@ impl Manifest {
             .map_err(GitoxideError::from)?
             .track_filename()
             .for_each_to_obtain_tree(&parent_tree, |change| {
-                if change
-                    .location
-                    .to_string()
-                    .starts_with(unsafe { self.name() })
-                {
+                if change.location.to_string().starts_with(self.name()) {
                     changed = true;
                     return Ok::<_, GitoxideError>(Action::Cancel);
                 }
@@ -766,6 +560,73 @@ impl Manifest {
         Ok(changed)
     }
 
+    /// Get [`InstallManifest`] for [`Manifest`]
+    ///
+    /// # Errors
+    /// - Missing or invalid [`InstallManifest`]
+    pub fn install_manifest(&self, ctx: &impl ScoopContext) -> Result<NamedInstallManifest> {
+        let apps_path = ctx.apps_path();
+        let install_path = apps_path
+            .join(self.name())
+            .join("current")
+            .join("install.json");
+
+        debug!("Getting install manifest for {}", install_path.display());
+
+        Named::from_path(install_path)
+    }
+}
+
+impl BucketManifest {
+    /// Gets the manifest from a bucket and manifest name
+    ///
+    /// # Errors
+    /// - If the manifest doesn't exist or bucket is invalid
+    pub fn from_reference(
+        ctx: &impl ScoopContext,
+        (bucket, name): (String, String),
+    ) -> Result<Self> {
+        Bucket::from_name(ctx, bucket)?.get_manifest(name)
+    }
+
+    #[cfg(feature = "manifest-hashes")]
+    /// Set the manifest version and get the hash for the manifest
+    ///
+    /// The updated manifest is written to the workspace directory,
+    /// keeping any unknown keys and formatting from the bucket's copy of the manifest
+    ///
+    /// # Errors
+    /// - Missing autoupdate field
+    /// - Hash error
+    pub async fn set_version(
+        &mut self,
+        ctx: &impl ScoopContext,
+        version: String,
+    ) -> Result<(), Error> {
+        let original = Bucket::from_name(ctx, self.bucket())
+            .ok()
+            .and_then(|bucket| std::fs::read_to_string(bucket.get_manifest_path(self.name())).ok());
+
+        (**self)
+            .set_version_with_original(ctx, version, original)
+            .await
+    }
+
+    /// Update the manifest in place inside its bucket
+    ///
+    /// Returns the path the manifest was written to
+    ///
+    /// # Errors
+    /// - The bucket does not exist
+    /// - See [`Manifest::write_to`]
+    pub fn write_to_bucket(&self, ctx: &impl ScoopContext) -> Result<std::path::PathBuf> {
+        let path = Bucket::from_name(ctx, self.bucket())?.get_manifest_path(self.name());
+
+        self.write_to(&path)?;
+
+        Ok(path)
+    }
+
     /// Get the time and author of the commit where this manifest was last changed
     ///
     /// # Errors
@@ -776,7 +637,7 @@ impl Manifest {
         &self,
         ctx: &impl ScoopContext,
     ) -> Result<(Option<DateTime<FixedOffset>>, Option<Signature>)> {
-        let bucket = Bucket::from_name(ctx, unsafe { self.bucket() })?;
+        let bucket = Bucket::from_name(ctx, self.bucket())?;
 
         let repo = Repo::from_bucket(&bucket)?;
         let gitoxide = repo.gitoxide();
@@ -816,11 +677,7 @@ impl Manifest {
                             debug!("{change:?}");
                             debug!("Filename: {}", change.location.to_string());
 
-                            if change
-                                .location
-                                .to_string()
-                                .starts_with(unsafe { self.name() })
-                            {
+                            if change.location.to_string().starts_with(self.name()) {
                                 matches = true;
                                 Ok::<_, Error>(Action::Cancel)
                             } else {
@@ -859,22 +716,6 @@ impl Manifest {
 
         Ok((Some(date_time), Some(author_wrapped)))
     }
-
-    /// Get [`InstallManifest`] for [`Manifest`]
-    ///
-    /// # Errors
-    /// - Missing or invalid [`InstallManifest`]
-    pub fn install_manifest(&self, ctx: &impl ScoopContext) -> Result<InstallManifest> {
-        let apps_path = ctx.apps_path();
-        let install_path = apps_path
-            .join(unsafe { self.name() })
-            .join("current")
-            .join("install.json");
-
-        debug!("Getting install manifest for {}", install_path.display());
-
-        InstallManifest::from_path(install_path)
-    }
 }
 
 /// Check if the manifest path is installed, and optionally confirm the bucket
@@ -950,6 +791,8 @@ impl MergeDefaults for Option<&ManifestArchitecture> {
         InstallConfig {
             bin: config.bin.or(default.bin),
             checkver: config.checkver.or(default.checkver),
+            env_add_path: config.env_add_path.or(default.env_add_path),
+            env_set: config.env_set.or(default.env_set),
             extract_dir: config.extract_dir.or(default.extract_dir),
             #[cfg(feature = "manifest-hashes")]
             hash: config.hash.or(default.hash),
@@ -991,7 +834,12 @@ impl HashExtractionOrArrayOfHashExtractions {
 mod tests {
     use std::error::Error;
 
-    use crate::{buckets::Bucket, contexts::User, Architecture};
+    use crate::{
+        buckets::Bucket,
+        contexts::{testing::TestContext, ScoopContext, User},
+        packages::models::manifest::TOrArrayOfTs,
+        Architecture,
+    };
 
     use rayon::prelude::*;
 
@@ -1005,23 +853,52 @@ mod tests {
             .into_par_iter()
             .flat_map(|bucket| bucket.list_packages())
             .flatten()
-            .filter(|manifest| !UNSUPPORTED_PACKAGES.contains(&unsafe { manifest.name() }))
+            .filter(|manifest| !UNSUPPORTED_PACKAGES.contains(&manifest.name()))
             .filter(|manifest| manifest.autoupdate_config(Architecture::ARCH).is_some())
             .collect::<Vec<_>>();
 
         manifests.par_iter().for_each(|manifest| {
-            assert!(!unsafe { manifest.name() }.is_empty());
-            assert!(!unsafe { manifest.bucket() }.is_empty());
+            assert!(!manifest.name().is_empty());
+            assert!(!manifest.bucket().is_empty());
 
             if let Some(autoupdate_config) = &manifest.autoupdate_config(Architecture::ARCH) {
                 assert!(
                     autoupdate_config.url.is_some(),
                     "URL is missing in package: {}",
-                    unsafe { manifest.name() }
+                    manifest.name()
                 );
             }
         });
 
         Ok(())
     }
+
+    #[test]
+    fn test_write_manifest_to_bucket() -> Result<(), Box<dyn Error>> {
+        const ORIGINAL: &str = "{\r\n    \"version\": \"1.0.0\",\r\n    \"x-maintainer\": \"someone\",\r\n    \"url\": \"https://example.com/1.0.0.zip\",\r\n    \"bin\": \"app.exe\"\r\n}\r\n";
+
+        let ctx = TestContext::new("write-manifest");
+
+        let bucket_path = ctx.buckets_path().join("main");
+        std::fs::create_dir_all(bucket_path.join("bucket"))?;
+        std::fs::write(bucket_path.join("bucket").join("app.json"), ORIGINAL)?;
+
+        let bucket = Bucket::from_path(&bucket_path)?;
+        let mut manifest = bucket.get_manifest("app")?;
+        manifest.version = "2.0.0".to_string().into();
+        manifest.install_config.url = Some(TOrArrayOfTs::Single(
+            "https://example.com/2.0.0.zip".to_string(),
+        ));
+
+        let path = manifest.write_to_bucket(&ctx)?;
+
+        assert_eq!(
+            std::fs::read_to_string(path)?,
+            ORIGINAL.replace("1.0.0", "2.0.0")
+        );
+
+        ctx.cleanup();
+
+        Ok(())
+    }
 }
@@ -0,0 +1,485 @@
+//! Manifest autoupdate
+//!
+//! Applies a manifest's `autoupdate` section for a new version, following Scoop's `autoupdate.ps1`.
+//!
+//! Urls and hashes are computed independently for each architecture,
+//! and the other autoupdatable fields are updated wherever the manifest defines them.
+//! The manifest is only modified once every hash has been obtained.
+
+use std::collections::HashMap;
+
+use quork::traits::list::ListVariants;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    contexts::ScoopContext,
+    hash::{
+        self,
+        substitutions::{Substitute, SubstitutionMap},
+        Hash,
+    },
+    version::Version,
+    Architecture,
+};
+
+use super::{
+    models::manifest::{
+        Autoupdate, AutoupdateLicense, InstallConfig, PackageLicense, Psmodule, TOrArrayOfTs,
+    },
+    Manifest, MergeDefaults, NamedManifest,
+};
+
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+/// Autoupdate errors
+pub enum Error {
+    #[error("Manifest does not have `autoupdate` field")]
+    MissingAutoUpdate,
+    #[error("Missing autoupdate url{}", .0.map(|arch| format!(" for {arch}")).unwrap_or_default())]
+    MissingUrl(Option<Architecture>),
+    #[error("Could not get hash for {url}: {source}")]
+    Hash {
+        url: String,
+        #[source]
+        source: hash::Error,
+    },
+    #[error("Comparing manifests: {0}")]
+    Serde(#[from] serde_json::Error),
+}
+
+/// Autoupdate result type
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+/// A single field changed by an autoupdate
+pub struct Change {
+    /// The path of the field in the manifest (i.e `architecture.64bit.url`)
+    pub path: String,
+    /// The previous value, or [`None`] if the field was added
+    pub old: Option<Value>,
+    /// The new value, or [`None`] if the field was removed
+    pub new: Option<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+/// A report of the changes made by an autoupdate
+pub struct Report {
+    /// The version before the update
+    pub old_version: Version,
+    /// The version after the update
+    pub new_version: Version,
+    /// The fields that changed, ordered by field name
+    pub changes: Vec<Change>,
+}
+
+impl Report {
+    #[must_use]
+    /// Check if the autoupdate changed nothing
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    #[must_use]
+    /// Get the change for the field at the given path, if it changed
+    pub fn get(&self, path: &str) -> Option<&Change> {
+        self.changes.iter().find(|change| change.path == path)
+    }
+}
+
+/// Where a set of urls and hashes lives in the manifest
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Target {
+    Root,
+    Arch(Architecture),
+}
+
+impl Target {
+    fn arch(self) -> Option<Architecture> {
+        match self {
+            Target::Root => None,
+            Target::Arch(arch) => Some(arch),
+        }
+    }
+}
+
+impl NamedManifest {
+    /// Update the manifest to the given version using its `autoupdate` section
+    ///
+    /// `matches` are the named captures from `checkver`, available as `$match<Name>` substitutions.
+    ///
+    /// If any hash cannot be obtained, an error is returned and the manifest is left unchanged.
+    ///
+    /// # Errors
+    /// - Missing autoupdate field
+    /// - Missing autoupdate url
+    /// - Any hash could not be obtained
+    pub async fn autoupdate(
+        &mut self,
+        ctx: &impl ScoopContext,
+        version: impl Into<Version>,
+        matches: &HashMap<String, String>,
+    ) -> Result<Report> {
+        let version = version.into();
+
+        let updated = apply(ctx, self.name(), self, version, matches).await?;
+        let report = Report {
+            old_version: self.version.clone(),
+            new_version: updated.version.clone(),
+            changes: diff(
+                &serde_json::to_value(&**self)?,
+                &serde_json::to_value(&updated)?,
+            ),
+        };
+
+        **self = updated;
+
+        Ok(report)
+    }
+}
+
+async fn apply(
+    ctx: &impl ScoopContext,
+    name: &str,
+    manifest: &Manifest,
+    version: Version,
+    matches: &HashMap<String, String>,
+) -> Result<Manifest> {
+    let autoupdate = manifest
+        .autoupdate
+        .as_ref()
+        .ok_or(Error::MissingAutoUpdate)?;
+
+    let mut submap = SubstitutionMap::new();
+    submap.append_version(&version);
+    submap.append_matches(matches);
+
+    let mut updated = manifest.clone();
+    updated.version = version;
+
+    // Urls and hashes are required, and computed per architecture unless the manifest has a root url
+    let targets = if manifest.install_config.url.is_some() {
+        vec![(Target::Root, autoupdate.default_config.clone())]
+    } else {
+        present_archs(manifest)
+            .map(|arch| {
+                let config = autoupdate
+                    .architecture
+                    .merge_default(autoupdate.default_config.clone(), arch);
+
+                (Target::Arch(arch), config)
+            })
+            .collect()
+    };
+
+    let hashed = futures::future::try_join_all(targets.iter().map(|(target, config)| {
+        let submap = &submap;
+        let version = &updated.version;
+
+        async move {
+            let urls = config
+                .url
+                .clone()
+                .ok_or(Error::MissingUrl(target.arch()))?
+                .into_substituted(submap, false)
+                .to_vec();
+
+            let hashes = futures::future::try_join_all(urls.iter().map(|url| async move {
+                Hash::get_for_autoupdate_url(ctx, name, version, url, config, submap)
+                    .await
+                    .map_err(|source| Error::Hash {
+                        url: url.clone(),
+                        source,
+                    })
+            }))
+            .await?;
+
+            Ok::<_, Error>((*target, urls, hashes))
+        }
+    }))
+    .await?;
+
+    for (target, urls, hashes) in hashed {
+        let config = target_config(&mut updated, target);
+
+        config.url = TOrArrayOfTs::from_vec(urls);
+        config.hash = TOrArrayOfTs::from_vec(hashes);
+    }
+
+    update_fields(&mut updated, autoupdate, &submap);
+
+    Ok(updated)
+}
+
+/// Update the autoupdatable fields other than urls and hashes
+///
+/// Fields are only updated where the manifest already defines them, like Scoop
+fn update_fields(manifest: &mut Manifest, autoupdate: &Autoupdate, submap: &SubstitutionMap) {
+    let archs = present_archs(manifest).collect::<Vec<_>>();
+
+    macro_rules! update_install_field {
+        ($field:ident) => {{
+            if manifest.install_config.$field.is_some() {
+                if let Some(value) = &autoupdate.default_config.$field {
+                    manifest.install_config.$field =
+                        Some(value.clone().into_substituted(submap, false));
+                }
+            } else {
+                for &arch in &archs {
+                    let arch_config = autoupdate
+                        .architecture
+                        .merge_default(autoupdate.default_config.clone(), arch);
+
+                    let config = target_config(manifest, Target::Arch(arch));
+
+                    if config.$field.is_some() {
+                        if let Some(value) = arch_config.$field {
+                            config.$field = Some(value.into_substituted(submap, false));
+                        }
+                    }
+                }
+            }
+        }};
+    }
+
+    update_install_field!(bin);
+    update_install_field!(env_add_path);
+    update_install_field!(env_set);
+    update_install_field!(extract_dir);
+    update_install_field!(installer);
+    update_install_field!(shortcuts);
+
+    if manifest.notes.is_some() {
+        if let Some(notes) = &autoupdate.notes {
+            manifest.notes = Some(notes.clone().into_substituted(submap, false));
+        }
+    }
+
+    if manifest.persist.is_some() {
+        if let Some(persist) = &autoupdate.persist {
+            manifest.persist = Some(persist.clone().into_substituted(submap, false));
+        }
+    }
+
+    if manifest.license.is_some() {
+        if let Some(license) = &autoupdate.license {
+            manifest.license = Some(match license.clone().into_substituted(submap, false) {
+                AutoupdateLicense::License(license) => PackageLicense::License(license),
+                AutoupdateLicense::String(license) => PackageLicense::String(license),
+            });
+        }
+    }
+
+    if manifest.psmodule.is_some() {
+        if let Some(psmodule) = &autoupdate.psmodule {
+            let psmodule = psmodule.clone().into_substituted(submap, false);
+            manifest.psmodule = Some(Psmodule {
+                name: psmodule.name,
+            });
+        }
+    }
+}
+
+/// The architectures defined in the manifest's `architecture` section
+fn present_archs(manifest: &Manifest) -> impl Iterator<Item = Architecture> + '_ {
+    Architecture::VARIANTS.into_iter().filter(|arch| {
+        manifest
+            .architecture
+            .as_ref()
+            .is_some_and(|architecture| architecture[*arch].is_some())
+    })
+}
+
+fn target_config(manifest: &mut Manifest, target: Target) -> &mut InstallConfig {
+    match target {
+        Target::Root => &mut manifest.install_config,
+        Target::Arch(arch) => manifest
+            .architecture
+            .as_mut()
+            .and_then(|architecture| architecture[arch].as_mut())
+            .expect("architecture present in manifest"),
+    }
+}
+
+/// List the changed fields between two manifests
+///
+/// The root object, `architecture`, and each architecture are compared field by field.
+/// Other values are compared as a whole.
+fn diff(old: &Value, new: &Value) -> Vec<Change> {
+    fn diff_object(path: &str, old: &Value, new: &Value, depth: usize, changes: &mut Vec<Change>) {
+        let empty = serde_json::Map::new();
+        let old_map = old.as_object().unwrap_or(&empty);
+        let new_map = new.as_object().unwrap_or(&empty);
+
+        let keys = old_map
+            .keys()
+            .chain(new_map.keys().filter(|key| !old_map.contains_key(*key)));
+
+        for key in keys {
+            let field_path = if path.is_empty() {
+                key.clone()
+            } else {
+                format!("{path}.{key}")
+            };
+
+            match (old_map.get(key), new_map.get(key)) {
+                (Some(old), Some(new)) if old == new => {}
+                (Some(old), Some(new))
+                    if old.is_object()
+                        && new.is_object()
+                        && (depth == 1 || (depth == 0 && key == "architecture")) =>
+                {
+                    diff_object(&field_path, old, new, depth + 1, changes);
+                }
+                (old, new) => changes.push(Change {
+                    path: field_path,
+                    old: old.cloned(),
+                    new: new.cloned(),
+                }),
+            }
+        }
+    }
+
+    let mut changes = vec![];
+    diff_object("", old, new, 0, &mut changes);
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        contexts::testing::TestContext,
+        packages::{models::manifest::StringArray, CreateManifest, Named},
+        requests::testing::serve,
+    };
+
+    const HASH_32: &str = "1111111111111111111111111111111111111111111111111111111111111111";
+    const HASH_64: &str = "6464646464646464646464646464646464646464646464646464646464646464";
+
+    fn manifest(base_url: &str) -> NamedManifest {
+        let manifest = json!({
+            "version": "1.0.0",
+            "notes": "Version 1.0.0",
+            "license": "Freeware",
+            "architecture": {
+                "64bit": {
+                    "url": format!("{base_url}/1.0.0/app-x64.zip"),
+                    "hash": HASH_64,
+                    "extract_dir": "app-1.0.0-x64",
+                },
+                "32bit": {
+                    "url": format!("{base_url}/1.0.0/app-x86.zip"),
+                    "hash": HASH_32,
+                }
+            },
+            "autoupdate": {
+                "notes": "Version $version ($matchBuild)",
+                "persist": "data-$version",
+                "hash": {
+                    "url": "$url.sha256"
+                },
+                "architecture": {
+                    "64bit": {
+                        "url": format!("{base_url}/$version/app-x64.zip"),
+                        "extract_dir": "app-$version-x64",
+                    },
+                    "32bit": {
+                        "url": format!("{base_url}/$version/app-x86.zip"),
+                    }
+                }
+            }
+        });
+
+        Named::new(
+            "app",
+            Manifest::from_str(manifest.to_string()).expect("valid manifest"),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_autoupdate_per_architecture() {
+        const HASH_32_NEW: &str =
+            "3232323232323232323232323232323232323232323232323232323232323232";
+        const HASH_64_NEW: &str =
+            "abababababababababababababababababababababababababababababababab";
+
+        let base_url = serve(&[
+            ("/2.0.0/app-x64.zip.sha256", HASH_64_NEW),
+            ("/2.0.0/app-x86.zip.sha256", HASH_32_NEW),
+        ]);
+        let ctx = TestContext::new("autoupdate");
+
+        let mut manifest = manifest(&base_url);
+        let report = manifest
+            .autoupdate(
+                &ctx,
+                "2.0.0".to_string(),
+                &HashMap::from([("build".to_string(), "1234".to_string())]),
+            )
+            .await
+            .unwrap();
+
+        let architecture = manifest.architecture.as_ref().unwrap();
+        let x64 = architecture.x64.as_ref().unwrap();
+        let x86 = architecture.x86.as_ref().unwrap();
+
+        assert_eq!(
+            x64.url,
+            Some(StringArray::Single(format!("{base_url}/2.0.0/app-x64.zip")))
+        );
+        assert_eq!(x64.hash.as_ref().unwrap().to_string(), HASH_64_NEW);
+        assert_eq!(x86.hash.as_ref().unwrap().to_string(), HASH_32_NEW);
+        assert_eq!(
+            x64.extract_dir,
+            Some(StringArray::Single("app-2.0.0-x64".to_string()))
+        );
+        assert_eq!(
+            manifest.notes,
+            Some(StringArray::Single("Version 2.0.0 (1234)".to_string()))
+        );
+        // The manifest does not define `persist`, so it is not added
+        assert_eq!(manifest.persist, None);
+
+        assert_eq!(report.new_version.as_str(), "2.0.0");
+        for path in [
+            "version",
+            "notes",
+            "architecture.64bit.url",
+            "architecture.64bit.hash",
+            "architecture.64bit.extract_dir",
+            "architecture.32bit.url",
+            "architecture.32bit.hash",
+        ] {
+            assert!(report.get(path).is_some(), "missing change for {path}");
+        }
+        assert!(report.get("license").is_none());
+
+        ctx.cleanup();
+    }
+
+    #[tokio::test]
+    async fn test_autoupdate_fails_without_partial_update() {
+        // Only the 64bit hash is available
+        let base_url = serve(&[("/2.0.0/app-x64.zip.sha256", HASH_64)]);
+        let ctx = TestContext::new("autoupdate-partial");
+
+        let mut manifest = manifest(&base_url);
+        let original = manifest.clone();
+
+        let error = manifest
+            .autoupdate(&ctx, "2.0.0".to_string(), &HashMap::new())
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(&error, Error::Hash { url, .. } if url.ends_with("/2.0.0/app-x86.zip")),
+            "unexpected error: {error}"
+        );
+        assert_eq!(manifest, original);
+
+        ctx.cleanup();
+    }
+}
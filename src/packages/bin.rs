@@ -0,0 +1,202 @@
+//! Package binaries
+//!
+//! Resolves a manifest's `bin` field into the shims Scoop creates for it
+
+use std::path::Path;
+
+use regex::Regex;
+use serde::Serialize;
+
+use crate::Architecture;
+
+use super::{
+    models::manifest::{AliasArray, TOrArrayOfTs},
+    Manifest,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+/// A binary that will be shimmed
+pub struct BinEntry {
+    /// The path to the binary, relative to the app directory
+    pub target: String,
+    /// The shim name
+    ///
+    /// Defaults to the target's file name without its extension
+    pub alias: String,
+    /// Arguments the shim passes to the binary before any user provided arguments
+    pub args: Vec<String>,
+}
+
+impl BinEntry {
+    #[must_use]
+    /// Create a binary entry for a target, with the default alias and no arguments
+    pub fn new(target: impl Into<String>) -> Self {
+        let target = target.into();
+        let alias = default_alias(&target);
+
+        Self {
+            target,
+            alias,
+            args: vec![],
+        }
+    }
+
+    /// Parse a single item of a manifest's `bin` field
+    ///
+    /// Returns [`None`] for an empty array
+    fn from_item(item: TOrArrayOfTs<String>) -> Option<Self> {
+        let mut parts = item.to_vec().into_iter();
+
+        let mut entry = Self::new(parts.next()?);
+        if let Some(alias) = parts.next().filter(|alias| !alias.is_empty()) {
+            entry.alias = alias;
+        }
+        entry.args = parts.collect();
+
+        Some(entry)
+    }
+
+    #[must_use]
+    /// Check if the target's file name or the alias matches the given regex
+    pub fn matches(&self, regex: &Regex) -> bool {
+        regex.is_match(file_name(&self.target)) || regex.is_match(&self.alias)
+    }
+
+    #[must_use]
+    /// Get the contents of the `.shim` file for this binary, installed in the given app directory
+    pub fn shim_contents(&self, app_dir: impl AsRef<Path>) -> String {
+        let path = app_dir.as_ref().join(&self.target);
+        let mut contents = format!("path = \"{}\"\r\n", path.display());
+
+        if !self.args.is_empty() {
+            contents.push_str(&format!("args = {}\r\n", self.args.join(" ")));
+        }
+
+        contents
+    }
+}
+
+fn file_name(target: &str) -> &str {
+    target.rsplit(['/', '\\']).next().unwrap_or(target)
+}
+
+fn default_alias(target: &str) -> String {
+    let name = file_name(target);
+
+    match name.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem.to_string(),
+        _ => name.to_string(),
+    }
+}
+
+impl AliasArray<String> {
+    #[must_use]
+    /// Resolve the binary entries, as Scoop does for the `bin` field
+    pub fn bin_entries(&self) -> Vec<BinEntry> {
+        match self {
+            AliasArray::NestedArray(TOrArrayOfTs::Single(target)) => {
+                vec![BinEntry::new(target.clone())]
+            }
+            AliasArray::NestedArray(TOrArrayOfTs::Array(targets)) => {
+                targets.iter().cloned().map(BinEntry::new).collect()
+            }
+            AliasArray::AliasArray(items) => items
+                .iter()
+                .cloned()
+                .filter_map(BinEntry::from_item)
+                .collect(),
+        }
+    }
+}
+
+impl Manifest {
+    #[must_use]
+    /// Get the binaries for a given architecture
+    pub fn bin_entries(&self, arch: Architecture) -> Vec<BinEntry> {
+        self.install_config(arch)
+            .bin
+            .map(|bin| bin.bin_entries())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_bin_entries() {
+        let manifest: Manifest = serde_json::from_value(json!({
+            "version": "1.0.0",
+            "bin": [
+                "bin/app.exe",
+                ["bin/app.exe", "app-alias", "--flag", "--other"],
+                ["tool.ps1", ""],
+                ["lib\\helper.cmd"],
+            ],
+            "architecture": {
+                "arm64": { "bin": "arm/app.exe" },
+            },
+        }))
+        .unwrap();
+
+        assert_eq!(
+            manifest.bin_entries(Architecture::X64),
+            [
+                BinEntry::new("bin/app.exe"),
+                BinEntry {
+                    target: "bin/app.exe".to_string(),
+                    alias: "app-alias".to_string(),
+                    args: vec!["--flag".to_string(), "--other".to_string()],
+                },
+                BinEntry {
+                    target: "tool.ps1".to_string(),
+                    alias: "tool".to_string(),
+                    args: vec![],
+                },
+                BinEntry {
+                    target: "lib\\helper.cmd".to_string(),
+                    alias: "helper".to_string(),
+                    args: vec![],
+                },
+            ]
+        );
+        assert_eq!(
+            manifest.bin_entries(Architecture::Arm64),
+            [BinEntry::new("arm/app.exe")]
+        );
+
+        let regex = Regex::new("alias").unwrap();
+        assert_eq!(
+            manifest.binary_matches(&regex, Architecture::X64),
+            Some(vec![manifest.bin_entries(Architecture::X64)[1].clone()])
+        );
+        assert_eq!(manifest.binary_matches(&regex, Architecture::Arm64), None);
+    }
+
+    #[test]
+    fn test_shim_contents() {
+        let entry = BinEntry {
+            target: "bin/app.exe".to_string(),
+            alias: "app".to_string(),
+            args: vec!["--flag".to_string(), "-v".to_string()],
+        };
+
+        assert_eq!(
+            entry.shim_contents("apps"),
+            format!(
+                "path = \"{}\"\r\nargs = --flag -v\r\n",
+                Path::new("apps").join("bin/app.exe").display()
+            )
+        );
+        assert_eq!(
+            BinEntry::new("app.exe").shim_contents("apps"),
+            format!(
+                "path = \"{}\"\r\n",
+                Path::new("apps").join("app.exe").display()
+            )
+        );
+    }
+}
@@ -0,0 +1,464 @@
+//! Manifest checkver
+//!
+//! Resolves the latest upstream version of a manifest from its `checkver` section, following Scoop's `checkver.ps1`.
+//!
+//! Note that regexes are evaluated with the [`regex`] crate, so .NET only features (i.e lookarounds) are not supported.
+
+use std::collections::HashMap;
+
+use regex::Regex;
+use reqwest::{header::USER_AGENT, StatusCode};
+use serde::Serialize;
+
+use crate::{
+    contexts::ScoopContext,
+    hash::{
+        formats::{
+            json::{self, parse_json},
+            xml::{parse_xml, XMLError},
+        },
+        substitutions::{Substitute, SubstitutionMap},
+    },
+    requests::Client,
+    scripts::{self, PowershellScript},
+    version::Version,
+};
+
+use super::{
+    models::manifest::{Checkver, CheckverClass, SourceforgeUnion},
+    Manifest,
+};
+
+/// The regex used to find the version on a GitHub releases page
+const GITHUB_REGEX: &str = r"/releases/tag/(?:v|V)?([\d.]+)";
+
+/// The regex used to find the version in a `SourceForge` RSS feed, if none is provided
+const SOURCEFORGE_REGEX: &str = r"(\d+(?:\.\d+)*)";
+
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+/// Checkver errors
+pub enum Error {
+    #[error("Manifest does not have `checkver` field")]
+    MissingCheckver,
+    #[error(
+        "Could not determine a url to check. The manifest has no `checkver.url` or `homepage`"
+    )]
+    MissingUrl,
+    #[error("Invalid regex: {0}")]
+    Regex(#[from] regex::Error),
+    #[error("Requesting page: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("HTTP error: {0}")]
+    ErrorStatus(StatusCode),
+    #[error("Parsing json: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+    #[error("Finding json path: {0}")]
+    JsonPath(#[from] json::Error),
+    #[error("Finding xpath: {0}")]
+    XPath(#[from] XMLError),
+    #[error("Running checkver script: {0}")]
+    Script(#[from] scripts::Error),
+    #[error("Could not match regex \"{0}\"")]
+    NoMatch(String),
+    #[error("Could not find a version")]
+    EmptyVersion,
+}
+
+/// Checkver result type
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+/// The latest version found by checkver
+pub struct Latest {
+    /// The latest version
+    pub version: Version,
+    /// The regex captures, by name or index (i.e `1` or `build`)
+    ///
+    /// These can be passed to [`NamedManifest::autoupdate`](super::NamedManifest::autoupdate),
+    /// where they are available as `$match<Name>` substitutions
+    pub matches: HashMap<String, String>,
+}
+
+/// A `checkver` section, resolved into what to request and how to find the version
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Query {
+    url: String,
+    regex: Option<String>,
+    jsonpath: Option<String>,
+    xpath: Option<String>,
+    replace: Option<String>,
+    reverse: bool,
+    useragent: Option<String>,
+    script: Option<String>,
+}
+
+impl Query {
+    fn from_manifest(manifest: &Manifest) -> Result<Self> {
+        let checkver = manifest
+            .install_config
+            .checkver
+            .as_ref()
+            .ok_or(Error::MissingCheckver)?;
+        let homepage = manifest.homepage.clone();
+
+        let query = match checkver {
+            Checkver::String(checkver) if checkver == "github" => Self {
+                url: github_url(&homepage.ok_or(Error::MissingUrl)?),
+                regex: Some(GITHUB_REGEX.to_string()),
+                ..Default::default()
+            },
+            // Any other string is a regex to match against the homepage
+            Checkver::String(regex) => Self {
+                url: homepage.ok_or(Error::MissingUrl)?,
+                regex: Some(regex.clone()),
+                ..Default::default()
+            },
+            Checkver::CheckverClass(checkver) => Self::from_class(checkver.as_ref(), homepage)?,
+        };
+
+        Ok(query)
+    }
+
+    fn from_class(checkver: &CheckverClass, homepage: Option<String>) -> Result<Self> {
+        let mut default_url = homepage;
+        let mut default_regex = None;
+
+        if let Some(github) = &checkver.github {
+            default_url = Some(github_url(github));
+            default_regex = Some(GITHUB_REGEX.to_string());
+        }
+
+        if let Some(sourceforge) = &checkver.sourceforge {
+            let (project, path) = match sourceforge {
+                SourceforgeUnion::String(sourceforge) => {
+                    let (project, path) = sourceforge.split_once('/').unwrap_or((sourceforge, ""));
+                    (project.to_string(), path.to_string())
+                }
+                SourceforgeUnion::SourceforgeClass(sourceforge) => (
+                    sourceforge.project.clone().unwrap_or_default(),
+                    sourceforge.path.clone().unwrap_or_default(),
+                ),
+            };
+            let path = path.trim_matches('/');
+
+            default_url = Some(format!(
+                "https://sourceforge.net/projects/{project}/rss?path=/{path}"
+            ));
+
+            let version_regex = checkver
+                .regex
+                .as_ref()
+                .or(checkver.re.as_ref())
+                .map_or(SOURCEFORGE_REGEX, String::as_str);
+            let path = if path.is_empty() {
+                String::new()
+            } else {
+                format!("{}/", regex::escape(path))
+            };
+
+            default_regex = Some(format!(r"CDATA\[/{path}.*?{version_regex}.*?\]\]"));
+        }
+
+        let regex = if checkver.sourceforge.is_some() {
+            default_regex
+        } else {
+            checkver
+                .regex
+                .clone()
+                .or_else(|| checkver.re.clone())
+                .or(default_regex)
+        };
+
+        Ok(Self {
+            url: checkver
+                .url
+                .clone()
+                .or(default_url)
+                .ok_or(Error::MissingUrl)?,
+            regex,
+            jsonpath: checkver.jsonpath.clone().or_else(|| checkver.jp.clone()),
+            xpath: checkver.xpath.clone(),
+            replace: checkver.replace.clone(),
+            reverse: checkver.reverse.unwrap_or_default(),
+            useragent: checkver.useragent.clone(),
+            script: checkver
+                .script
+                .clone()
+                .map(|script| script.to_vec().join("\r\n")),
+        })
+    }
+
+    async fn fetch(&self, ctx: &impl ScoopContext) -> Result<String> {
+        if let Some(script) = &self.script {
+            let output = PowershellScript::new(script.as_str())
+                .save(ctx)?
+                .run()
+                .await?;
+
+            return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+        }
+
+        debug!("Checking {}", self.url);
+
+        let mut request = Client::asynchronous().get(&self.url);
+        if let Some(useragent) = &self.useragent {
+            request = request.header(USER_AGENT, useragent);
+        }
+
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(Error::ErrorStatus(response.status()));
+        }
+
+        Ok(response.text().await?)
+    }
+
+    /// Find the version in a fetched page
+    fn find_version(&self, page: &str, submap: &SubstitutionMap) -> Result<Latest> {
+        let mut page = page.to_string();
+
+        if let Some(jsonpath) = &self.jsonpath {
+            let json = serde_json::from_str(&page)?;
+            page = parse_json(&json, submap, jsonpath)?;
+        } else if let Some(xpath) = &self.xpath {
+            page = parse_xml(&page, submap, xpath)?;
+        }
+
+        let Some(regex) = &self.regex else {
+            // Without a regex, the extracted value (or script output) is the version
+            let version = page.trim();
+
+            if version.is_empty() {
+                return Err(Error::EmptyVersion);
+            }
+
+            return Ok(Latest {
+                version: Version::new(version),
+                matches: HashMap::new(),
+            });
+        };
+
+        let regex = Regex::new(&regex.clone().into_substituted(submap, true))?;
+
+        let captures = if self.reverse {
+            regex.captures_iter(&page).last()
+        } else {
+            regex.captures(&page)
+        }
+        .ok_or_else(|| Error::NoMatch(regex.to_string()))?;
+
+        let matches = regex
+            .capture_names()
+            .enumerate()
+            .filter_map(|(i, name)| {
+                let value = captures.get(i)?.as_str().to_string();
+                Some((
+                    name.map_or_else(|| i.to_string(), ToString::to_string),
+                    value,
+                ))
+            })
+            .collect::<HashMap<_, _>>();
+
+        let version = if let Some(replace) = &self.replace {
+            let mut version = String::new();
+            captures.expand(replace, &mut version);
+            version
+        } else {
+            matches
+                .get("version")
+                .or_else(|| matches.get("1"))
+                .cloned()
+                .unwrap_or_default()
+        };
+
+        if version.is_empty() {
+            return Err(Error::EmptyVersion);
+        }
+
+        Ok(Latest {
+            version: Version::new(version),
+            matches,
+        })
+    }
+}
+
+fn github_url(repo: &str) -> String {
+    format!("{}/releases/latest", repo.trim_end_matches('/'))
+}
+
+impl Manifest {
+    /// Find the latest upstream version of the manifest using its `checkver` section
+    ///
+    /// The `checkver` url, regex, json path, xpath and user agent may use the current version's substitutions
+    /// (i.e `$version` or `$majorVersion`)
+    ///
+    /// # Errors
+    /// - Missing checkver field
+    /// - The page could not be requested
+    /// - The checkver script failed
+    /// - The version could not be found in the page
+    pub async fn checkver(&self, ctx: &impl ScoopContext) -> Result<Latest> {
+        let mut submap = SubstitutionMap::new();
+        submap.append_version(&self.version);
+
+        let mut query = Query::from_manifest(self)?;
+        query.url.substitute(&submap, false);
+        query.useragent.substitute(&submap, false);
+
+        let page = query.fetch(ctx).await?;
+
+        query.find_version(&page, &submap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        contexts::testing::TestContext, packages::CreateManifest, requests::testing::serve,
+    };
+
+    fn manifest(value: &serde_json::Value) -> Manifest {
+        Manifest::from_str(value.to_string()).expect("valid manifest")
+    }
+
+    #[test]
+    fn test_query_from_manifest() {
+        let cases = [
+            (
+                json!({ "homepage": "https://github.com/winpax/sfsu/", "checkver": "github" }),
+                Query {
+                    url: "https://github.com/winpax/sfsu/releases/latest".to_string(),
+                    regex: Some(GITHUB_REGEX.to_string()),
+                    ..Default::default()
+                },
+            ),
+            (
+                json!({ "homepage": "https://example.com", "checkver": "v([\\d.]+)" }),
+                Query {
+                    url: "https://example.com".to_string(),
+                    regex: Some(r"v([\d.]+)".to_string()),
+                    ..Default::default()
+                },
+            ),
+            (
+                json!({ "checkver": { "github": "https://github.com/winpax/sfsu", "regex": "sfsu-([\\d.]+)" } }),
+                Query {
+                    url: "https://github.com/winpax/sfsu/releases/latest".to_string(),
+                    regex: Some(r"sfsu-([\d.]+)".to_string()),
+                    ..Default::default()
+                },
+            ),
+            (
+                json!({ "checkver": { "sourceforge": "sevenzip/7-Zip" } }),
+                Query {
+                    url: "https://sourceforge.net/projects/sevenzip/rss?path=/7-Zip".to_string(),
+                    regex: Some(format!(r"CDATA\[/7\-Zip/.*?{SOURCEFORGE_REGEX}.*?\]\]")),
+                    ..Default::default()
+                },
+            ),
+            (
+                json!({ "checkver": { "url": "https://example.com/api", "jp": "$.version", "reverse": true } }),
+                Query {
+                    url: "https://example.com/api".to_string(),
+                    jsonpath: Some("$.version".to_string()),
+                    reverse: true,
+                    ..Default::default()
+                },
+            ),
+        ];
+
+        for (value, expected) in cases {
+            let mut value = value;
+            value["version"] = json!("1.0.0");
+
+            assert_eq!(Query::from_manifest(&manifest(&value)).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_find_version() {
+        const PAGE: &str = "app-1.2.3-build45.zip app-1.3.0-build50.zip";
+
+        let query = Query {
+            regex: Some(r"app-(?<version>[\d.]+)-build(?<build>\d+)".to_string()),
+            ..Default::default()
+        };
+
+        let latest = query.find_version(PAGE, &SubstitutionMap::new()).unwrap();
+        assert_eq!(latest.version.as_str(), "1.2.3");
+        assert_eq!(latest.matches["build"], "45");
+        assert_eq!(latest.matches["0"], "app-1.2.3-build45");
+
+        let query = Query {
+            reverse: true,
+            replace: Some("${version}.${build}".to_string()),
+            ..query
+        };
+
+        let latest = query.find_version(PAGE, &SubstitutionMap::new()).unwrap();
+        assert_eq!(latest.version.as_str(), "1.3.0.50");
+
+        let query = Query {
+            regex: Some("missing".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            query.find_version(PAGE, &SubstitutionMap::new()),
+            Err(Error::NoMatch(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_checkver() {
+        let base_url = serve(&[
+            (
+                "/app/releases/latest",
+                r#"<a href="/app/releases/tag/v2.1.0">v2.1.0</a>"#,
+            ),
+            ("/api/1.0.0", r#"{"latest": {"version": "3.0.1"}}"#),
+            (
+                "/feed.xml",
+                "<feed><item><title>app 4.0.0-beta</title></item></feed>",
+            ),
+        ]);
+        let ctx = TestContext::new("checkver");
+
+        let cases = [
+            (
+                json!({ "homepage": format!("{base_url}/app"), "checkver": "github" }),
+                "2.1.0",
+            ),
+            (
+                json!({ "checkver": { "url": format!("{base_url}/api/$version"), "jsonpath": "$.latest.version" } }),
+                "3.0.1",
+            ),
+            (
+                json!({ "checkver": { "url": format!("{base_url}/feed.xml"), "xpath": "/feed/item/title", "regex": "app ([\\d.]+)" } }),
+                "4.0.0",
+            ),
+        ];
+
+        for (value, expected) in cases {
+            let mut value = value;
+            value["version"] = json!("1.0.0");
+
+            let latest = manifest(&value).checkver(&ctx).await.unwrap();
+            assert_eq!(latest.version.as_str(), expected);
+        }
+
+        let missing = manifest(&json!({
+            "version": "1.0.0",
+            "checkver": { "url": format!("{base_url}/missing"), "regex": "([\\d.]+)" },
+        }));
+        assert!(matches!(
+            missing.checkver(&ctx).await,
+            Err(Error::ErrorStatus(StatusCode::NOT_FOUND))
+        ));
+
+        ctx.cleanup();
+    }
+}
@@ -0,0 +1,580 @@
+//! Dependency resolution
+//!
+//! Resolves the dependencies of packages into an install order, following Scoop's `depends.ps1`.
+//!
+//! Along with each manifest's `depends`, the helpers Scoop needs to install the package are added as dependencies
+//! (i.e `7zip` for `.7z` archives, or `innounp` for Inno Setup installers).
+//!
+//! [`Dependents`] answers the reverse question, listing the installed apps that depend on an app.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use itertools::Itertools as _;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::{config, contexts::ScoopContext, Architecture};
+
+use super::{
+    is_installed,
+    reference::{self, manifest, package},
+    Manifest, NamedManifest,
+};
+
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+/// Dependency resolution errors
+pub enum Error {
+    #[error("Circular dependency detected: {}", .0.join(" -> "))]
+    Circular(Vec<String>),
+    #[error("Invalid app name in package reference: {0}")]
+    MissingName(String),
+    #[error("Could not resolve {name}: {source}")]
+    Reference {
+        name: String,
+        #[source]
+        source: reference::Error,
+    },
+    #[error("{name} is required by {}", .dependents.join(", "))]
+    HasDependents {
+        name: String,
+        dependents: Vec<String>,
+    },
+}
+
+/// Dependency resolution result type
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+// Like Scoop's `-match`, these are case insensitive
+static SEVEN_ZIP_URL: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)\.(001|7z|bz(ip)?2?|gz|img|iso|lzma|lzh|nupkg|rar|tar|t[abgpx]z2?|t?zst|xz)(\.[^\d.]+)?$",
+    )
+    .expect("valid 7zip url regex")
+});
+static MSI_URL: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\.msi$").expect("valid msi url regex"));
+static SEVEN_ZIP_SCRIPT: Lazy<Regex> =
+    Lazy::new(|| Regex::new("(?i)Expand-7zipArchive ").expect("valid 7zip script regex"));
+static MSI_SCRIPT: Lazy<Regex> =
+    Lazy::new(|| Regex::new("(?i)Expand-MsiArchive ").expect("valid msi script regex"));
+static INNO_SCRIPT: Lazy<Regex> =
+    Lazy::new(|| Regex::new("(?i)Expand-InnoArchive ").expect("valid inno script regex"));
+static DARK_SCRIPT: Lazy<Regex> =
+    Lazy::new(|| Regex::new("(?i)Expand-DarkArchive ").expect("valid dark script regex"));
+
+impl Manifest {
+    #[must_use]
+    /// List the helper apps Scoop needs to install the manifest for the given architecture
+    ///
+    /// These are `7zip` for archives other than zip (unless `use_external_7zip` is set),
+    /// `lessmsi` for msi files (if `use_lessmsi` is set), `innounp` for Inno Setup installers,
+    /// and `dark` for `WiX` bundles. Like Scoop, urls and scripts are matched case insensitively
+    pub fn installation_helpers(
+        &self,
+        arch: Architecture,
+        config: &config::Scoop,
+    ) -> Vec<manifest::Reference> {
+        let install_config = self.install_config(arch);
+
+        let urls = install_config
+            .url
+            .map(super::models::manifest::TOrArrayOfTs::to_vec)
+            .unwrap_or_default();
+        let script = [
+            install_config.pre_install,
+            install_config
+                .installer
+                .and_then(|installer| installer.script),
+            install_config.post_install,
+        ]
+        .into_iter()
+        .flatten()
+        .map(String::from)
+        .join("\n");
+
+        let url_matches = |regex: &Regex| urls.iter().any(|url| regex.is_match(url));
+
+        let mut helpers = vec![];
+
+        if !config.use_external_7zip
+            && (url_matches(&SEVEN_ZIP_URL) || SEVEN_ZIP_SCRIPT.is_match(&script))
+        {
+            helpers.push("7zip");
+        }
+
+        if config.use_lessmsi && (url_matches(&MSI_URL) || MSI_SCRIPT.is_match(&script)) {
+            helpers.push("lessmsi");
+        }
+
+        if self.innosetup == Some(true) || INNO_SCRIPT.is_match(&script) {
+            helpers.push("innounp");
+        }
+
+        if DARK_SCRIPT.is_match(&script) {
+            helpers.push("dark");
+        }
+
+        helpers
+            .into_iter()
+            .map(|helper| manifest::Reference::Name(helper.to_string()))
+            .collect()
+    }
+}
+
+/// Resolve the given packages and their dependencies into an install order
+///
+/// Dependencies are installed before the packages that depend on them,
+/// and the given packages are installed in the order they are provided, after their dependencies.
+///
+/// Dependencies that are already installed are skipped, along with their own dependencies.
+/// The given packages are always included.
+///
+/// # Errors
+/// - A package or dependency could not be found
+/// - A dependency depends on itself, directly or indirectly
+pub async fn resolve(
+    ctx: &impl ScoopContext<Config = config::Scoop>,
+    packages: &[package::Reference],
+    arch: Architecture,
+) -> Result<Vec<package::Reference>> {
+    let graph = Graph::load(ctx, packages, arch).await?;
+
+    graph.install_order(packages)
+}
+
+/// The dependencies of each package, by name
+#[derive(Debug, Default)]
+struct Graph {
+    references: HashMap<String, package::Reference>,
+    dependencies: HashMap<String, Vec<String>>,
+}
+
+impl Graph {
+    async fn load(
+        ctx: &impl ScoopContext<Config = config::Scoop>,
+        packages: &[package::Reference],
+        arch: Architecture,
+    ) -> Result<Self> {
+        let mut graph = Self::default();
+        let mut queue = packages.iter().cloned().collect::<VecDeque<_>>();
+
+        while let Some(reference) = queue.pop_front() {
+            let name = reference_name(&reference)?;
+
+            if graph.references.contains_key(&name) {
+                continue;
+            }
+
+            let manifest = reference
+                .manifest(ctx)
+                .await
+                .map_err(|source| Error::Reference {
+                    name: name.clone(),
+                    source,
+                })?;
+
+            let mut dependencies = vec![];
+            let helpers = manifest.installation_helpers(arch, ctx.config());
+
+            for dependency in helpers.into_iter().chain(manifest.depends()) {
+                let dependency = dependency.into_package_ref();
+                let dependency_name = reference_name(&dependency)?;
+
+                if dependencies.contains(&dependency_name)
+                    || is_installed(ctx, &dependency_name, None::<&str>)
+                {
+                    continue;
+                }
+
+                dependencies.push(dependency_name);
+                queue.push_back(dependency);
+            }
+
+            graph.references.insert(name.clone(), reference);
+            graph.dependencies.insert(name, dependencies);
+        }
+
+        Ok(graph)
+    }
+
+    fn install_order(&self, packages: &[package::Reference]) -> Result<Vec<package::Reference>> {
+        fn visit(
+            graph: &Graph,
+            name: &str,
+            stack: &mut Vec<String>,
+            resolved: &mut Vec<String>,
+            seen: &mut HashSet<String>,
+        ) -> Result<()> {
+            if seen.contains(name) {
+                return Ok(());
+            }
+
+            if let Some(start) = stack.iter().position(|visiting| visiting == name) {
+                let mut cycle = stack[start..].to_vec();
+                cycle.push(name.to_string());
+
+                return Err(Error::Circular(cycle));
+            }
+
+            stack.push(name.to_string());
+            for dependency in graph.dependencies.get(name).into_iter().flatten() {
+                visit(graph, dependency, stack, resolved, seen)?;
+            }
+            stack.pop();
+
+            seen.insert(name.to_string());
+            resolved.push(name.to_string());
+
+            Ok(())
+        }
+
+        let mut resolved = vec![];
+        let mut seen = HashSet::new();
+
+        for package in packages {
+            let name = reference_name(package)?;
+            visit(self, &name, &mut vec![], &mut resolved, &mut seen)?;
+        }
+
+        Ok(resolved
+            .into_iter()
+            .filter_map(|name| self.references.get(&name).cloned())
+            .collect())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// The reverse dependency graph of installed apps
+///
+/// Only the `depends` field of each manifest is considered,
+/// as the helpers used to install an app are not needed once it is installed
+pub struct Dependents {
+    dependents: HashMap<String, Vec<String>>,
+}
+
+impl Dependents {
+    /// Build the reverse dependency graph from the installed apps
+    ///
+    /// Installed apps with invalid manifests are ignored
+    ///
+    /// # Errors
+    /// - Reading the apps directory fails
+    pub fn from_installed(ctx: &impl ScoopContext) -> super::Result<Self> {
+        let manifests = NamedManifest::list_installed(ctx)?
+            .into_iter()
+            .filter_map(std::result::Result::ok)
+            .collect::<Vec<_>>();
+
+        Ok(Self::from_manifests(&manifests))
+    }
+
+    #[must_use]
+    /// Build the reverse dependency graph from the given manifests
+    pub fn from_manifests(manifests: &[NamedManifest]) -> Self {
+        let mut dependents: HashMap<String, Vec<String>> = HashMap::new();
+
+        for manifest in manifests {
+            for dependency in manifest.depends() {
+                let Some(dependency) = dependency.into_package_ref().name() else {
+                    continue;
+                };
+
+                let entry = dependents.entry(dependency).or_default();
+                if !entry.iter().any(|name| name == manifest.name()) {
+                    entry.push(manifest.name().to_string());
+                }
+            }
+        }
+
+        for names in dependents.values_mut() {
+            names.sort();
+        }
+
+        Self { dependents }
+    }
+
+    #[must_use]
+    /// List the apps that directly depend on the given app, sorted by name
+    pub fn direct(&self, name: &str) -> &[String] {
+        self.dependents.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    #[must_use]
+    /// List the apps that depend on the given app, directly or through other apps
+    ///
+    /// Direct dependents are listed first, followed by their dependents, and so on
+    pub fn transitive(&self, name: &str) -> Vec<String> {
+        let mut dependents = vec![];
+        let mut queue = VecDeque::from([name.to_string()]);
+
+        while let Some(current) = queue.pop_front() {
+            for dependent in self.direct(&current) {
+                if dependent != name && !dependents.contains(dependent) {
+                    dependents.push(dependent.clone());
+                    queue.push_back(dependent.clone());
+                }
+            }
+        }
+
+        dependents
+    }
+
+    #[must_use]
+    /// Check if any app depends on the given app
+    pub fn has_dependents(&self, name: &str) -> bool {
+        !self.direct(name).is_empty()
+    }
+
+    /// Ensure no app depends on the given app, i.e before uninstalling it
+    ///
+    /// # Errors
+    /// - Any app depends on the given app, directly or transitively
+    pub fn ensure_none(&self, name: &str) -> Result<()> {
+        let dependents = self.transitive(name);
+
+        if dependents.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::HasDependents {
+                name: name.to_string(),
+                dependents,
+            })
+        }
+    }
+}
+
+fn reference_name(reference: &package::Reference) -> Result<String> {
+    reference
+        .name()
+        .ok_or_else(|| Error::MissingName(reference.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::contexts::testing::TestContext;
+
+    fn write_manifests(ctx: &TestContext, bucket: &str, manifests: &[(&str, serde_json::Value)]) {
+        for (name, manifest) in manifests {
+            let mut manifest = manifest.clone();
+            manifest["version"] = json!("1.0.0");
+
+            ctx.write_bucket_manifest(bucket, name, &manifest);
+        }
+    }
+
+    fn names(plan: &[package::Reference]) -> Vec<String> {
+        plan.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_installation_helpers() {
+        let cases = [
+            (json!({ "url": "https://example.com/app.zip" }), vec![]),
+            (
+                json!({ "url": "https://example.com/app.tar.gz" }),
+                vec!["7zip"],
+            ),
+            (
+                json!({ "url": "https://example.com/download#/dl.7z" }),
+                vec!["7zip"],
+            ),
+            (json!({ "url": "https://example.com/app.msi" }), vec![]),
+            (
+                json!({ "url": "https://example.com/app.exe", "innosetup": true }),
+                vec!["innounp"],
+            ),
+            (
+                json!({
+                    "url": "https://example.com/app.exe",
+                    "installer": { "script": "Expand-DarkArchive \"$dir\\app.exe\" \"$dir\"" },
+                }),
+                vec!["dark"],
+            ),
+            (
+                json!({
+                    "architecture": { "64bit": { "url": "https://example.com/app.7z" } },
+                    "pre_install": "Expand-MsiArchive \"$dir\\app.msi\" \"$dir\"",
+                }),
+                vec!["7zip"],
+            ),
+        ];
+
+        let ctx = TestContext::new("installation-helpers");
+        let mut config = ctx.config().clone();
+
+        for (manifest, expected) in cases {
+            let mut manifest = manifest;
+            manifest["version"] = json!("1.0.0");
+            let manifest: Manifest = serde_json::from_value(manifest).unwrap();
+
+            assert_eq!(
+                manifest
+                    .installation_helpers(Architecture::X64, &config)
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>(),
+                expected
+            );
+        }
+
+        let manifest: Manifest = serde_json::from_value(json!({
+            "version": "1.0.0",
+            "url": ["https://example.com/app.7z", "https://example.com/app.msi"],
+        }))
+        .unwrap();
+        config.use_external_7zip = true;
+        config.use_lessmsi = true;
+
+        assert_eq!(
+            manifest.installation_helpers(Architecture::X64, &config),
+            vec![manifest::Reference::Name("lessmsi".to_string())]
+        );
+
+        // Urls and scripts are matched case insensitively, as Scoop's `-match` is
+        let manifest: Manifest = serde_json::from_value(json!({
+            "version": "1.0.0",
+            "url": ["https://example.com/APP.7Z", "https://example.com/Setup.MSI"],
+            "pre_install": "expand-innoarchive \"$dir\\setup.exe\" \"$dir\"",
+            "post_install": "EXPAND-DARKARCHIVE \"$dir\\bundle.exe\" \"$dir\"",
+        }))
+        .unwrap();
+        config.use_external_7zip = false;
+
+        assert_eq!(
+            manifest
+                .installation_helpers(Architecture::X64, &config)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["7zip", "lessmsi", "innounp", "dark"]
+        );
+
+        let manifest: Manifest = serde_json::from_value(json!({
+            "version": "1.0.0",
+            "url": "https://example.com/app.exe",
+            "installer": { "script": "expand-7ZIPARCHIVE \"$dir\\app.exe\" \"$dir\"" },
+        }))
+        .unwrap();
+
+        assert_eq!(
+            manifest.installation_helpers(Architecture::X64, &config),
+            vec![manifest::Reference::Name("7zip".to_string())]
+        );
+
+        ctx.cleanup();
+    }
+
+    #[tokio::test]
+    async fn test_resolve() {
+        let ctx = TestContext::new("dependencies");
+
+        write_manifests(
+            &ctx,
+            "main",
+            &[
+                ("app", json!({ "depends": ["lib", "extras/tool"] })),
+                (
+                    "lib",
+                    json!({ "url": "https://example.com/lib.7z", "depends": "tool" }),
+                ),
+                ("7zip", json!({})),
+                ("installed", json!({ "depends": "lib" })),
+            ],
+        );
+        write_manifests(
+            &ctx,
+            "extras",
+            &[("tool", json!({ "innosetup": true, "depends": "installed" }))],
+        );
+
+        // Installed dependencies are skipped
+        for installed in ["innounp", "installed"] {
+            let current = ctx.apps_path().join(installed).join("current");
+            std::fs::create_dir_all(&current).unwrap();
+            std::fs::write(current.join("install.json"), "{}").unwrap();
+        }
+
+        let plan = resolve(&ctx, &["app".parse().unwrap()], Architecture::X64)
+            .await
+            .unwrap();
+
+        assert_eq!(names(&plan), ["7zip", "extras/tool", "lib", "app"]);
+
+        ctx.cleanup();
+    }
+
+    #[test]
+    fn test_dependents() {
+        let manifests = [
+            ("app", json!(["runtime", "main/tool"])),
+            ("tool", json!("runtime")),
+            ("plugin", json!("app")),
+            ("runtime", json!(null)),
+            ("other", json!("unrelated")),
+        ]
+        .map(|(name, depends)| {
+            let manifest =
+                serde_json::from_value(json!({ "version": "1.0.0", "depends": depends })).unwrap();
+
+            NamedManifest::new(name, manifest)
+        });
+
+        let dependents = Dependents::from_manifests(&manifests);
+
+        assert_eq!(dependents.direct("runtime"), ["app", "tool"]);
+        assert_eq!(dependents.transitive("runtime"), ["app", "tool", "plugin"]);
+        assert_eq!(dependents.transitive("tool"), ["app", "plugin"]);
+        assert!(!dependents.has_dependents("plugin"));
+        assert!(dependents.ensure_none("plugin").is_ok());
+        assert_eq!(
+            dependents.ensure_none("tool").unwrap_err().to_string(),
+            "tool is required by app, plugin"
+        );
+    }
+
+    #[test]
+    fn test_dependents_from_installed() {
+        let ctx = TestContext::new("dependents");
+
+        for (name, manifest) in [
+            ("app", json!({ "version": "1.0.0", "depends": "runtime" })),
+            ("runtime", json!({ "version": "1.0.0" })),
+        ] {
+            ctx.write_installed_manifest(name, &manifest);
+        }
+
+        let dependents = Dependents::from_installed(&ctx).unwrap();
+        assert_eq!(dependents.direct("runtime"), ["app"]);
+
+        ctx.cleanup();
+    }
+
+    #[tokio::test]
+    async fn test_resolve_circular() {
+        let ctx = TestContext::new("dependencies-circular");
+
+        write_manifests(
+            &ctx,
+            "main",
+            &[
+                ("app", json!({ "depends": "first" })),
+                ("first", json!({ "depends": "second" })),
+                ("second", json!({ "depends": "first" })),
+            ],
+        );
+
+        let error = resolve(&ctx, &["app".parse().unwrap()], Architecture::X64)
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Circular dependency detected: first -> second -> first"
+        );
+
+        ctx.cleanup();
+    }
+}
@@ -79,6 +79,27 @@ impl DownloadUrl {
         }
     }
 
+    #[must_use]
+    /// Get the name the downloaded file is saved as in the app directory
+    ///
+    /// This is the `#/` fragment if there is one, as in `https://example.com/download#/dl.7z`,
+    /// otherwise the last segment of the url, without anything up to a trailing query parameter.
+    /// This matches Scoop's `url_filename`
+    ///
+    /// # Panics
+    /// - If the hardcoded regex is invalid
+    pub fn target_file_name(&self) -> String {
+        if let Some(file_name) = &self.file_name {
+            return file_name.clone();
+        }
+
+        let query_regex = Regex::new(r".*[?=]+").expect("valid regex");
+
+        let leaf = self.url.rsplit('/').next().unwrap_or_default();
+
+        query_regex.replace(leaf, "").to_string()
+    }
+
     #[must_use]
     /// Get the full download url, including the file name segment
     pub fn full_url(&self) -> String {
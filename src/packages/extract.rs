@@ -0,0 +1,997 @@
+//! Archive extraction for downloaded packages
+//!
+//! Zip, tar (optionally compressed with gzip, xz or zstd), 7z and MSI archives are extracted natively.
+//! Other formats Scoop hands to 7-Zip (rar, iso, bzip2, etc.) need a `7z` executable,
+//! which is also used for every format but MSI when `use_external_7zip` is set.
+//! MSI packages with LZX or Quantum compressed cabinets, which have no native decoder,
+//! are extracted with lessmsi if it is installed, or msiexec otherwise, as Scoop does.
+
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::{Component, Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
+};
+
+use indicatif::ProgressBar;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use strum::Display;
+
+use crate::{config, contexts::ScoopContext, Architecture};
+
+use super::{downloading::DownloadUrl, models::manifest::TOrArrayOfTs, Manifest};
+
+mod cab;
+mod msi;
+
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+/// Extraction errors
+pub enum Error {
+    #[error("Extraction I/O error: {0}")]
+    IO(#[from] std::io::Error),
+    #[error("Reading zip archive: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("Reading 7z archive: {0}")]
+    SevenZip(#[from] sevenz_rust::Error),
+    #[error("Decompressing xz stream: {0}")]
+    Xz(#[from] lzma_rs::error::Error),
+    #[error("Decompressing zstd stream: {0}")]
+    Zstd(#[from] ruzstd::frame_decoder::FrameDecoderError),
+    #[error("Could not find 7-Zip: {0}")]
+    Missing7zip(#[from] which::Error),
+    #[error("{0} is not a supported archive")]
+    UnknownFormat(String),
+    #[error("{0} can only be extracted with 7-Zip, which is not installed")]
+    Requires7zip(String),
+    #[error("7-Zip exited with {status}: {output}")]
+    External { status: ExitStatus, output: String },
+    #[error("{program} exited with {status}: {output}")]
+    ExternalMsi {
+        program: &'static str,
+        status: ExitStatus,
+        output: String,
+    },
+    #[error("{0} points outside of the extraction directory")]
+    PathTraversal(String),
+    #[error("Could not find '{0}' in the archive")]
+    ExtractDirNotFound(String),
+    #[error("Invalid MSI package: {0}")]
+    InvalidMsi(String),
+    #[error("Invalid cabinet: {0}")]
+    InvalidCabinet(String),
+    #[error("{0} compressed cabinets are not supported")]
+    UnsupportedCompression(String),
+    #[error("Could not find {0}, which is not in any of the MSI package's cabinets")]
+    MissingMsiFile(String),
+}
+
+/// Extraction result type
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+static EXTERNAL_FORMATS: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\.(001|bz(ip)?2?|img|iso|lzma|lzh|rar|t[abp]z2?)$").expect("valid archive regex")
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+#[strum(serialize_all = "snake_case")]
+/// An archive format
+pub enum Format {
+    /// A zip archive, including `.nupkg` packages
+    Zip,
+    /// An uncompressed tar archive
+    Tar,
+    /// A gzip compressed tar archive
+    TarGz,
+    /// An xz compressed tar archive
+    TarXz,
+    /// A zstd compressed tar archive
+    TarZst,
+    /// A single gzip compressed file
+    Gzip,
+    /// A single xz compressed file
+    Xz,
+    /// A single zstd compressed file
+    Zstd,
+    /// A 7z archive
+    SevenZip,
+    /// A Windows Installer package, extracted as an administrative install would
+    Msi,
+    /// A format that can only be extracted with 7-Zip
+    External,
+}
+
+impl Format {
+    #[must_use]
+    /// Detect the archive format from a file name
+    ///
+    /// Returns [`None`] for files that are not archives, such as installers
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        let file_name = file_name.to_lowercase();
+        let ends_with = |extensions: &[&str]| extensions.iter().any(|ext| file_name.ends_with(ext));
+
+        let format = if ends_with(&[".tar.gz", ".tgz"]) {
+            Self::TarGz
+        } else if ends_with(&[".tar.xz", ".txz"]) {
+            Self::TarXz
+        } else if ends_with(&[".tar.zst", ".tzst"]) {
+            Self::TarZst
+        } else if ends_with(&[".tar"]) {
+            Self::Tar
+        } else if ends_with(&[".zip", ".nupkg"]) {
+            Self::Zip
+        } else if ends_with(&[".7z"]) {
+            Self::SevenZip
+        } else if ends_with(&[".msi"]) {
+            Self::Msi
+        } else if ends_with(&[".gz"]) {
+            Self::Gzip
+        } else if ends_with(&[".xz"]) {
+            Self::Xz
+        } else if ends_with(&[".zst"]) {
+            Self::Zstd
+        } else if EXTERNAL_FORMATS.is_match(&file_name) {
+            Self::External
+        } else {
+            return None;
+        };
+
+        Some(format)
+    }
+
+    #[must_use]
+    /// Check if the format is a tar archive, compressed or not
+    pub fn is_tar(self) -> bool {
+        matches!(self, Self::Tar | Self::TarGz | Self::TarXz | Self::TarZst)
+    }
+
+    /// The name of the file a compressed stream decompresses to
+    fn decompressed_name(self, file_name: &str) -> String {
+        let stem = Path::new(file_name)
+            .file_stem()
+            .map_or_else(String::new, |stem| stem.to_string_lossy().to_string());
+
+        // `.tgz` and friends decompress to a tar file of the same name
+        if self.is_tar() && !stem.to_lowercase().ends_with(".tar") {
+            format!("{stem}.tar")
+        } else {
+            stem
+        }
+    }
+}
+
+/// Convert an archive entry name, or a manifest provided directory, into a relative path
+///
+/// Both separators are accepted, and `.` components are dropped
+///
+/// # Errors
+/// - The name is absolute, or contains `..` components
+pub fn relative_path(name: &str) -> Result<PathBuf> {
+    let mut path = PathBuf::new();
+
+    for component in Path::new(&name.replace('\\', "/")).components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(Error::PathTraversal(name.to_string()));
+            }
+        }
+    }
+
+    Ok(path)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A downloaded file, and where to extract it to
+pub struct Extraction {
+    /// The url the file is downloaded from
+    pub url: DownloadUrl,
+    /// The name the file is saved as in the app directory
+    pub file_name: String,
+    /// The archive format, or [`None`] if the file is not extracted
+    pub format: Option<Format>,
+    /// The directory in the archive to extract
+    pub extract_dir: Option<String>,
+    /// The directory in the app directory to extract to
+    pub extract_to: Option<String>,
+}
+
+#[must_use]
+/// Pair downloaded files with `extract_dir` and `extract_to` entries
+///
+/// As in Scoop, entries are consumed only by archives.
+/// For example, with urls `setup.exe` and `app.zip`, the first `extract_dir` applies to `app.zip`
+pub fn pair(
+    urls: impl IntoIterator<Item = DownloadUrl>,
+    extract_dirs: &[String],
+    extract_tos: &[String],
+) -> Vec<Extraction> {
+    let mut extracted = 0;
+
+    urls.into_iter()
+        .map(|url| {
+            let file_name = url.target_file_name();
+            let format = Format::from_file_name(&file_name);
+
+            let (extract_dir, extract_to) = if format.is_some() {
+                extracted += 1;
+                (
+                    extract_dirs.get(extracted - 1).cloned(),
+                    extract_tos.get(extracted - 1).cloned(),
+                )
+            } else {
+                (None, None)
+            };
+
+            Extraction {
+                url,
+                file_name,
+                format,
+                extract_dir,
+                extract_to,
+            }
+        })
+        .collect()
+}
+
+impl Manifest {
+    #[must_use]
+    /// Get the downloaded files for the given architecture, paired with their `extract_dir` and `extract_to` entries
+    pub fn extractions(&self, arch: Architecture) -> Vec<Extraction> {
+        let extract_dirs = self
+            .install_config(arch)
+            .extract_dir
+            .map(TOrArrayOfTs::to_vec)
+            .unwrap_or_default();
+        let extract_tos = self
+            .extract_to
+            .clone()
+            .map(TOrArrayOfTs::to_vec)
+            .unwrap_or_default();
+
+        pair(
+            self.download_urls(arch).unwrap_or_default(),
+            &extract_dirs,
+            &extract_tos,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+#[must_use]
+/// Extracts archives into app directories
+pub struct Extractor {
+    seven_zip: Option<PathBuf>,
+    prefer_7zip: bool,
+    lessmsi: Option<PathBuf>,
+    progress: Option<ProgressBar>,
+}
+
+impl Extractor {
+    /// Create an extractor that only uses the native decoders
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an extractor following the context's configuration
+    ///
+    /// If `use_external_7zip` is set, `7z` from the system path is used for every archive.
+    /// Otherwise a 7-Zip installed by Scoop is used for formats without a native decoder, if there is one.
+    /// A lessmsi installed by Scoop is used for MSI packages without a native decoder, if there is one
+    ///
+    /// # Errors
+    /// - `use_external_7zip` is set, but `7z` is not in the system path
+    pub fn from_context(ctx: &impl ScoopContext<Config = config::Scoop>) -> Result<Self> {
+        let helper = |app: &str, executable: &str| {
+            let path = ctx.apps_path().join(app).join("current").join(executable);
+            path.exists().then_some(path)
+        };
+
+        let mut extractor = Self::new();
+        if ctx.config().use_external_7zip {
+            extractor = extractor
+                .with_7zip(which::which("7z")?)
+                .with_prefer_7zip(true);
+        } else if let Some(seven_zip) = helper("7zip", "7z.exe") {
+            extractor = extractor.with_7zip(seven_zip);
+        }
+
+        Ok(match helper("lessmsi", "lessmsi.exe") {
+            Some(lessmsi) => extractor.with_lessmsi(lessmsi),
+            None => extractor,
+        })
+    }
+
+    /// Use the 7-Zip executable at the given path for formats without a native decoder
+    pub fn with_7zip(mut self, path: impl Into<PathBuf>) -> Self {
+        self.seven_zip = Some(path.into());
+        self
+    }
+
+    /// Use 7-Zip for every format, not only those without a native decoder
+    pub fn with_prefer_7zip(mut self, prefer_7zip: bool) -> Self {
+        self.prefer_7zip = prefer_7zip;
+        self
+    }
+
+    /// Use the lessmsi executable at the given path for MSI packages without a native decoder
+    ///
+    /// Without it, such packages are extracted with msiexec, which is only available on Windows
+    pub fn with_lessmsi(mut self, path: impl Into<PathBuf>) -> Self {
+        self.lessmsi = Some(path.into());
+        self
+    }
+
+    /// Report each extracted entry to the given progress bar
+    ///
+    /// The bar's length is set to the number of entries, when the format allows knowing it upfront
+    pub fn with_progress(mut self, progress: ProgressBar) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Extract each downloaded archive in the app directory, then remove it
+    ///
+    /// Archives are expected in `dir` under their [`Extraction::file_name`], as Scoop downloads them.
+    /// Files that are not archives are left in place
+    ///
+    /// # Errors
+    /// - An archive could not be extracted
+    /// - An `extract_to` entry points outside of the app directory
+    pub fn extract_all(&self, dir: &Path, extractions: &[Extraction]) -> Result<()> {
+        for extraction in extractions {
+            if extraction.format.is_none() {
+                continue;
+            }
+
+            let archive = dir.join(&extraction.file_name);
+            let destination = match &extraction.extract_to {
+                Some(extract_to) => dir.join(relative_path(extract_to)?),
+                None => dir.to_path_buf(),
+            };
+
+            self.extract(&archive, &destination, extraction.extract_dir.as_deref())?;
+            std::fs::remove_file(&archive)?;
+        }
+
+        Ok(())
+    }
+
+    /// Extract an archive into the destination directory
+    ///
+    /// If `extract_dir` is provided, only the contents of that directory in the archive are extracted
+    ///
+    /// # Errors
+    /// - The archive's format is not supported
+    /// - The archive could not be read
+    /// - An entry, or the `extract_dir`, points outside of the destination
+    /// - The `extract_dir` is not in the archive
+    pub fn extract(
+        &self,
+        archive: &Path,
+        destination: &Path,
+        extract_dir: Option<&str>,
+    ) -> Result<()> {
+        let file_name = archive
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let format = Format::from_file_name(&file_name)
+            .ok_or_else(|| Error::UnknownFormat(file_name.clone()))?;
+
+        let extract_dir = extract_dir
+            .map(relative_path)
+            .transpose()?
+            .filter(|dir| dir.components().next().is_some());
+
+        // As in Scoop, archives with an `extract_dir` are extracted to a temporary directory first
+        let target = if extract_dir.is_some() {
+            destination.join("_tmp")
+        } else {
+            destination.to_path_buf()
+        };
+        std::fs::create_dir_all(&target)?;
+
+        match self.seven_zip_for(format) {
+            Some(seven_zip) => extract_external(seven_zip, archive, &target, format)?,
+            None if format == Format::External => return Err(Error::Requires7zip(file_name)),
+            None => self.extract_native(archive, &file_name, &target, format)?,
+        }
+
+        if let Some(extract_dir) = extract_dir {
+            let source = target.join(&extract_dir);
+            if !source.is_dir() {
+                std::fs::remove_dir_all(&target)?;
+                return Err(Error::ExtractDirNotFound(extract_dir.display().to_string()));
+            }
+
+            move_contents(&source, destination)?;
+            std::fs::remove_dir_all(&target)?;
+        }
+
+        Ok(())
+    }
+
+    fn seven_zip_for(&self, format: Format) -> Option<&Path> {
+        let seven_zip = self.seven_zip.as_deref()?;
+
+        // 7-Zip extracts MSI packages' raw streams, rather than their files
+        (self.prefer_7zip && format != Format::Msi || format == Format::External)
+            .then_some(seven_zip)
+    }
+
+    fn extract_native(
+        &self,
+        archive: &Path,
+        file_name: &str,
+        destination: &Path,
+        format: Format,
+    ) -> Result<()> {
+        match format {
+            Format::Zip => self.unpack_zip(archive, destination),
+            Format::SevenZip => self.unpack_7z(archive, destination),
+            Format::Msi => match msi::extract(archive, destination, || self.tick()) {
+                Err(Error::UnsupportedCompression(compression)) => extract_msi_external(
+                    self.lessmsi.as_deref(),
+                    archive,
+                    destination,
+                    &compression,
+                ),
+                result => result,
+            },
+            Format::Tar => self.unpack_tar(archive, destination),
+            Format::Gzip
+            | Format::Xz
+            | Format::Zstd
+            | Format::TarGz
+            | Format::TarXz
+            | Format::TarZst => {
+                let decompressed = destination.join(format.decompressed_name(file_name));
+                decompress(archive, &decompressed, format)?;
+                self.tick();
+
+                if format.is_tar() {
+                    let unpacked = self.unpack_tar(&decompressed, destination);
+                    std::fs::remove_file(&decompressed)?;
+                    unpacked?;
+                }
+
+                Ok(())
+            }
+            Format::External => Err(Error::Requires7zip(file_name.to_string())),
+        }
+    }
+
+    fn tick(&self) {
+        if let Some(progress) = &self.progress {
+            progress.inc(1);
+        }
+    }
+
+    fn set_length(&self, length: usize) {
+        if let Some(progress) = &self.progress {
+            progress.set_length(length as u64);
+        }
+    }
+
+    fn unpack_zip(&self, archive: &Path, destination: &Path) -> Result<()> {
+        let mut zip = zip::ZipArchive::new(BufReader::new(File::open(archive)?))?;
+        self.set_length(zip.len());
+
+        for i in 0..zip.len() {
+            let mut entry = zip.by_index(i)?;
+            let name = entry.name().to_string();
+            let is_dir = entry.is_dir();
+
+            unpack_entry(destination, &name, is_dir, &mut entry)?;
+            self.tick();
+        }
+
+        Ok(())
+    }
+
+    fn unpack_tar(&self, archive: &Path, destination: &Path) -> Result<()> {
+        let mut tar = tar::Archive::new(BufReader::new(File::open(archive)?));
+
+        for entry in tar.entries()? {
+            let mut entry = entry?;
+            let name = entry.path()?.to_string_lossy().to_string();
+            let entry_type = entry.header().entry_type();
+
+            if entry_type.is_dir() || entry_type.is_file() {
+                unpack_entry(destination, &name, entry_type.is_dir(), &mut entry)?;
+            } else {
+                // Links could point outside of the destination, and are not used by Windows packages
+                debug!("Skipping {entry_type:?} entry {name}");
+            }
+            self.tick();
+        }
+
+        Ok(())
+    }
+
+    fn unpack_7z(&self, archive: &Path, destination: &Path) -> Result<()> {
+        let mut reader = sevenz_rust::SevenZReader::open(archive, sevenz_rust::Password::empty())?;
+        self.set_length(reader.archive().files.len());
+
+        let mut failure = None;
+        reader.for_each_entries(|entry, data| {
+            match unpack_entry(destination, entry.name(), entry.is_directory(), data) {
+                Ok(()) => {
+                    self.tick();
+                    Ok(true)
+                }
+                Err(error) => {
+                    failure = Some(error);
+                    Ok(false)
+                }
+            }
+        })?;
+
+        failure.map_or(Ok(()), Err)
+    }
+}
+
+/// Write a single archive entry into the destination
+fn unpack_entry(destination: &Path, name: &str, is_dir: bool, data: &mut dyn Read) -> Result<()> {
+    let path = destination.join(relative_path(name)?);
+
+    if is_dir {
+        std::fs::create_dir_all(path)?;
+    } else {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::io::copy(data, &mut File::create(path)?)?;
+    }
+
+    Ok(())
+}
+
+/// Decompress a single compressed stream
+fn decompress(archive: &Path, output: &Path, format: Format) -> Result<()> {
+    let mut input = BufReader::new(File::open(archive)?);
+    let mut output = File::create(output)?;
+
+    match format {
+        Format::Gzip | Format::TarGz => {
+            std::io::copy(&mut flate2::read::MultiGzDecoder::new(input), &mut output)?;
+        }
+        Format::Xz | Format::TarXz => lzma_rs::xz_decompress(&mut input, &mut output)?,
+        Format::Zstd | Format::TarZst => {
+            std::io::copy(&mut ruzstd::StreamingDecoder::new(input)?, &mut output)?;
+        }
+        _ => unreachable!("{format} is not a compressed stream"),
+    }
+
+    Ok(())
+}
+
+/// Extract an archive with an external 7-Zip
+///
+/// Compressed tar archives are decompressed into a second 7-Zip process, so the tar file never touches the disk
+fn extract_external(
+    seven_zip: &Path,
+    archive: &Path,
+    destination: &Path,
+    format: Format,
+) -> Result<()> {
+    let mut output_dir = std::ffi::OsString::from("-o");
+    output_dir.push(destination);
+
+    let output = if format.is_tar() && format != Format::Tar {
+        let mut decompress = Command::new(seven_zip)
+            .args(["x", "-so"])
+            .arg(archive)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdout = decompress.stdout.take().expect("stdout is piped");
+
+        let output = Command::new(seven_zip)
+            .args(["x", "-si", "-ttar", "-y"])
+            .arg(&output_dir)
+            .stdin(stdout)
+            .output()?;
+
+        let status = decompress.wait()?;
+        if !status.success() {
+            return Err(Error::External {
+                status,
+                output: String::new(),
+            });
+        }
+
+        output
+    } else {
+        Command::new(seven_zip)
+            .args(["x", "-y"])
+            .arg(&output_dir)
+            .arg(archive)
+            .output()?
+    };
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(Error::External {
+            status: output.status,
+            output: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        })
+    }
+}
+
+/// Extract an MSI package with lessmsi, or with msiexec if lessmsi is not available, as Scoop does
+///
+/// Both extract the package's files as an administrative install would, so the layout matches [`msi::extract`]
+///
+/// `compression` is the unsupported compression method, reported if neither is available
+fn extract_msi_external(
+    lessmsi: Option<&Path>,
+    msi: &Path,
+    destination: &Path,
+    compression: &str,
+) -> Result<()> {
+    debug!(
+        "{} has {compression} compressed cabinets, extracting it externally",
+        msi.display()
+    );
+    let source_dir = destination.join("SourceDir");
+
+    let (program, output) = match lessmsi {
+        Some(lessmsi) => {
+            // lessmsi extracts into `SourceDir` in the given directory, which must end with a separator
+            let mut target = destination.as_os_str().to_os_string();
+            target.push(std::path::MAIN_SEPARATOR_STR);
+
+            (
+                "lessmsi",
+                Command::new(lessmsi)
+                    .arg("x")
+                    .arg(msi)
+                    .arg(target)
+                    .output()?,
+            )
+        }
+        #[cfg(windows)]
+        None => ("msiexec", msiexec(msi, &source_dir)?),
+        #[cfg(not(windows))]
+        None => return Err(Error::UnsupportedCompression(compression.to_string())),
+    };
+
+    if !output.status.success() {
+        return Err(Error::ExternalMsi {
+            program,
+            status: output.status,
+            output: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+
+    if source_dir.is_dir() {
+        move_contents(&source_dir, destination)?;
+        std::fs::remove_dir(&source_dir)?;
+    }
+
+    // msiexec copies the package itself into the target directory
+    if let Some(file_name) = msi.file_name() {
+        let copy = destination.join(file_name);
+        if copy != msi && copy.is_file() {
+            std::fs::remove_file(copy)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(windows)]
+fn msiexec(msi: &Path, target: &Path) -> std::io::Result<std::process::Output> {
+    use std::os::windows::process::CommandExt;
+
+    // msiexec only accepts quotes around the property's value, rather than the whole argument
+    Command::new("msiexec")
+        .arg("/a")
+        .arg(msi)
+        .arg("/qn")
+        .raw_arg(format!("TARGETDIR=\"{}\"", target.display()))
+        .output()
+}
+
+/// Move the contents of a directory into another, merging directories and replacing files
+fn move_contents(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(to)?;
+
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let source = entry.path();
+        let target = to.join(entry.file_name());
+
+        if source.is_dir() && target.is_dir() {
+            move_contents(&source, &target)?;
+            std::fs::remove_dir(&source)?;
+            continue;
+        }
+
+        if target.is_dir() {
+            std::fs::remove_dir_all(&target)?;
+        } else if target.exists() {
+            std::fs::remove_file(&target)?;
+        }
+        std::fs::rename(&source, &target)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::contexts::testing::TestContext;
+
+    fn write_zip(path: &Path, entries: &[(&str, &str)]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, contents) in entries {
+            zip.start_file(*name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_format_detection() {
+        assert_eq!(Format::from_file_name("app.zip"), Some(Format::Zip));
+        assert_eq!(Format::from_file_name("App.TAR.GZ"), Some(Format::TarGz));
+        assert_eq!(Format::from_file_name("app.tzst"), Some(Format::TarZst));
+        assert_eq!(Format::from_file_name("app.exe.xz"), Some(Format::Xz));
+        assert_eq!(Format::from_file_name("dl.7z"), Some(Format::SevenZip));
+        assert_eq!(Format::from_file_name("setup.msi"), Some(Format::Msi));
+        assert_eq!(Format::from_file_name("app.rar"), Some(Format::External));
+        assert_eq!(Format::from_file_name("setup.exe"), None);
+
+        assert_eq!(Format::TarGz.decompressed_name("app.tgz"), "app.tar");
+        assert_eq!(Format::TarXz.decompressed_name("app.tar.xz"), "app.tar");
+        assert_eq!(Format::Gzip.decompressed_name("app.exe.gz"), "app.exe");
+    }
+
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(
+            relative_path(r".\app-1.0\bin").unwrap(),
+            Path::new("app-1.0").join("bin")
+        );
+        assert!(matches!(
+            relative_path("app/../../evil"),
+            Err(Error::PathTraversal(_))
+        ));
+        assert!(matches!(
+            relative_path("/etc/passwd"),
+            Err(Error::PathTraversal(_))
+        ));
+    }
+
+    #[test]
+    fn test_pair_extractions() {
+        let urls = [
+            "https://example.com/setup.exe",
+            "https://example.com/app.zip",
+            "https://example.com/download?file=tools.7z",
+            "https://example.com/tools.exe#/dl.7z",
+        ]
+        .map(DownloadUrl::from_string);
+
+        let extractions = pair(
+            urls,
+            &["app-1.0".to_string(), "tools".to_string()],
+            &[String::new(), "bin".to_string(), "more".to_string()],
+        );
+
+        let summary = extractions
+            .iter()
+            .map(|extraction| {
+                (
+                    extraction.file_name.as_str(),
+                    extraction.format,
+                    extraction.extract_dir.as_deref(),
+                    extraction.extract_to.as_deref(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            summary,
+            [
+                ("setup.exe", None, None, None),
+                ("app.zip", Some(Format::Zip), Some("app-1.0"), Some("")),
+                (
+                    "tools.7z",
+                    Some(Format::SevenZip),
+                    Some("tools"),
+                    Some("bin")
+                ),
+                ("dl.7z", Some(Format::SevenZip), None, Some("more")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_zip_with_extract_dir() {
+        let ctx = TestContext::new("extract-zip");
+        let dir = ctx.path();
+        let archive = dir.join("app.zip");
+        write_zip(
+            &archive,
+            &[
+                ("app-1.0/bin/app.exe", "binary"),
+                ("app-1.0/readme.txt", "readme"),
+                ("other.txt", "other"),
+            ],
+        );
+
+        let extractions = pair(
+            [DownloadUrl::from_string("https://example.com/app.zip")],
+            &["app-1.0".to_string()],
+            &["current".to_string()],
+        );
+
+        let progress = ProgressBar::hidden();
+        Extractor::new()
+            .with_progress(progress.clone())
+            .extract_all(dir, &extractions)
+            .unwrap();
+
+        let current = dir.join("current");
+        assert_eq!(
+            std::fs::read_to_string(current.join("bin").join("app.exe")).unwrap(),
+            "binary"
+        );
+        assert!(current.join("readme.txt").exists());
+        assert!(!current.join("other.txt").exists());
+        assert!(!current.join("_tmp").exists());
+        assert!(!archive.exists());
+        assert_eq!(progress.position(), 3);
+
+        ctx.cleanup();
+    }
+
+    #[test]
+    fn test_extract_missing_extract_dir() {
+        let ctx = TestContext::new("extract-missing");
+        let dir = ctx.path();
+        let archive = dir.join("app.zip");
+        write_zip(&archive, &[("app/app.exe", "binary")]);
+
+        let result = Extractor::new().extract(&archive, dir, Some("app-1.0"));
+
+        assert!(matches!(result, Err(Error::ExtractDirNotFound(_))));
+        assert!(!dir.join("_tmp").exists());
+
+        ctx.cleanup();
+    }
+
+    #[test]
+    fn test_extract_rejects_path_traversal() {
+        let ctx = TestContext::new("extract-traversal");
+        let dir = ctx.path();
+        let archive = dir.join("evil.zip");
+        write_zip(&archive, &[("../evil.txt", "evil")]);
+
+        let destination = dir.join("app");
+        let result = Extractor::new().extract(&archive, &destination, None);
+
+        assert!(matches!(result, Err(Error::PathTraversal(_))));
+        assert!(!dir.join("evil.txt").exists());
+
+        ctx.cleanup();
+    }
+
+    #[test]
+    fn test_extract_tar_gz() {
+        let ctx = TestContext::new("extract-targz");
+        let dir = ctx.path();
+        let archive = dir.join("app.tgz");
+
+        let encoder = flate2::write::GzEncoder::new(
+            File::create(&archive).unwrap(),
+            flate2::Compression::default(),
+        );
+        let mut tar = tar::Builder::new(encoder);
+        let mut header = tar::Header::new_gnu();
+        header.set_size(6);
+        header.set_mode(0o644);
+        header.set_cksum();
+        tar.append_data(&mut header, "app/bin/app.exe", "binary".as_bytes())
+            .unwrap();
+        tar.into_inner().unwrap().finish().unwrap();
+
+        Extractor::new().extract(&archive, dir, None).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(dir.join("app").join("bin").join("app.exe")).unwrap(),
+            "binary"
+        );
+        assert!(!dir.join("app.tar").exists());
+
+        ctx.cleanup();
+    }
+
+    #[test]
+    fn test_external_formats_require_7zip() {
+        let ctx = TestContext::new("extract-external");
+        let dir = ctx.path();
+        let archive = dir.join("app.rar");
+        std::fs::write(&archive, "not really a rar").unwrap();
+
+        let result = Extractor::new().extract(&archive, dir, None);
+
+        assert!(matches!(result, Err(Error::Requires7zip(_))));
+
+        ctx.cleanup();
+    }
+
+    #[test]
+    fn test_extract_7z() {
+        let ctx = TestContext::new("extract-7z");
+        let dir = ctx.path();
+        let source = dir.join("source");
+        let archive = dir.join("app.7z");
+        let destination = dir.join("app");
+
+        std::fs::create_dir_all(source.join("bin")).unwrap();
+        std::fs::write(source.join("bin").join("app.exe"), "binary").unwrap();
+        std::fs::write(source.join("readme.txt"), "readme").unwrap();
+        sevenz_rust::compress_to_path(&source, &archive).unwrap();
+
+        Extractor::new()
+            .extract(&archive, &destination, Some("bin"))
+            .unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(destination.join("app.exe")).unwrap(),
+            "binary"
+        );
+        assert!(!destination.join("readme.txt").exists());
+
+        ctx.cleanup();
+    }
+
+    #[test]
+    fn test_from_context() {
+        let mut ctx = TestContext::new("extract-from-context");
+
+        let extractor = Extractor::from_context(&ctx).unwrap();
+        assert_eq!(extractor.seven_zip, None);
+        assert_eq!(extractor.lessmsi, None);
+        assert!(!extractor.prefer_7zip);
+
+        let mut helpers = vec![];
+        for (app, executable) in [("7zip", "7z.exe"), ("lessmsi", "lessmsi.exe")] {
+            let current = ctx.apps_path().join(app).join("current");
+            std::fs::create_dir_all(&current).unwrap();
+            std::fs::write(current.join(executable), "").unwrap();
+            helpers.push(current.join(executable));
+        }
+
+        let extractor = Extractor::from_context(&ctx).unwrap();
+        assert_eq!(extractor.seven_zip.as_ref(), Some(&helpers[0]));
+        assert_eq!(extractor.lessmsi.as_ref(), Some(&helpers[1]));
+        assert!(!extractor.prefer_7zip);
+
+        // `use_external_7zip` uses the 7-Zip on the PATH for everything, instead of the installed app
+        ctx.config_mut().use_external_7zip = true;
+        match (Extractor::from_context(&ctx), which::which("7z")) {
+            (Ok(extractor), Ok(seven_zip)) => {
+                assert_eq!(extractor.seven_zip, Some(seven_zip));
+                assert_eq!(extractor.lessmsi.as_ref(), Some(&helpers[1]));
+                assert!(extractor.prefer_7zip);
+            }
+            (Err(Error::Missing7zip(_)), Err(_)) => {}
+            (result, seven_zip) => panic!("unexpected {result:?} with 7z at {seven_zip:?}"),
+        }
+
+        ctx.cleanup();
+    }
+}
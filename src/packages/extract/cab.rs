@@ -0,0 +1,509 @@
+//! A reader for Microsoft cabinet files, as embedded in MSI packages
+//!
+//! Only uncompressed and MSZIP compressed folders are supported.
+//! Quantum and LZX folders are rejected with [`Error::UnsupportedCompression`],
+//! in which case MSI packages are extracted with lessmsi or msiexec instead (see [`super::Extractor`])
+
+use std::io::{Read, Seek, SeekFrom};
+
+use miniz_oxide::inflate::{
+    core::{decompress, DecompressorOxide},
+    TINFLStatus,
+};
+
+use super::{Error, Result};
+
+/// The size of the MSZIP history window. Blocks may refer back to up to 32KiB of previous blocks
+const WINDOW_SIZE: usize = 1 << 16;
+
+const FLAG_PREV_CABINET: u16 = 0x0001;
+const FLAG_NEXT_CABINET: u16 = 0x0002;
+const FLAG_RESERVE_PRESENT: u16 = 0x0004;
+
+/// The first folder index used for files continued from or into another cabinet
+const FOLDER_CONTINUED: u16 = 0xFFFD;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Compression {
+    None,
+    MsZip,
+}
+
+#[derive(Debug, Clone)]
+struct Folder {
+    offset: u32,
+    blocks: u16,
+    compression: Compression,
+}
+
+#[derive(Debug, Clone)]
+/// A file stored in a cabinet
+pub struct CabFile {
+    /// The file's name
+    pub name: String,
+    /// The file's uncompressed size
+    pub size: u32,
+    folder: u16,
+    offset: u32,
+}
+
+#[derive(Debug)]
+/// A cabinet file
+pub struct Cabinet<R> {
+    reader: R,
+    folders: Vec<Folder>,
+    files: Vec<CabFile>,
+    data_reserve: u64,
+}
+
+fn invalid(message: impl Into<String>) -> Error {
+    Error::InvalidCabinet(message.into())
+}
+
+fn read_bytes<const N: usize>(reader: &mut impl Read) -> Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_cstring(reader: &mut impl Read) -> Result<String> {
+    let mut bytes = vec![];
+    loop {
+        match read_bytes::<1>(reader)? {
+            [0] => break,
+            [byte] => bytes.push(byte),
+        }
+    }
+
+    Ok(String::from_utf8_lossy(&bytes).to_string())
+}
+
+fn skip(reader: &mut impl Read, length: u64) -> std::io::Result<()> {
+    std::io::copy(&mut reader.take(length), &mut std::io::sink())?;
+    Ok(())
+}
+
+impl<R: Read + Seek> Cabinet<R> {
+    /// Read a cabinet's header and file list
+    ///
+    /// # Errors
+    /// - The reader does not contain a valid cabinet
+    /// - The cabinet uses an unsupported compression method
+    pub fn new(mut reader: R) -> Result<Self> {
+        let header = read_bytes::<36>(&mut reader)?;
+        if &header[0..4] != b"MSCF" {
+            return Err(invalid("missing cabinet signature"));
+        }
+
+        let u16_at = |offset: usize| u16::from_le_bytes([header[offset], header[offset + 1]]);
+        let files_offset = u32::from_le_bytes([header[16], header[17], header[18], header[19]]);
+        let (folder_count, file_count, flags) = (u16_at(26), u16_at(28), u16_at(30));
+
+        let (mut folder_reserve, mut data_reserve) = (0, 0);
+        if flags & FLAG_RESERVE_PRESENT != 0 {
+            let [h0, h1, folder, data] = read_bytes::<4>(&mut reader)?;
+            (folder_reserve, data_reserve) = (u64::from(folder), u64::from(data));
+            skip(&mut reader, u64::from(u16::from_le_bytes([h0, h1])))?;
+        }
+        for flag in [FLAG_PREV_CABINET, FLAG_NEXT_CABINET] {
+            if flags & flag != 0 {
+                // The cabinet and disk names
+                read_cstring(&mut reader)?;
+                read_cstring(&mut reader)?;
+            }
+        }
+
+        let folders = (0..folder_count)
+            .map(|_| {
+                let folder = read_bytes::<8>(&mut reader)?;
+                skip(&mut reader, folder_reserve)?;
+
+                let compression = match u16::from_le_bytes([folder[6], folder[7]]) & 0x000F {
+                    0 => Compression::None,
+                    1 => Compression::MsZip,
+                    2 => return Err(Error::UnsupportedCompression("Quantum".into())),
+                    3 => return Err(Error::UnsupportedCompression("LZX".into())),
+                    other => return Err(invalid(format!("unknown compression type {other}"))),
+                };
+
+                Ok(Folder {
+                    offset: u32::from_le_bytes([folder[0], folder[1], folder[2], folder[3]]),
+                    blocks: u16::from_le_bytes([folder[4], folder[5]]),
+                    compression,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        reader.seek(SeekFrom::Start(u64::from(files_offset)))?;
+        let files = (0..file_count)
+            .map(|_| {
+                let file = read_bytes::<16>(&mut reader)?;
+
+                Ok(CabFile {
+                    size: u32::from_le_bytes([file[0], file[1], file[2], file[3]]),
+                    offset: u32::from_le_bytes([file[4], file[5], file[6], file[7]]),
+                    folder: u16::from_le_bytes([file[8], file[9]]),
+                    name: read_cstring(&mut reader)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            reader,
+            folders,
+            files,
+            data_reserve,
+        })
+    }
+
+    /// Decompress each file in the cabinet, passing its contents to the callback
+    ///
+    /// Files are visited in the order they are stored, which is not necessarily the order they are listed in
+    ///
+    /// # Errors
+    /// - The cabinet's data is invalid
+    /// - A file continues into another cabinet
+    /// - The callback failed
+    pub fn for_each_file(
+        &mut self,
+        mut each: impl FnMut(&CabFile, &mut dyn Read) -> Result<()>,
+    ) -> Result<()> {
+        if let Some(file) = self
+            .files
+            .iter()
+            .find(|file| file.folder >= FOLDER_CONTINUED)
+        {
+            return Err(invalid(format!(
+                "{} spans multiple cabinets, which is not supported",
+                file.name
+            )));
+        }
+
+        for (index, folder) in self.folders.iter().enumerate() {
+            let mut files = self
+                .files
+                .iter()
+                .filter(|file| usize::from(file.folder) == index)
+                .collect::<Vec<_>>();
+            files.sort_by_key(|file| file.offset);
+
+            self.reader
+                .seek(SeekFrom::Start(u64::from(folder.offset)))?;
+            let mut data = FolderReader::new(&mut self.reader, folder, self.data_reserve);
+            let mut position = 0;
+
+            for file in files {
+                let Some(gap) = file.offset.checked_sub(position) else {
+                    return Err(invalid(format!("{} overlaps another file", file.name)));
+                };
+                skip(&mut data, u64::from(gap))?;
+
+                let mut contents = (&mut data).take(u64::from(file.size));
+                each(file, &mut contents)?;
+                std::io::copy(&mut contents, &mut std::io::sink())?;
+
+                position = file.offset + file.size;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads the decompressed contents of a folder, one data block at a time
+struct FolderReader<'a, R> {
+    reader: &'a mut R,
+    compression: Compression,
+    remaining_blocks: u16,
+    data_reserve: u64,
+    block: Vec<u8>,
+    position: usize,
+    window: Vec<u8>,
+    window_position: usize,
+}
+
+impl<'a, R: Read> FolderReader<'a, R> {
+    fn new(reader: &'a mut R, folder: &Folder, data_reserve: u64) -> Self {
+        Self {
+            reader,
+            compression: folder.compression,
+            remaining_blocks: folder.blocks,
+            data_reserve,
+            block: vec![],
+            position: 0,
+            window: vec![],
+            window_position: 0,
+        }
+    }
+
+    fn next_block(&mut self) -> std::io::Result<()> {
+        let mut header = [0; 8];
+        self.reader.read_exact(&mut header)?;
+        skip(self.reader, self.data_reserve)?;
+
+        let compressed_size = u16::from_le_bytes([header[4], header[5]]);
+        let uncompressed_size = u16::from_le_bytes([header[6], header[7]]);
+
+        let mut data = vec![0; usize::from(compressed_size)];
+        self.reader.read_exact(&mut data)?;
+
+        self.remaining_blocks -= 1;
+        self.position = 0;
+        self.block = match self.compression {
+            Compression::None => data,
+            Compression::MsZip => self.inflate(&data)?,
+        };
+
+        if self.block.len() != usize::from(uncompressed_size) {
+            return Err(corrupt("data block has the wrong uncompressed size"));
+        }
+
+        Ok(())
+    }
+
+    /// Inflate an MSZIP block
+    ///
+    /// Each block is a separate deflate stream, but may refer back to data from previous blocks,
+    /// so the output is kept in a wrapping history window
+    fn inflate(&mut self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        let Some(mut input) = data.strip_prefix(b"CK") else {
+            return Err(corrupt("MSZIP block is missing its signature"));
+        };
+
+        if self.window.is_empty() {
+            self.window = vec![0; WINDOW_SIZE];
+        }
+
+        let mut state = DecompressorOxide::new();
+        let mut output = vec![];
+        loop {
+            let (status, consumed, written) =
+                decompress(&mut state, input, &mut self.window, self.window_position, 0);
+
+            output.extend_from_slice(
+                &self.window[self.window_position..self.window_position + written],
+            );
+            self.window_position = (self.window_position + written) % WINDOW_SIZE;
+            input = &input[consumed..];
+
+            match status {
+                TINFLStatus::Done => return Ok(output),
+                TINFLStatus::HasMoreOutput => {}
+                _ => return Err(corrupt("invalid MSZIP block")),
+            }
+        }
+    }
+}
+
+fn corrupt(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+impl<R: Read> Read for FolderReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.position == self.block.len() {
+            if self.remaining_blocks == 0 {
+                return Ok(0);
+            }
+            self.next_block()?;
+        }
+
+        let length = buf.len().min(self.block.len() - self.position);
+        buf[..length].copy_from_slice(&self.block[self.position..self.position + length]);
+        self.position += length;
+
+        Ok(length)
+    }
+}
+
+#[cfg(test)]
+pub(super) mod testing {
+    /// Build a single folder cabinet containing the given files, split into blocks of the given size
+    pub fn cabinet(files: &[(&str, &[u8])], compress: bool, block_size: usize) -> Vec<u8> {
+        let contents = files
+            .iter()
+            .flat_map(|(_, data)| data.iter().copied())
+            .collect::<Vec<_>>();
+        let blocks = contents
+            .chunks(block_size)
+            .map(|chunk| {
+                if compress {
+                    (mszip(chunk), chunk.len())
+                } else {
+                    (chunk.to_vec(), chunk.len())
+                }
+            })
+            .collect::<Vec<_>>();
+
+        with_blocks(files, &blocks, u16::from(compress))
+    }
+
+    /// Compress a data block independently of any previous blocks
+    pub fn mszip(data: &[u8]) -> Vec<u8> {
+        let mut block = b"CK".to_vec();
+        block.extend(miniz_oxide::deflate::compress_to_vec(data, 6));
+        block
+    }
+
+    /// Build a single folder cabinet containing the given files, from already encoded data blocks
+    ///
+    /// Each block is given with its uncompressed size. `compression` is the folder's compression type
+    pub fn with_blocks(
+        files: &[(&str, &[u8])],
+        blocks: &[(Vec<u8>, usize)],
+        compression: u16,
+    ) -> Vec<u8> {
+        let mut entries = vec![];
+        let mut offset = 0u32;
+        for (name, data) in files {
+            let size = u32::try_from(data.len()).unwrap();
+            entries.extend(size.to_le_bytes());
+            entries.extend(offset.to_le_bytes());
+            entries.extend([0; 8]);
+            entries.extend(name.as_bytes());
+            entries.push(0);
+            offset += size;
+        }
+
+        let data_offset = 44 + entries.len();
+        let total_size = data_offset
+            + blocks
+                .iter()
+                .map(|(block, _)| 8 + block.len())
+                .sum::<usize>();
+
+        let u16_le = |value: usize| u16::try_from(value).unwrap().to_le_bytes();
+        let u32_le = |value: usize| u32::try_from(value).unwrap().to_le_bytes();
+
+        let mut cabinet = b"MSCF".to_vec();
+        cabinet.extend([0; 4]);
+        cabinet.extend(u32_le(total_size));
+        cabinet.extend([0; 4]);
+        cabinet.extend(u32_le(44));
+        cabinet.extend([0; 4]);
+        cabinet.extend([3, 1]);
+        cabinet.extend(u16_le(1));
+        cabinet.extend(u16_le(files.len()));
+        cabinet.extend([0; 6]);
+
+        cabinet.extend(u32_le(data_offset));
+        cabinet.extend(u16_le(blocks.len()));
+        cabinet.extend(compression.to_le_bytes());
+
+        cabinet.extend(entries);
+
+        for (block, uncompressed_size) in blocks {
+            cabinet.extend([0; 4]);
+            cabinet.extend(u16_le(block.len()));
+            cabinet.extend(u16_le(*uncompressed_size));
+            cabinet.extend(block);
+        }
+
+        cabinet
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn read_all(cabinet: Vec<u8>) -> Vec<(String, Vec<u8>)> {
+        let mut cabinet = Cabinet::new(Cursor::new(cabinet)).unwrap();
+        let mut files = vec![];
+
+        cabinet
+            .for_each_file(|file, data| {
+                let mut contents = vec![];
+                data.read_to_end(&mut contents)?;
+                files.push((file.name.clone(), contents));
+                Ok(())
+            })
+            .unwrap();
+
+        files
+    }
+
+    #[test]
+    fn test_read_cabinet() {
+        let large = "sprinkles ".repeat(10_000);
+        let files: [(&str, &[u8]); 3] = [
+            ("app.exe", b"binary"),
+            ("large.txt", large.as_bytes()),
+            ("empty.txt", b""),
+        ];
+
+        for compress in [false, true] {
+            let extracted = read_all(testing::cabinet(&files, compress, 32 * 1024));
+
+            assert_eq!(
+                extracted,
+                files.map(|(name, data)| (name.to_string(), data.to_vec()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_mszip_history() {
+        let first = b"hello sprinkles ";
+
+        // A fixed Huffman block, made only of a 16 byte match 16 bytes back, in the first block
+        let mut second = b"CK".to_vec();
+        let mut bits = 0u32;
+        let mut length = 0;
+        let mut push = |value: u32, count: u32, reverse: bool| {
+            let value = if reverse {
+                value.reverse_bits() >> (32 - count)
+            } else {
+                value
+            };
+            bits |= value << length;
+            length += count;
+        };
+        // Final block, with fixed Huffman codes
+        push(0b011, 3, false);
+        // Length code 267 (15-16), with 1 extra bit
+        push(0b000_1011, 7, true);
+        push(1, 1, false);
+        // Distance code 7 (13-16), with 2 extra bits
+        push(0b00111, 5, true);
+        push(3, 2, false);
+        // End of block
+        push(0, 7, true);
+        second.extend(bits.to_le_bytes());
+
+        let files: [(&str, &[u8]); 2] = [("first.txt", first), ("second.txt", first)];
+        let cabinet = testing::with_blocks(
+            &files,
+            &[(testing::mszip(first), first.len()), (second, first.len())],
+            1,
+        );
+
+        assert_eq!(
+            read_all(cabinet),
+            files.map(|(name, data)| (name.to_string(), data.to_vec()))
+        );
+    }
+
+    #[test]
+    fn test_unsupported_compression() {
+        let files: [(&str, &[u8]); 1] = [("app.exe", b"binary")];
+        let cabinet = testing::with_blocks(&files, &[(b"binary".to_vec(), 6)], 3);
+
+        assert!(matches!(
+            Cabinet::new(Cursor::new(cabinet)),
+            Err(Error::UnsupportedCompression(compression)) if compression == "LZX"
+        ));
+    }
+
+    #[test]
+    fn test_invalid_cabinet() {
+        assert!(matches!(
+            Cabinet::new(Cursor::new(
+                b"not a cabinet, but long enough to read a header".to_vec()
+            )),
+            Err(Error::InvalidCabinet(_))
+        ));
+    }
+}
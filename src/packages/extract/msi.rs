@@ -0,0 +1,732 @@
+//! Native MSI extraction
+//!
+//! An MSI package is a compound file holding a database of tables, and usually the cabinets with the package's files.
+//! Files are laid out as an administrative install would, relative to the package's root directory (`SourceDir`),
+//! so `extract_dir` entries written for msiexec and lessmsi work unchanged.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{Cursor, Read, Seek},
+    path::{Path, PathBuf},
+};
+
+use cfb::CompoundFile;
+
+use super::{cab::Cabinet, relative_path, Error, Result};
+
+const COLUMN_STRING: u16 = 0x0800;
+const COLUMN_NON_BINARY: u16 = 0x0400;
+const COLUMN_WIDTH: u16 = 0x00FF;
+
+/// The string pool flag for three byte string references
+const LONG_STRING_REFS: u32 = 0x8000_0000;
+
+fn invalid(message: impl Into<String>) -> Error {
+    Error::InvalidMsi(message.into())
+}
+
+/// Map a character to its 6 bit value in MSI's stream name encoding
+fn encode_char(c: char) -> Option<u32> {
+    let value = match c {
+        '0'..='9' => c as u32 - '0' as u32,
+        'A'..='Z' => c as u32 - 'A' as u32 + 10,
+        'a'..='z' => c as u32 - 'a' as u32 + 36,
+        '.' => 62,
+        '_' => 63,
+        _ => return None,
+    };
+
+    Some(value)
+}
+
+/// Encode a stream name as MSI stores it in the compound file
+///
+/// Pairs of characters are packed into a single character, so long table names fit in the compound file's name limit.
+/// Table streams are prefixed with a marker character
+pub(super) fn encode_stream_name(name: &str, table: bool) -> String {
+    let mut encoded = String::new();
+    if table {
+        encoded.push('\u{4840}');
+    }
+
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        let Some(first) = encode_char(c) else {
+            encoded.push(c);
+            continue;
+        };
+
+        let packed = match chars.peek().copied().and_then(encode_char) {
+            Some(second) => {
+                chars.next();
+                0x3800 + first + (second << 6)
+            }
+            None => 0x4800 + first,
+        };
+        encoded.push(char::from_u32(packed).expect("packed stream names are valid characters"));
+    }
+
+    encoded
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A value in a table row
+enum Value {
+    Null,
+    Int(i32),
+    Str(String),
+}
+
+impl Value {
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn as_int(&self) -> Option<i32> {
+        match self {
+            Value::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Column {
+    name: String,
+    kind: u16,
+}
+
+impl Column {
+    fn is_string(&self) -> bool {
+        self.kind & COLUMN_STRING != 0 && self.kind & COLUMN_NON_BINARY != 0
+    }
+
+    fn is_binary(&self) -> bool {
+        self.kind & COLUMN_STRING != 0 && self.kind & COLUMN_NON_BINARY == 0
+    }
+
+    fn size(&self, long_refs: bool) -> usize {
+        if self.is_string() && long_refs {
+            3
+        } else if self.is_string() || self.is_binary() || self.kind & COLUMN_WIDTH <= 2 {
+            2
+        } else {
+            4
+        }
+    }
+}
+
+/// A table row, mapping column names to values
+type Row = HashMap<String, Value>;
+
+fn get<'a>(row: &'a Row, column: &str) -> Option<&'a str> {
+    row.get(column).and_then(Value::as_str)
+}
+
+/// An MSI database
+struct Database<F> {
+    file: CompoundFile<F>,
+    strings: Vec<String>,
+    long_refs: bool,
+    tables: HashMap<String, Vec<Column>>,
+}
+
+impl<F: Read + Seek> Database<F> {
+    fn open(inner: F) -> Result<Self> {
+        let mut file = CompoundFile::open(inner).map_err(|_| invalid("not a compound file"))?;
+
+        let pool = read_stream(&mut file, &encode_stream_name("_StringPool", true))?
+            .ok_or_else(|| invalid("missing string pool"))?;
+        let data =
+            read_stream(&mut file, &encode_stream_name("_StringData", true))?.unwrap_or_default();
+        let (strings, long_refs) = parse_strings(&pool, &data)?;
+
+        let mut database = Self {
+            file,
+            strings,
+            long_refs,
+            tables: HashMap::new(),
+        };
+
+        let columns_schema = [
+            ("Table", 0x0D40),
+            ("Number", 0x0502),
+            ("Name", 0x0D40),
+            ("Type", 0x0502),
+        ]
+        .map(|(name, kind)| Column {
+            name: name.to_string(),
+            kind,
+        })
+        .to_vec();
+        database
+            .tables
+            .insert("_Columns".to_string(), columns_schema);
+
+        let mut columns = database
+            .table("_Columns")?
+            .into_iter()
+            .filter_map(|row| {
+                let table = get(&row, "Table")?.to_string();
+                let number = row.get("Number")?.as_int()?;
+                let name = get(&row, "Name")?.to_string();
+                let kind = u16::try_from(row.get("Type")?.as_int()? & 0xFFFF).ok()?;
+
+                Some((table, number, Column { name, kind }))
+            })
+            .collect::<Vec<_>>();
+        columns.sort_by_key(|(_, number, _)| *number);
+
+        for (table, _, column) in columns {
+            database.tables.entry(table).or_default().push(column);
+        }
+
+        Ok(database)
+    }
+
+    /// Read a table's rows. Tables without rows have no stream, and return an empty list
+    fn table(&mut self, name: &str) -> Result<Vec<Row>> {
+        let columns = self
+            .tables
+            .get(name)
+            .ok_or_else(|| invalid(format!("missing {name} table")))?;
+        let Some(data) = read_stream(&mut self.file, &encode_stream_name(name, true))? else {
+            return Ok(vec![]);
+        };
+
+        let row_size = columns
+            .iter()
+            .map(|column| column.size(self.long_refs))
+            .sum::<usize>();
+        if row_size == 0 || data.len() % row_size != 0 {
+            return Err(invalid(format!("{name} table has a partial row")));
+        }
+        let row_count = data.len() / row_size;
+
+        let mut rows = vec![Row::new(); row_count];
+        // Tables are stored column by column
+        let mut offset = 0;
+        for column in columns {
+            let size = column.size(self.long_refs);
+
+            for row in &mut rows {
+                let bytes = &data[offset..offset + size];
+                offset += size;
+
+                let raw = bytes
+                    .iter()
+                    .rev()
+                    .fold(0usize, |raw, byte| (raw << 8) | usize::from(*byte));
+
+                let value = if raw == 0 {
+                    Value::Null
+                } else if column.is_string() {
+                    let string = self
+                        .strings
+                        .get(raw)
+                        .ok_or_else(|| invalid(format!("{name} refers to a missing string")))?;
+                    Value::Str(string.clone())
+                } else if column.is_binary() {
+                    // Binary columns refer to streams, which are not needed for extraction
+                    Value::Null
+                } else if let Ok(bytes) = <[u8; 2]>::try_from(bytes) {
+                    // Integers are stored with their sign bit flipped
+                    Value::Int(i32::from(i16::from_le_bytes(bytes) ^ i16::MIN))
+                } else {
+                    let bytes =
+                        <[u8; 4]>::try_from(bytes).expect("integer columns are 2 or 4 bytes");
+                    Value::Int(i32::from_le_bytes(bytes) ^ i32::MIN)
+                };
+
+                row.insert(column.name.clone(), value);
+            }
+        }
+
+        Ok(rows)
+    }
+
+    fn stream(&mut self, name: &str) -> Result<Option<Vec<u8>>> {
+        read_stream(&mut self.file, &encode_stream_name(name, false))
+    }
+}
+
+fn read_stream<F: Read + Seek>(file: &mut CompoundFile<F>, name: &str) -> Result<Option<Vec<u8>>> {
+    let path = format!("/{name}");
+    if !file.is_stream(&path) {
+        return Ok(None);
+    }
+
+    let mut data = vec![];
+    file.open_stream(&path)?.read_to_end(&mut data)?;
+
+    Ok(Some(data))
+}
+
+/// Parse the string pool, returning the strings indexed from 1, and whether string references are three bytes long
+fn parse_strings(pool: &[u8], data: &[u8]) -> Result<(Vec<String>, bool)> {
+    if pool.len() < 4 {
+        return Err(invalid("string pool is too short"));
+    }
+
+    let header = u32::from_le_bytes([pool[0], pool[1], pool[2], pool[3]]);
+    let codepage = header & !LONG_STRING_REFS;
+
+    let words = pool[4..]
+        .chunks_exact(2)
+        .map(|word| usize::from(u16::from_le_bytes([word[0], word[1]])))
+        .collect::<Vec<_>>();
+
+    // String 0 is the null string
+    let mut strings = vec![String::new()];
+    let mut offset = 0;
+    let mut i = 0;
+    while i + 1 < words.len() {
+        let (mut length, refcount) = (words[i], words[i + 1]);
+        i += 2;
+
+        // Strings over 64KiB store the high word of their length in place of the reference count,
+        // followed by the low word and the real reference count
+        if length == 0 && refcount > 0 {
+            let low = *words
+                .get(i)
+                .ok_or_else(|| invalid("truncated string pool"))?;
+            length = (refcount << 16) | low;
+            i += 2;
+        }
+
+        let bytes = data
+            .get(offset..offset + length)
+            .ok_or_else(|| invalid("string pool is longer than its data"))?;
+        offset += length;
+
+        strings.push(if codepage == 65001 || bytes.is_ascii() {
+            String::from_utf8_lossy(bytes).to_string()
+        } else {
+            // Treat other codepages as Latin-1, which covers the names found in practice
+            bytes.iter().map(|byte| char::from(*byte)).collect()
+        });
+    }
+
+    Ok((strings, header & LONG_STRING_REFS != 0))
+}
+
+/// Get the long name from a `short|long` name pair
+fn long_name(name: &str) -> &str {
+    name.rsplit('|').next().unwrap_or(name)
+}
+
+/// Resolve each directory's path relative to the package root
+///
+/// Source names are used over target names, as an administrative install does
+fn resolve_directories(rows: &[Row]) -> Result<HashMap<String, PathBuf>> {
+    let parents = rows
+        .iter()
+        .filter_map(|row| {
+            let key = get(row, "Directory")?;
+            let parent = get(row, "Directory_Parent").filter(|parent| *parent != key);
+
+            let default_dir = get(row, "DefaultDir").unwrap_or(".");
+            let name = default_dir
+                .split_once(':')
+                .map_or(default_dir, |(_, source)| source);
+
+            Some((key, (parent, long_name(name))))
+        })
+        .collect::<HashMap<_, _>>();
+
+    let mut resolved = HashMap::new();
+    for key in parents.keys() {
+        let mut chain = vec![];
+        let mut current = Some(*key);
+        let mut seen = HashSet::new();
+
+        while let Some(key) = current {
+            if !seen.insert(key) {
+                return Err(invalid(format!("directory {key} is its own ancestor")));
+            }
+            let Some((parent, name)) = parents.get(key) else {
+                return Err(invalid(format!("missing parent directory {key}")));
+            };
+
+            // The root directory is the destination itself
+            if parent.is_some() && *name != "." {
+                chain.push(*name);
+            }
+            current = *parent;
+        }
+
+        let mut path = PathBuf::new();
+        for name in chain.iter().rev() {
+            path.push(relative_path(name)?);
+        }
+        resolved.insert((*key).to_string(), path);
+    }
+
+    Ok(resolved)
+}
+
+fn write_file(path: &Path, data: &mut dyn Read) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::io::copy(data, &mut File::create(path)?)?;
+
+    Ok(())
+}
+
+/// Unpack the files in a cabinet, which are named by their key in the File table
+fn unpack_cabinet(
+    cabinet: impl Read + Seek,
+    files: &HashMap<String, PathBuf>,
+    destination: &Path,
+    extracted: &mut HashSet<String>,
+    tick: &mut impl FnMut(),
+) -> Result<()> {
+    Cabinet::new(cabinet)?.for_each_file(|file, data| {
+        let Some(path) = files.get(&file.name) else {
+            debug!("Skipping {}, which is not in the File table", file.name);
+            return Ok(());
+        };
+
+        write_file(&destination.join(path), data)?;
+        extracted.insert(file.name.clone());
+        tick();
+
+        Ok(())
+    })
+}
+
+/// Extract an MSI package into the destination, calling `tick` after each file
+///
+/// # Errors
+/// - The package is not a valid MSI
+/// - A cabinet could not be read, or uses an unsupported compression method
+/// - A directory or file name points outside of the destination
+/// - An uncompressed file is missing from beside the package
+pub(super) fn extract(msi: &Path, destination: &Path, mut tick: impl FnMut()) -> Result<()> {
+    let mut database = Database::open(File::open(msi)?)?;
+
+    let directories = resolve_directories(&database.table("Directory")?)?;
+
+    let components = database
+        .table("Component")?
+        .into_iter()
+        .filter_map(|row| {
+            Some((
+                get(&row, "Component")?.to_string(),
+                get(&row, "Directory_")?.to_string(),
+            ))
+        })
+        .collect::<HashMap<_, _>>();
+
+    let mut files = HashMap::new();
+    for row in database.table("File")? {
+        let (Some(key), Some(component), Some(name)) = (
+            get(&row, "File"),
+            get(&row, "Component_"),
+            get(&row, "FileName"),
+        ) else {
+            continue;
+        };
+
+        let directory = components
+            .get(component)
+            .and_then(|directory| directories.get(directory))
+            .ok_or_else(|| invalid(format!("file {key} has no directory")))?;
+
+        files.insert(
+            key.to_string(),
+            directory.join(relative_path(long_name(name))?),
+        );
+    }
+
+    let mut extracted = HashSet::new();
+    for media in database.table("Media")? {
+        let Some(cabinet) = get(&media, "Cabinet") else {
+            continue;
+        };
+
+        if let Some(stream) = cabinet.strip_prefix('#') {
+            let data = database
+                .stream(stream)?
+                .ok_or_else(|| invalid(format!("missing embedded cabinet {stream}")))?;
+
+            unpack_cabinet(
+                Cursor::new(data),
+                &files,
+                destination,
+                &mut extracted,
+                &mut tick,
+            )?;
+        } else {
+            let path = msi
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join(relative_path(cabinet)?);
+
+            unpack_cabinet(
+                File::open(path)?,
+                &files,
+                destination,
+                &mut extracted,
+                &mut tick,
+            )?;
+        }
+    }
+
+    // Files outside of any cabinet are stored uncompressed beside the package, in the same layout
+    let source_dir = msi.parent().unwrap_or(Path::new(""));
+    for (key, path) in &files {
+        if extracted.contains(key) {
+            continue;
+        }
+
+        let source = source_dir.join(path);
+        if !source.is_file() {
+            return Err(Error::MissingMsiFile(key.clone()));
+        }
+        write_file(&destination.join(path), &mut File::open(source)?)?;
+        tick();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::contexts::{testing::TestContext, ScoopContext};
+
+    /// Builds the streams of an MSI database
+    #[derive(Default)]
+    struct Builder {
+        strings: Vec<String>,
+        columns: Vec<[u32; 4]>,
+        tables: Vec<(String, Vec<u8>)>,
+    }
+
+    impl Builder {
+        fn string(&mut self, value: &str) -> u32 {
+            let index = self
+                .strings
+                .iter()
+                .position(|string| string == value)
+                .unwrap_or_else(|| {
+                    self.strings.push(value.to_string());
+                    self.strings.len() - 1
+                });
+
+            u32::try_from(index).unwrap() + 1
+        }
+
+        /// Add a table of string (`s`) and short integer (`i`) columns
+        fn table(&mut self, name: &str, columns: &[(&str, char)], rows: &[&[&str]]) {
+            let table = self.string(name);
+            let mut data = vec![];
+
+            for (number, (column, kind)) in columns.iter().enumerate() {
+                let column_name = self.string(column);
+                let kind = if *kind == 's' { 0x1D48 } else { 0x1502 };
+                self.columns
+                    .push([table, u32::try_from(number).unwrap() + 1, column_name, kind]);
+
+                for row in rows {
+                    let value = row[number];
+                    let raw = if value.is_empty() {
+                        0
+                    } else if kind == 0x1502 {
+                        value.parse::<u32>().unwrap() ^ 0x8000
+                    } else {
+                        self.string(value)
+                    };
+                    data.extend(u16::try_from(raw).unwrap().to_le_bytes());
+                }
+            }
+
+            self.tables.push((name.to_string(), data));
+        }
+
+        fn write(mut self, path: &Path, streams: &[(&str, Vec<u8>)]) {
+            let mut columns = vec![];
+            for field in 0..4 {
+                for row in &self.columns {
+                    let value = if field % 2 == 1 {
+                        row[field] ^ 0x8000
+                    } else {
+                        row[field]
+                    };
+                    columns.extend(u16::try_from(value).unwrap().to_le_bytes());
+                }
+            }
+            self.tables.push(("_Columns".to_string(), columns));
+
+            let mut pool = 1252u32.to_le_bytes().to_vec();
+            let mut data = vec![];
+            for string in &self.strings {
+                pool.extend(u16::try_from(string.len()).unwrap().to_le_bytes());
+                pool.extend(1u16.to_le_bytes());
+                data.extend(string.as_bytes());
+            }
+
+            let mut file = cfb::create(path).unwrap();
+            let mut add = |name: String, contents: &[u8]| {
+                file.create_stream(format!("/{name}"))
+                    .unwrap()
+                    .write_all(contents)
+                    .unwrap();
+            };
+
+            add(encode_stream_name("_StringPool", true), &pool);
+            add(encode_stream_name("_StringData", true), &data);
+            for (name, contents) in &self.tables {
+                add(encode_stream_name(name, true), contents);
+            }
+            for (name, contents) in streams {
+                add(encode_stream_name(name, false), contents);
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_stream_name() {
+        assert_eq!(
+            encode_stream_name("_Tables", true),
+            "\u{4840}\u{3f7f}\u{4164}\u{422f}\u{4836}"
+        );
+        assert_eq!(encode_stream_name("a b", false), "\u{4824} \u{4825}");
+    }
+
+    /// Write an MSI package installing `app.exe` and `docs/readme.txt` to `PFiles/My App`,
+    /// with the given embedded cabinet
+    fn package(path: &Path, cabinet: Vec<u8>) {
+        let mut builder = Builder::default();
+        builder.table(
+            "Directory",
+            &[
+                ("Directory", 's'),
+                ("Directory_Parent", 's'),
+                ("DefaultDir", 's'),
+            ],
+            &[
+                &["TARGETDIR", "", "SourceDir"],
+                &["ProgramFilesFolder", "TARGETDIR", ".:PFiles"],
+                &["APPDIR", "ProgramFilesFolder", "MYAPP|My App"],
+                &["DOCS", "APPDIR", "docs"],
+            ],
+        );
+        builder.table(
+            "Component",
+            &[("Component", 's'), ("Directory_", 's')],
+            &[&["Main", "APPDIR"], &["Docs", "DOCS"]],
+        );
+        builder.table(
+            "File",
+            &[
+                ("File", 's'),
+                ("Component_", 's'),
+                ("FileName", 's'),
+                ("Sequence", 'i'),
+            ],
+            &[
+                &["app_exe", "Main", "APP.EXE|app.exe", "1"],
+                &["readme", "Docs", "README.TXT|readme.txt", "2"],
+            ],
+        );
+        builder.table(
+            "Media",
+            &[("DiskId", 'i'), ("LastSequence", 'i'), ("Cabinet", 's')],
+            &[&["1", "2", "#app.cab"]],
+        );
+
+        builder.write(path, &[("app.cab", cabinet)]);
+    }
+
+    #[test]
+    fn test_extract_msi() {
+        let ctx = TestContext::new("msi");
+        let dir = ctx.path();
+
+        let cabinet = super::super::cab::testing::cabinet(
+            &[("app_exe", b"binary"), ("readme", b"readme")],
+            true,
+            4,
+        );
+
+        let msi = dir.join("app.msi");
+        package(&msi, cabinet);
+
+        let mut files = 0;
+        extract(&msi, &dir.join("out"), || files += 1).unwrap();
+
+        let app_dir = dir.join("out").join("PFiles").join("My App");
+        assert_eq!(
+            std::fs::read_to_string(app_dir.join("app.exe")).unwrap(),
+            "binary"
+        );
+        assert_eq!(
+            std::fs::read_to_string(app_dir.join("docs").join("readme.txt")).unwrap(),
+            "readme"
+        );
+        assert_eq!(files, 2);
+
+        ctx.cleanup();
+    }
+
+    /// Write a fake lessmsi, which extracts `app.exe` into `SourceDir` as lessmsi would
+    fn write_lessmsi(dir: &Path) -> PathBuf {
+        cfg_if::cfg_if! {
+            if #[cfg(windows)] {
+                let path = dir.join("lessmsi.cmd");
+                std::fs::write(&path, "@mkdir \"%~3SourceDir\\PFiles\\My App\"\r\n@echo binary> \"%~3SourceDir\\PFiles\\My App\\app.exe\"\r\n").unwrap();
+                path
+            } else {
+                use std::os::unix::fs::PermissionsExt;
+
+                let path = dir.join("lessmsi.sh");
+                std::fs::write(&path, "#!/bin/sh\nmkdir -p \"$3SourceDir/PFiles/My App\"\nprintf binary > \"$3SourceDir/PFiles/My App/app.exe\"\n").unwrap();
+                std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+                path
+            }
+        }
+    }
+
+    #[test]
+    fn test_extract_lzx_msi() {
+        let ctx = TestContext::new("msi-lzx");
+        let dir = ctx.path();
+
+        let files: [(&str, &[u8]); 2] = [("app_exe", b"binary"), ("readme", b"readme")];
+        let cabinet = super::super::cab::testing::with_blocks(&files, &[(vec![0; 12], 12)], 3);
+
+        let msi = dir.join("app.msi");
+        package(&msi, cabinet);
+        let out = dir.join("out");
+
+        #[cfg(not(windows))]
+        assert!(matches!(
+            super::super::Extractor::new().extract(&msi, &out, None),
+            Err(Error::UnsupportedCompression(compression)) if compression == "LZX"
+        ));
+
+        super::super::Extractor::new()
+            .with_lessmsi(write_lessmsi(dir))
+            .extract(&msi, &out, None)
+            .unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(out.join("PFiles").join("My App").join("app.exe"))
+                .unwrap()
+                .trim(),
+            "binary"
+        );
+        assert!(!out.join("SourceDir").exists());
+
+        ctx.cleanup();
+    }
+}
@@ -0,0 +1,342 @@
+//! Scoop manifest formatting
+//!
+//! Formats manifests the same way as Scoop's `formatjson` script,
+//! so that written manifests can be committed to buckets without noisy diffs.
+
+use serde_json::{Map, Value};
+
+/// The canonical order of top level manifest keys, as used by Scoop's manifests
+///
+/// This is only used to position keys that were not present in the original manifest.
+pub const CANONICAL_ORDER: &[&str] = &[
+    "##",
+    "$schema",
+    "version",
+    "description",
+    "homepage",
+    "license",
+    "notes",
+    "depends",
+    "suggest",
+    "url",
+    "hash",
+    "architecture",
+    "extract_dir",
+    "extract_to",
+    "innosetup",
+    "pre_install",
+    "installer",
+    "post_install",
+    "bin",
+    "shortcuts",
+    "env_add_path",
+    "env_set",
+    "persist",
+    "psmodule",
+    "pre_uninstall",
+    "uninstaller",
+    "post_uninstall",
+    "checkver",
+    "autoupdate",
+];
+
+const INDENT: &str = "    ";
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+/// The line ending used when writing manifests
+pub enum LineEnding {
+    #[default]
+    /// `\r\n`, as written by Scoop
+    CrLf,
+    /// `\n`
+    Lf,
+}
+
+impl LineEnding {
+    #[must_use]
+    /// Detect the line ending used in the given contents
+    ///
+    /// Defaults to [`LineEnding::CrLf`] if the contents do not contain a line ending
+    pub fn detect(contents: &str) -> Self {
+        match contents.find('\n') {
+            Some(idx) if !contents[..idx].ends_with('\r') => Self::Lf,
+            _ => Self::CrLf,
+        }
+    }
+
+    #[must_use]
+    /// Get the line ending as a string
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Lf => "\n",
+        }
+    }
+}
+
+/// Normalize values the same way as Scoop's formatter
+///
+/// - Multi-line strings are split into arrays of trimmed lines
+/// - Single element arrays are collapsed into their element, unless the element is itself an array
+/// - Single element arrays within arrays are collapsed into their element
+///
+/// Like Scoop, this only descends into objects, not into objects within arrays.
+pub fn normalize(value: &mut Value) {
+    let Value::Object(map) = value else {
+        return;
+    };
+
+    for value in map.values_mut() {
+        if value.is_object() {
+            normalize(value);
+        }
+
+        if let Value::String(string) = value {
+            let parts = string
+                .split('\n')
+                .map(|part| Value::String(part.trim().to_string()))
+                .collect::<Vec<_>>();
+
+            if parts.len() > 1 {
+                *value = Value::Array(parts);
+            }
+        }
+
+        if let Value::Array(array) = value {
+            if array.len() == 1 {
+                if !array[0].is_array() {
+                    *value = array.remove(0);
+                }
+            } else {
+                for element in array.iter_mut() {
+                    if let Value::Array(inner) = element {
+                        if inner.len() == 1 {
+                            *element = inner.remove(0);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Merge an updated manifest into the original manifest, without losing any information
+///
+/// - `original` is the manifest as it was read from disk
+/// - `known` is the original manifest after a round trip through the typed manifest,
+///   which is used to tell unknown keys from keys that were removed
+/// - `updated` is the updated typed manifest
+///
+/// Values that were not changed keep their original representation,
+/// unknown keys are preserved, and keys keep their original order.
+/// New top level keys are inserted according to [`CANONICAL_ORDER`].
+#[must_use]
+pub fn merge(original: &Value, known: &Value, updated: &Value) -> Value {
+    merge_inner(original, known, updated, true)
+}
+
+fn merge_inner(original: &Value, known: &Value, updated: &Value, root: bool) -> Value {
+    if known == updated {
+        return original.clone();
+    }
+
+    let (Value::Object(original), Value::Object(updated)) = (original, updated) else {
+        return updated.clone();
+    };
+
+    let empty = Map::new();
+    let known = known.as_object().unwrap_or(&empty);
+
+    let mut entries: Vec<(String, Value)> = vec![];
+
+    for (key, original_value) in original {
+        if let Some(updated_value) = updated.get(key) {
+            let known_value = known.get(key).unwrap_or(&Value::Null);
+
+            entries.push((
+                key.clone(),
+                merge_inner(original_value, known_value, updated_value, false),
+            ));
+        } else if !known.contains_key(key) {
+            // The key is not part of the typed manifest, so it must be kept as is
+            entries.push((key.clone(), original_value.clone()));
+        }
+    }
+
+    for (key, value) in updated {
+        if original.contains_key(key) {
+            continue;
+        }
+
+        let position = if root {
+            canonical_position(&entries, key)
+        } else {
+            entries.len()
+        };
+
+        entries.insert(position, (key.clone(), value.clone()));
+    }
+
+    Value::Object(entries.into_iter().collect())
+}
+
+fn canonical_position(entries: &[(String, Value)], key: &str) -> usize {
+    let rank = |key: &str| CANONICAL_ORDER.iter().position(|k| *k == key);
+
+    let Some(key_rank) = rank(key) else {
+        return entries.len();
+    };
+
+    entries
+        .iter()
+        .rposition(|(existing, _)| rank(existing).is_some_and(|rank| rank < key_rank))
+        .map_or(0, |idx| idx + 1)
+}
+
+#[must_use]
+/// Format the value the same way as Scoop's formatter
+///
+/// This uses 4 space indentation, `": "` as the key separator,
+/// and ends the output with a line ending.
+pub fn to_string(value: &Value, line_ending: LineEnding) -> String {
+    let mut output = String::new();
+
+    write_value(&mut output, value, 0, line_ending.as_str());
+    output.push_str(line_ending.as_str());
+
+    output
+}
+
+fn write_value(output: &mut String, value: &Value, depth: usize, eol: &str) {
+    match value {
+        Value::Object(map) if map.is_empty() => output.push_str("{}"),
+        Value::Array(array) if array.is_empty() => output.push_str("[]"),
+        Value::Object(map) => {
+            output.push('{');
+            for (idx, (key, value)) in map.iter().enumerate() {
+                if idx != 0 {
+                    output.push(',');
+                }
+                output.push_str(eol);
+                output.push_str(&INDENT.repeat(depth + 1));
+                output.push_str(&Value::String(key.clone()).to_string());
+                output.push_str(": ");
+                write_value(output, value, depth + 1, eol);
+            }
+            output.push_str(eol);
+            output.push_str(&INDENT.repeat(depth));
+            output.push('}');
+        }
+        Value::Array(array) => {
+            output.push('[');
+            for (idx, value) in array.iter().enumerate() {
+                if idx != 0 {
+                    output.push(',');
+                }
+                output.push_str(eol);
+                output.push_str(&INDENT.repeat(depth + 1));
+                write_value(output, value, depth + 1, eol);
+            }
+            output.push_str(eol);
+            output.push_str(&INDENT.repeat(depth));
+            output.push(']');
+        }
+        value => output.push_str(&value.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_scoop_formatting() {
+        let value = json!({
+            "version": "1.0.0",
+            "bin": [["app.exe", "app"]],
+            "notes": ["Line 1", "Line 2"],
+            "env_set": {},
+        });
+
+        let formatted = to_string(&value, LineEnding::Lf);
+
+        assert_eq!(
+            formatted,
+            r#"{
+    "version": "1.0.0",
+    "bin": [
+        [
+            "app.exe",
+            "app"
+        ]
+    ],
+    "notes": [
+        "Line 1",
+        "Line 2"
+    ],
+    "env_set": {}
+}
+"#
+        );
+        assert_eq!(
+            to_string(&value, LineEnding::CrLf),
+            formatted.replace('\n', "\r\n")
+        );
+    }
+
+    #[test]
+    fn test_normalize() {
+        let mut value = json!({
+            "url": ["https://example.com"],
+            "bin": [["app.exe", "app"]],
+            "shortcuts": [["app.exe"], ["other.exe", "Other"]],
+            "notes": "Line 1\n  Line 2",
+            "architecture": { "64bit": { "hash": ["abc"] } },
+        });
+
+        normalize(&mut value);
+
+        assert_eq!(
+            value,
+            json!({
+                "url": "https://example.com",
+                "bin": [["app.exe", "app"]],
+                "shortcuts": ["app.exe", ["other.exe", "Other"]],
+                "notes": ["Line 1", "Line 2"],
+                "architecture": { "64bit": { "hash": "abc" } },
+            })
+        );
+    }
+
+    #[test]
+    fn test_merge_preserves_unknown_keys() {
+        let original = json!({
+            "version": "1.0.0",
+            "x-custom": { "keep": true },
+            "url": "https://example.com/1.0.0.zip",
+            "persist": "data",
+        });
+        let known = json!({
+            "version": "1.0.0",
+            "url": "https://example.com/1.0.0.zip",
+            "persist": "data",
+        });
+        let updated = json!({
+            "version": "2.0.0",
+            "url": "https://example.com/2.0.0.zip",
+            "hash": "abc",
+            "bin": "app.exe",
+        });
+
+        let merged = merge(&original, &known, &updated);
+
+        assert_eq!(
+            merged.as_object().unwrap().keys().collect::<Vec<_>>(),
+            ["version", "x-custom", "url", "hash", "bin"]
+        );
+        assert_eq!(merged["x-custom"], json!({ "keep": true }));
+        assert_eq!(merged["version"], "2.0.0");
+    }
+}
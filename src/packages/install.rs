@@ -0,0 +1,1151 @@
+//! Transactional package installs
+//!
+//! Installs packages the way Scoop's `install_app` does, recording how to undo each step as it goes.
+//! If any step fails, the completed steps are undone in reverse order,
+//! so a failed install leaves the system as it was, including any previously installed version of the app
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use futures::{future::BoxFuture, FutureExt};
+use indicatif::MultiProgress;
+
+use crate::{
+    cache, config,
+    contexts::ScoopContext,
+    handles::{
+        environment::{self, Backend, EnvironmentManager},
+        packages::PackageHandle,
+        shim::{self, ShimHandle},
+        shortcut::{self, ShortcutManager},
+    },
+    requests::AsyncClient,
+    scripts::engine::{RunOptions, ScriptEngine},
+    Architecture,
+};
+
+use super::{
+    dependencies,
+    extract::{self, Extractor},
+    installer::{self, InstallerHost},
+    models::manifest::InstallConfig,
+    persist::{link, remove_link},
+    psmodule,
+    reference::{self, manifest, package},
+    CreateManifest, InstallManifest, Manifest, NamedManifest,
+};
+
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+/// Install errors
+pub enum Error {
+    #[error("Install I/O error: {0}")]
+    IO(#[from] std::io::Error),
+    #[error("Package reference error: {0}")]
+    Reference(#[from] reference::Error),
+    #[error("Resolving dependencies: {0}")]
+    Dependencies(#[from] dependencies::Error),
+    #[error("Package manifest error: {0}")]
+    Packages(#[from] super::Error),
+    #[error("Downloading: {0}")]
+    Cache(#[from] cache::Error),
+    #[error("Extracting: {0}")]
+    Extract(#[from] extract::Error),
+    #[error("Running installer: {0}")]
+    Installer(#[from] installer::Error),
+    #[error("Creating shims: {0}")]
+    Shim(#[from] shim::Error),
+    #[error("Creating shortcuts: {0}")]
+    Shortcut(#[from] shortcut::Error),
+    #[error("Linking PowerShell module: {0}")]
+    PsModule(#[from] psmodule::Error),
+    #[error("Changing the environment: {0}")]
+    Environment(#[from] environment::Error),
+    #[error("Serializing install info: {0}")]
+    JSON(#[from] serde_json::Error),
+    #[error("{name} ({version}) is already installed")]
+    AlreadyInstalled { name: String, version: String },
+    #[error("Hash check failed for {url}. Expected {expected}, got {actual}")]
+    HashMismatch {
+        url: String,
+        expected: crate::hash::Hash,
+        actual: crate::hash::Hash,
+    },
+}
+
+/// Install result type
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Clone)]
+#[must_use]
+/// Options for installing packages
+pub struct InstallOptions {
+    arch: Architecture,
+    dependencies: bool,
+    engine: Option<Arc<dyn ScriptEngine>>,
+    environment: Option<Arc<dyn Backend + Send + Sync>>,
+    script_options: RunOptions,
+    progress: Option<MultiProgress>,
+}
+
+impl std::fmt::Debug for InstallOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InstallOptions")
+            .field("arch", &self.arch)
+            .field("dependencies", &self.dependencies)
+            .field("engine", &self.engine)
+            .field(
+                "environment",
+                &self.environment.as_ref().map(|_| "<backend>"),
+            )
+            .field("script_options", &self.script_options)
+            .field("progress", &self.progress)
+            .finish()
+    }
+}
+
+impl Default for InstallOptions {
+    fn default() -> Self {
+        Self {
+            arch: Architecture::default(),
+            dependencies: true,
+            engine: None,
+            environment: None,
+            script_options: RunOptions::default(),
+            progress: None,
+        }
+    }
+}
+
+impl InstallOptions {
+    /// Create the default install options
+    ///
+    /// Packages are installed for the current architecture, along with their dependencies
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Install the packages for the given architecture
+    pub fn with_arch(mut self, arch: Architecture) -> Self {
+        self.arch = arch;
+        self
+    }
+
+    /// Install the package's dependencies and installation helpers (i.e `7zip`) that are not installed yet
+    pub fn with_dependencies(mut self, dependencies: bool) -> Self {
+        self.dependencies = dependencies;
+        self
+    }
+
+    /// Run the manifests' scripts with the given engine, rather than `PowerShell`
+    pub fn with_engine(mut self, engine: Arc<dyn ScriptEngine>) -> Self {
+        self.engine = Some(engine);
+        self
+    }
+
+    /// Apply the manifests' environment changes to the given store
+    ///
+    /// Defaults to the context's environment in the registry on Windows.
+    /// Elsewhere, environment changes are skipped unless a store is given
+    pub fn with_environment(mut self, backend: impl Backend + Send + Sync + 'static) -> Self {
+        self.environment = Some(Arc::new(backend));
+        self
+    }
+
+    /// Run the installers and scripts with the given options (i.e timeout, environment and output streaming)
+    pub fn with_script_options(mut self, options: RunOptions) -> Self {
+        self.script_options = options;
+        self
+    }
+
+    /// Show download progress in the given progress bars
+    pub fn with_progress(mut self, progress: MultiProgress) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    fn environment(
+        &self,
+        ctx: &impl ScoopContext<Config = config::Scoop>,
+    ) -> Option<EnvironmentManager<Arc<dyn Backend + Send + Sync>>> {
+        #[cfg(windows)]
+        let default = Some(Arc::new(environment::registry::Registry::for_context(ctx))
+            as Arc<dyn Backend + Send + Sync>);
+        #[cfg(not(windows))]
+        let default = None;
+
+        self.environment
+            .clone()
+            .or(default)
+            .map(|backend| EnvironmentManager::new(ctx, backend))
+    }
+}
+
+type Undo = Box<dyn FnOnce() -> BoxFuture<'static, Result<()>> + Send>;
+
+#[derive(Default)]
+#[must_use]
+/// The steps of an install, and how to undo them
+struct Transaction {
+    steps: Vec<(&'static str, Undo)>,
+}
+
+impl Transaction {
+    /// Record how to undo a step
+    ///
+    /// Steps that change several things are recorded before they run,
+    /// so the undo must also handle the step having only partially completed
+    fn push(&mut self, step: &'static str, undo: impl FnOnce() -> Result<()> + Send + 'static) {
+        self.push_async(step, move || async move { undo() }.boxed());
+    }
+
+    fn push_async(
+        &mut self,
+        step: &'static str,
+        undo: impl FnOnce() -> BoxFuture<'static, Result<()>> + Send + 'static,
+    ) {
+        self.steps.push((step, Box::new(undo)));
+    }
+
+    /// Undo the recorded steps, in reverse order
+    ///
+    /// Failures are logged, and the remaining steps are still undone
+    async fn rollback(self) {
+        for (step, undo) in self.steps.into_iter().rev() {
+            debug!("Rolling back {step}");
+
+            if let Err(e) = undo().await {
+                error!("Failed to roll back {step}: {e}");
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Where a version of an app is installed
+struct Layout {
+    name: String,
+    version: String,
+    arch: Architecture,
+    version_dir: PathBuf,
+    current: PathBuf,
+    persist_dir: PathBuf,
+}
+
+impl Layout {
+    fn new(ctx: &impl ScoopContext, name: &str, version: &str, arch: Architecture) -> Self {
+        let app_dir = ctx.apps_path().join(name);
+
+        Self {
+            name: name.to_string(),
+            version: version.to_string(),
+            arch,
+            version_dir: app_dir.join(version),
+            current: app_dir.join("current"),
+            persist_dir: ctx.persist_path().join(name),
+        }
+    }
+
+    /// The directory shims, shortcuts and the environment refer to
+    ///
+    /// This is `current`, unless the config has `no_junction` set to `true`
+    fn dir(&self, ctx: &impl ScoopContext) -> &Path {
+        if ctx.symlinks_enabled() {
+            &self.current
+        } else {
+            &self.version_dir
+        }
+    }
+}
+
+/// The installed version of an app
+struct Previous {
+    layout: Layout,
+    manifest: Manifest,
+}
+
+impl Previous {
+    /// Find the version `current` links to, or the most recently installed version, if `current` is not linked
+    fn find(ctx: &impl ScoopContext, name: &str) -> Option<Self> {
+        let app_dir = ctx.apps_path().join(name);
+
+        let version_dir = std::fs::read_link(app_dir.join("current"))
+            .ok()
+            .or_else(|| {
+                app_dir
+                    .read_dir()
+                    .ok()?
+                    .filter_map(|entry| Some(entry.ok()?.path()))
+                    .filter(|path| path.join("install.json").exists())
+                    .max_by_key(|path| path.metadata().and_then(|meta| meta.modified()).ok())
+            })?;
+        let version = version_dir.file_name()?.to_string_lossy().to_string();
+
+        let manifest = Manifest::from_path(version_dir.join("manifest.json")).ok()?;
+        let arch = InstallManifest::from_path(version_dir.join("install.json"))
+            .ok()
+            .and_then(|install| install.architecture)
+            .unwrap_or_default();
+
+        Some(Self {
+            layout: Layout::new(ctx, name, &version, arch),
+            manifest,
+        })
+    }
+
+    /// Get a handle to the installed version
+    fn handle<'a, C: ScoopContext>(&self, ctx: &'a C) -> PackageHandle<'a, C> {
+        let mut reference = manifest::Reference::Name(self.layout.name.clone()).into_package_ref();
+        reference.set_version(self.layout.version.clone());
+
+        PackageHandle::from_manifest(
+            ctx,
+            reference,
+            NamedManifest::new(&self.layout.name, self.manifest.clone()),
+        )
+    }
+}
+
+/// Install a package, along with its dependencies
+///
+/// For each package, in dependency order:
+/// - Download the package's files into the cache, and check their hashes
+/// - Extract them into `apps/<name>/<version>`
+/// - Run the `pre_install` script and the installer
+/// - Link `current` to the version directory
+/// - Create the shims, Start Menu shortcuts and `PowerShell` module link
+/// - Apply the environment changes
+/// - Link the persisted data into the version directory
+/// - Run the `post_install` script
+/// - Write `manifest.json` and `install.json`
+///
+/// The install is transactional: if any step fails, for any package, every completed step is undone,
+/// including those of packages installed before it, and the error is returned.
+/// Previously installed versions are left as they were, with `current` linked to them.
+///
+/// Returns the installed manifests, in install order
+///
+/// # Errors
+/// - A package or dependency could not be found, or its version is already installed
+/// - A download failed, or its hash did not match the manifest
+/// - Any install step failed
+pub async fn install<C: ScoopContext<Config = config::Scoop>>(
+    ctx: &C,
+    reference: package::Reference,
+    options: &InstallOptions,
+) -> Result<Vec<NamedManifest>> {
+    let references = if options.dependencies {
+        Box::pin(dependencies::resolve(
+            ctx,
+            std::slice::from_ref(&reference),
+            options.arch,
+        ))
+        .await?
+    } else {
+        vec![reference]
+    };
+
+    let mut transaction = Transaction::default();
+    let mut installed = vec![];
+
+    for reference in references {
+        match Box::pin(install_package(ctx, &reference, options, &mut transaction)).await {
+            Ok(manifest) => installed.push(manifest),
+            Err(e) => {
+                transaction.rollback().await;
+                return Err(e);
+            }
+        }
+    }
+
+    Ok(installed)
+}
+
+async fn install_package<C: ScoopContext<Config = config::Scoop>>(
+    ctx: &C,
+    reference: &package::Reference,
+    options: &InstallOptions,
+    transaction: &mut Transaction,
+) -> Result<NamedManifest> {
+    let arch = options.arch;
+    let handle =
+        PackageHandle::from_manifest(ctx, reference.clone(), reference.manifest(ctx).await?);
+    let manifest = handle.remote_manifest();
+    let name = manifest.name().to_string();
+
+    let layout = Layout::new(ctx, &name, manifest.version.as_str(), arch);
+    if layout.version_dir.exists() {
+        return Err(Error::AlreadyInstalled {
+            name,
+            version: layout.version,
+        });
+    }
+    let previous = Previous::find(ctx, &name);
+
+    let downloads = download(ctx, manifest, options).await?;
+    let extractions = manifest.extractions(arch);
+
+    debug!("Installing {name} ({})", layout.version);
+
+    {
+        let app_dir = ctx.apps_path().join(&name);
+        let created_app_dir = !app_dir.exists();
+        let version_dir = layout.version_dir.clone();
+
+        transaction.push("version directory", move || {
+            if version_dir.exists() {
+                std::fs::remove_dir_all(&version_dir)?;
+            }
+            if created_app_dir && app_dir.exists() {
+                std::fs::remove_dir_all(&app_dir)?;
+            }
+            Ok(())
+        });
+    }
+
+    std::fs::create_dir_all(&layout.version_dir)?;
+    for (cached, extraction) in downloads.iter().zip(&extractions) {
+        std::fs::copy(cached, layout.version_dir.join(&extraction.file_name))?;
+    }
+    Extractor::from_context(ctx)?.extract_all(&layout.version_dir, &extractions)?;
+
+    run_installer(ctx, &handle, &layout, &extractions, options, transaction).await?;
+
+    if ctx.symlinks_enabled() {
+        link_current(&layout, transaction)?;
+    }
+
+    let dir = layout.dir(ctx).to_path_buf();
+    let linked = manifest.with_variables(&handle.install_variables("install", arch, &dir));
+
+    create_shims(ctx, &linked, arch, &dir, transaction)?;
+    create_shortcuts(ctx, &linked, arch, &dir, transaction)?;
+
+    link_psmodule(ctx, &linked, &layout, previous.as_ref(), transaction)?;
+    apply_environment(
+        ctx,
+        &linked,
+        &layout,
+        previous.as_ref(),
+        options,
+        transaction,
+    )?;
+
+    persist(&linked, &layout, transaction)?;
+
+    let post_install = InstallConfig {
+        post_install: linked.install_config(arch).post_install,
+        ..Default::default()
+    };
+    let host = InstallerHost::from_config(ctx, &post_install, &layout.version_dir, None)
+        .with_prelude(handle.script_prelude(arch, &dir)?);
+    run_host(host, options).await?;
+
+    std::fs::write(
+        layout.version_dir.join("manifest.json"),
+        manifest.to_scoop_string(None)?,
+    )?;
+    std::fs::write(
+        layout.version_dir.join("install.json"),
+        serde_json::to_string_pretty(&install_info(ctx, reference, arch))?,
+    )?;
+
+    Ok(manifest.clone())
+}
+
+/// Download the package's files into the cache, checking their hashes
+///
+/// Files already in the cache with the expected hash are not downloaded again.
+/// Like Scoop, urls without a hash are downloaded with a warning, and are not checked.
+///
+/// Returns the paths to the cached files, in the order of the manifest's urls
+async fn download(
+    ctx: &impl ScoopContext,
+    manifest: &NamedManifest,
+    options: &InstallOptions,
+) -> Result<Vec<PathBuf>> {
+    let cache_path = ctx.cache_path();
+    let urls = manifest.download_urls(options.arch).unwrap_or_default();
+    let mut handles =
+        cache::Handle::open_manifest(&cache_path, manifest, options.arch)?.into_iter();
+
+    let mut downloads = vec![];
+
+    for url in &urls {
+        let Some(handle) = handles.next() else {
+            warn!(
+                "Skipping hash check for {}, as the manifest has no hash for it",
+                url.full_url()
+            );
+
+            let file_name =
+                cache::CacheFile::new(manifest.name(), &manifest.version, url).filename();
+            let path = cache_path.join(&file_name);
+
+            // A cached file can't be checked without a hash, so it is always downloaded again
+            if path.exists() {
+                std::fs::remove_file(&path)?;
+            }
+
+            cache::Handle::unverified(&cache_path, file_name, url.url.clone())?
+                .begin_download::<AsyncClient>(options.progress.as_ref())
+                .await?
+                .download()
+                .await?;
+
+            downloads.push(path);
+            continue;
+        };
+
+        let path = cache_path.join(&handle.file_name);
+
+        if handle.is_cached()? {
+            debug!("Using cached {}", path.display());
+            downloads.push(path);
+            continue;
+        }
+
+        // The cached file is stale or corrupt
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+
+        let result = handle
+            .begin_download::<AsyncClient>(options.progress.as_ref())
+            .await?
+            .download()
+            .await?;
+
+        if !result
+            .computed_hash
+            .no_prefix()
+            .eq_ignore_ascii_case(result.actual_hash.no_prefix())
+        {
+            // Remove the file, so it is downloaded again next time
+            _ = std::fs::remove_file(&path);
+
+            return Err(Error::HashMismatch {
+                url: url.full_url(),
+                expected: result.actual_hash,
+                actual: result.computed_hash,
+            });
+        }
+
+        downloads.push(path);
+    }
+
+    Ok(downloads)
+}
+
+/// Run the `pre_install` script and the installer, running the uninstaller on rollback
+async fn run_installer<C: ScoopContext>(
+    ctx: &C,
+    handle: &PackageHandle<'_, C>,
+    layout: &Layout,
+    extractions: &[extract::Extraction],
+    options: &InstallOptions,
+    transaction: &mut Transaction,
+) -> Result<()> {
+    // Like Scoop, `$dir` is the version directory until `current` is linked
+    let installing = handle
+        .remote_manifest()
+        .with_variables(&handle.install_variables("install", layout.arch, &layout.version_dir));
+    let prelude = handle.script_prelude(layout.arch, &layout.version_dir)?;
+    let mut config = installing.install_config(layout.arch);
+    config.post_install = None;
+
+    let host = InstallerHost::from_config(
+        ctx,
+        &config,
+        &layout.version_dir,
+        extractions
+            .first()
+            .map(|extraction| extraction.file_name.as_str()),
+    )
+    .with_prelude(prelude.clone());
+    run_host(host, options).await?;
+
+    if config.uninstaller.is_some() {
+        let ctx = ctx.clone();
+        let options = options.clone();
+        let layout = layout.clone();
+
+        transaction.push_async("installer", move || {
+            async move {
+                let host = installing
+                    .uninstaller_host(&ctx, layout.arch, &layout.version_dir)
+                    .with_prelude(prelude);
+                let host = match &options.engine {
+                    Some(engine) => host.with_engine(engine.clone()),
+                    None => host,
+                };
+
+                host.with_options(options.script_options.clone())
+                    .run()
+                    .await?;
+
+                Ok(())
+            }
+            .boxed()
+        });
+    }
+
+    Ok(())
+}
+
+async fn run_host<C: ScoopContext>(
+    host: InstallerHost<'_, C>,
+    options: &InstallOptions,
+) -> Result<()> {
+    let host = match &options.engine {
+        Some(engine) => host.with_engine(engine.clone()),
+        None => host,
+    };
+
+    host.with_options(options.script_options.clone())
+        .run()
+        .await?;
+
+    Ok(())
+}
+
+/// Link `current` to the version directory, restoring the previous link on rollback
+fn link_current(layout: &Layout, transaction: &mut Transaction) -> Result<()> {
+    let current = layout.current.clone();
+    let previous_target = std::fs::read_link(&current).ok();
+
+    transaction.push("current", move || {
+        if current.symlink_metadata().is_ok() {
+            remove_link(&current)?;
+        }
+        if let Some(target) = previous_target {
+            link(&target, &current)?;
+        }
+        Ok(())
+    });
+
+    if layout.current.symlink_metadata().is_ok() {
+        remove_link(&layout.current)?;
+    }
+    link(&layout.version_dir, &layout.current)?;
+
+    Ok(())
+}
+
+fn create_shims(
+    ctx: &impl ScoopContext<Config = config::Scoop>,
+    manifest: &Manifest,
+    arch: Architecture,
+    dir: &Path,
+    transaction: &mut Transaction,
+) -> Result<()> {
+    for entry in manifest.bin_entries(arch) {
+        let handle = ShimHandle::from_bin(ctx, &entry);
+        let previous = handle
+            .files()
+            .filter(|path| path.exists())
+            .map(|path| std::fs::read(path).map(|contents| (path.to_path_buf(), contents)))
+            .collect::<std::io::Result<Vec<_>>>()?;
+
+        {
+            let handle = handle.clone();
+
+            transaction.push("shim", move || {
+                handle.delete_all()?;
+                for (path, contents) in previous {
+                    std::fs::write(path, contents)?;
+                }
+                Ok(())
+            });
+        }
+
+        handle.write_shim(&entry, dir)?;
+    }
+
+    Ok(())
+}
+
+fn create_shortcuts(
+    ctx: &impl ScoopContext,
+    manifest: &Manifest,
+    arch: Architecture,
+    dir: &Path,
+    transaction: &mut Transaction,
+) -> Result<()> {
+    let shortcuts = ShortcutManager::new(ctx);
+
+    for shortcut in manifest.shortcut_entries(arch) {
+        let path = shortcuts.link_path(&shortcut)?;
+        let previous = path.exists().then(|| std::fs::read(&path)).transpose()?;
+
+        {
+            let shortcuts = shortcuts.clone();
+            let shortcut = shortcut.clone();
+
+            transaction.push("shortcut", move || {
+                match previous {
+                    Some(contents) => std::fs::write(path, contents)?,
+                    None => shortcuts.remove(&shortcut)?,
+                }
+                Ok(())
+            });
+        }
+
+        shortcuts.create(&shortcut, dir)?;
+    }
+
+    Ok(())
+}
+
+/// Link the package's `PowerShell` module, linking the previous version's module again on rollback
+fn link_psmodule(
+    ctx: &impl ScoopContext,
+    manifest: &Manifest,
+    layout: &Layout,
+    previous: Option<&Previous>,
+    transaction: &mut Transaction,
+) -> Result<()> {
+    let dir = layout.dir(ctx);
+    let named = NamedManifest::new(&layout.name, manifest.clone());
+
+    if let Some(module_path) = named.install_psmodule(ctx, dir)? {
+        let previous_target = previous
+            .filter(|previous| previous.manifest.psmodule.is_some())
+            .map(|previous| previous.layout.dir(ctx).to_path_buf());
+
+        transaction.push("PowerShell module", move || {
+            remove_link(&module_path)?;
+            if let Some(target) = previous_target {
+                link(&target, &module_path)?;
+            }
+            Ok(())
+        });
+    }
+
+    Ok(())
+}
+
+/// Apply the package's environment changes, in place of the previous version's
+///
+/// On rollback, the changes are reverted and the previous version's changes are applied again
+fn apply_environment(
+    ctx: &impl ScoopContext<Config = config::Scoop>,
+    manifest: &Manifest,
+    layout: &Layout,
+    previous: Option<&Previous>,
+    options: &InstallOptions,
+    transaction: &mut Transaction,
+) -> Result<()> {
+    let Some(environment) = options.environment(ctx) else {
+        return Ok(());
+    };
+
+    let previous = previous.map(|previous| {
+        let dir = previous.layout.dir(ctx).to_path_buf();
+        let manifest = previous
+            .manifest
+            .with_variables(&previous.handle(ctx).install_variables(
+                "install",
+                previous.layout.arch,
+                &dir,
+            ));
+
+        (manifest, previous.layout.arch, dir)
+    });
+
+    {
+        let environment = environment.clone();
+        let name = layout.name.clone();
+
+        transaction.push("environment", move || {
+            environment.revert(&name)?;
+            if let Some((manifest, arch, dir)) = previous {
+                environment.apply(&name, &manifest, arch, dir)?;
+            }
+            Ok(())
+        });
+    }
+
+    environment.revert(&layout.name)?;
+    environment.apply(&layout.name, manifest, layout.arch, layout.dir(ctx))?;
+
+    Ok(())
+}
+
+/// Persist the package's data, removing any data first persisted by this install on rollback
+fn persist(manifest: &Manifest, layout: &Layout, transaction: &mut Transaction) -> Result<()> {
+    let entries = manifest.persist_entries();
+    if entries.is_empty() {
+        return Ok(());
+    }
+
+    let created_persist_dir = !layout.persist_dir.exists();
+    let created_targets = entries
+        .iter()
+        .map(|entry| entry.target_path(&layout.persist_dir))
+        .filter(|target| !target.exists())
+        .collect::<Vec<_>>();
+
+    {
+        let manifest = manifest.clone();
+        let layout = layout.clone();
+
+        transaction.push("persisted data", move || {
+            manifest.unpersist_data(
+                &layout.version_dir,
+                &layout.persist_dir,
+                created_persist_dir,
+            )?;
+
+            for target in created_targets {
+                if target.is_dir() {
+                    std::fs::remove_dir_all(&target)?;
+                } else if target.exists() {
+                    std::fs::remove_file(&target)?;
+                }
+            }
+            Ok(())
+        });
+    }
+
+    manifest.persist_data(&layout.version_dir, &layout.persist_dir)?;
+
+    Ok(())
+}
+
+/// Get the install info for a package, recording where it was installed from
+fn install_info(
+    ctx: &impl ScoopContext,
+    reference: &package::Reference,
+    arch: Architecture,
+) -> InstallManifest {
+    let (bucket, url) = match &reference.manifest {
+        manifest::Reference::BucketNamePair { bucket, .. } => (Some(bucket.clone()), None),
+        manifest::Reference::Name(_) => (
+            reference
+                .first(ctx)
+                .map(|manifest| manifest.bucket().to_string()),
+            None,
+        ),
+        manifest::Reference::File(path) => (None, Some(path.display().to_string())),
+        manifest::Reference::Url(url) => (None, Some(url.to_string())),
+    };
+
+    InstallManifest {
+        bucket,
+        hold: None,
+        url,
+        architecture: Some(arch),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use sha2::{Digest, Sha256};
+
+    use super::*;
+    use crate::{
+        contexts::testing::TestContext, handles::environment::file::FileBackend, hash::encode_hex,
+        packages::downloading::DownloadUrl, requests::testing::serve, scripts::testing::TestEngine,
+    };
+
+    const APP_V1: &str = "Write-Host 'app 1.0.0'";
+    const APP_V2: &str = "Write-Host 'app 2.0.0'";
+    const LIB: &str = "lib";
+    const ROUTES: &[(&str, &str)] = &[
+        ("/1.0.0/app.ps1", APP_V1),
+        ("/2.0.0/app.ps1", APP_V2),
+        ("/lib.txt", LIB),
+    ];
+
+    fn sha256(body: &str) -> String {
+        encode_hex(&Sha256::digest(body.as_bytes()))
+    }
+
+    fn setup(name: &str) -> (TestContext, String, TestEngine, InstallOptions) {
+        let ctx = TestContext::new(name);
+        let base_url = serve(ROUTES);
+
+        ctx.write_bucket_manifest(
+            "main",
+            "lib",
+            &json!({
+                "version": "1.0.0",
+                "url": format!("{base_url}/lib.txt"),
+                "hash": sha256(LIB),
+            }),
+        );
+        ctx.write_bucket_manifest(
+            "main",
+            "app",
+            &json!({
+                "version": "1.0.0",
+                "url": format!("{base_url}/1.0.0/app.ps1"),
+                "hash": sha256(APP_V1),
+                "depends": "main/lib",
+                "bin": "app.ps1",
+                "env_set": { "APP_HOME": "$dir" },
+                "persist": "data",
+                "pre_install": "Write-Host 'pre install'",
+                "post_install": "Write-Host 'post install'",
+            }),
+        );
+
+        let engine = TestEngine::new().fail_on("\"boom\"");
+        let options = InstallOptions::new()
+            .with_arch(Architecture::X64)
+            .with_engine(Arc::new(engine.clone()))
+            .with_environment(FileBackend::new(ctx.path().join("env.json")));
+
+        (ctx, base_url, engine, options)
+    }
+
+    fn reference(name: &str) -> package::Reference {
+        format!("main/{name}").parse().unwrap()
+    }
+
+    fn env(ctx: &TestContext, name: &str) -> Option<String> {
+        FileBackend::new(ctx.path().join("env.json"))
+            .get(name)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_install() {
+        let (ctx, _, engine, options) = setup("install");
+
+        let installed = install(&ctx, reference("app"), &options).await.unwrap();
+        assert_eq!(
+            installed
+                .iter()
+                .map(NamedManifest::name)
+                .collect::<Vec<_>>(),
+            ["lib", "app"]
+        );
+
+        let app_dir = ctx.apps_path().join("app");
+        let version_dir = app_dir.join("1.0.0");
+        let current = app_dir.join("current");
+
+        assert_eq!(
+            std::fs::read_to_string(version_dir.join("app.ps1")).unwrap(),
+            APP_V1
+        );
+        assert_eq!(std::fs::read_link(&current).unwrap(), version_dir);
+        assert!(ctx
+            .apps_path()
+            .join("lib")
+            .join("1.0.0")
+            .join("lib.txt")
+            .exists());
+
+        for shim in ["app.cmd", "app.ps1", "app"] {
+            assert!(ctx.shims_path().join(shim).exists(), "{shim}");
+        }
+        assert!(!ctx.shims_path().join("app.shim").exists());
+        assert!(std::fs::read_to_string(ctx.shims_path().join("app.cmd"))
+            .unwrap()
+            .contains(&current.join("app.ps1").display().to_string()));
+        assert_eq!(
+            env(&ctx, "APP_HOME").as_deref(),
+            Some(current.display().to_string().as_str())
+        );
+        assert!(ctx.persist_path().join("app").join("data").is_dir());
+        assert!(version_dir.join("data").is_symlink());
+
+        let install_manifest =
+            InstallManifest::from_path(version_dir.join("install.json")).unwrap();
+        assert_eq!(install_manifest.bucket.as_deref(), Some("main"));
+        assert_eq!(install_manifest.architecture, Some(Architecture::X64));
+        assert_eq!(
+            Manifest::from_path(version_dir.join("manifest.json"))
+                .unwrap()
+                .version
+                .as_str(),
+            "1.0.0"
+        );
+
+        let runs = engine.runs();
+        assert_eq!(runs.len(), 2);
+        assert!(runs[0].script.ends_with("Write-Host 'pre install'"));
+        assert!(runs[0]
+            .script
+            .contains(&format!("$dir = '{}'", version_dir.display())));
+        assert!(runs[1].script.ends_with("Write-Host 'post install'"));
+        assert!(runs[1]
+            .script
+            .contains(&format!("$dir = '{}'", current.display())));
+
+        let result = install(&ctx, reference("app"), &options).await;
+        assert!(matches!(result, Err(Error::AlreadyInstalled { .. })));
+
+        ctx.cleanup();
+    }
+
+    #[tokio::test]
+    async fn test_install_rollback() {
+        let (ctx, base_url, engine, options) = setup("install-rollback");
+
+        install(&ctx, reference("app"), &options).await.unwrap();
+
+        ctx.write_bucket_manifest(
+            "main",
+            "app",
+            &json!({
+                "version": "2.0.0",
+                "url": format!("{base_url}/2.0.0/app.ps1"),
+                "hash": sha256(APP_V2),
+                "bin": ["app.ps1", ["app.ps1", "app2"]],
+                "shortcuts": [["app.ps1", "App"]],
+                "env_set": { "APP_HOME": "$dir", "APP_VERSION": "$version" },
+                "persist": ["data", "logs"],
+                "uninstaller": { "script": "Write-Host 'uninstall'" },
+                "post_install": "Write-Error \"boom\"",
+            }),
+        );
+
+        let result = install(&ctx, reference("app"), &options).await;
+        assert!(matches!(result, Err(Error::Installer(_))));
+
+        let app_dir = ctx.apps_path().join("app");
+        assert!(!app_dir.join("2.0.0").exists());
+        assert_eq!(
+            std::fs::read_link(app_dir.join("current")).unwrap(),
+            app_dir.join("1.0.0")
+        );
+        assert_eq!(
+            std::fs::read_to_string(app_dir.join("1.0.0").join("app.ps1")).unwrap(),
+            APP_V1
+        );
+
+        for shim in ["app.cmd", "app.ps1", "app"] {
+            assert!(ctx.shims_path().join(shim).exists(), "{shim}");
+        }
+        assert!(!ctx.shims_path().join("app.shim").exists());
+        for shim in ["app2.shim", "app2.cmd", "app2.ps1", "app2"] {
+            assert!(!ctx.shims_path().join(shim).exists(), "{shim}");
+        }
+        assert!(!ctx.shortcuts_path().join("App.lnk").exists());
+        assert_eq!(
+            env(&ctx, "APP_HOME").as_deref(),
+            Some(app_dir.join("current").display().to_string().as_str())
+        );
+        assert_eq!(env(&ctx, "APP_VERSION"), None);
+        assert!(ctx.persist_path().join("app").join("data").is_dir());
+        assert!(!ctx.persist_path().join("app").join("logs").exists());
+
+        // The new version's uninstaller is run to undo its installer
+        let runs = engine.runs();
+        assert!(runs
+            .last()
+            .unwrap()
+            .script
+            .ends_with("Write-Host 'uninstall'"));
+
+        ctx.cleanup();
+    }
+
+    #[tokio::test]
+    async fn test_install_rollback_dependencies() {
+        let (ctx, base_url, _, options) = setup("install-rollback-dependencies");
+
+        ctx.write_bucket_manifest(
+            "main",
+            "app",
+            &json!({
+                "version": "1.0.0",
+                "url": format!("{base_url}/1.0.0/app.ps1"),
+                "hash": sha256(APP_V2),
+                "depends": "main/lib",
+            }),
+        );
+
+        let result = install(&ctx, reference("app"), &options).await;
+        assert!(matches!(result, Err(Error::HashMismatch { .. })));
+
+        // The dependency installed before the failure is rolled back as well
+        assert!(!ctx.apps_path().join("lib").exists());
+        assert!(!ctx.apps_path().join("app").exists());
+
+        // The mismatched download is removed from the cache
+        let cached = ctx
+            .cache_path()
+            .read_dir()
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(cached.len(), 1);
+        assert!(cached[0].starts_with("lib#"));
+
+        ctx.cleanup();
+    }
+
+    #[tokio::test]
+    async fn test_install_without_hash() {
+        let (ctx, base_url, _, options) = setup("install-without-hash");
+
+        ctx.write_bucket_manifest(
+            "main",
+            "nightly",
+            &json!({ "version": "nightly", "url": format!("{base_url}/lib.txt") }),
+        );
+
+        // A stale cached file is downloaded again, as it can't be checked
+        let url = DownloadUrl::from_string(format!("{base_url}/lib.txt"));
+        let version = crate::version::Version::new("nightly");
+        let cached = ctx
+            .cache_path()
+            .join(cache::CacheFile::new("nightly", &version, &url).filename());
+        std::fs::create_dir_all(ctx.cache_path()).unwrap();
+        std::fs::write(&cached, "stale").unwrap();
+
+        install(&ctx, reference("nightly"), &options).await.unwrap();
+
+        let current = ctx.apps_path().join("nightly").join("current");
+        assert_eq!(
+            std::fs::read_to_string(current.join("lib.txt")).unwrap(),
+            LIB
+        );
+        assert_eq!(std::fs::read_to_string(&cached).unwrap(), LIB);
+
+        ctx.cleanup();
+    }
+
+    #[tokio::test]
+    async fn test_install_from_cache() {
+        let (ctx, base_url, _, options) = setup("install-from-cache");
+
+        // The url isn't served, so the install only succeeds using the cached file
+        let url = format!("{base_url}/cached.txt");
+        ctx.write_bucket_manifest(
+            "main",
+            "cached",
+            &json!({ "version": "1.0.0", "url": url, "hash": sha256("cached") }),
+        );
+
+        let version = crate::version::Version::new("1.0.0");
+        let cached = ctx.cache_path().join(
+            cache::CacheFile::new("cached", &version, &DownloadUrl::from_string(url)).filename(),
+        );
+        std::fs::create_dir_all(ctx.cache_path()).unwrap();
+        std::fs::write(cached, "cached").unwrap();
+
+        install(&ctx, reference("cached"), &options).await.unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(
+                ctx.apps_path()
+                    .join("cached")
+                    .join("current")
+                    .join("cached.txt")
+            )
+            .unwrap(),
+            "cached"
+        );
+
+        ctx.cleanup();
+    }
+}
@@ -1,12 +1,31 @@
 //! Installer helpers
 
-use std::{future::IntoFuture, process::Output};
+use std::{
+    future::IntoFuture,
+    path::{Component, Path, PathBuf},
+    process::{ExitStatus, Output},
+    sync::Arc,
+};
 
 use futures::FutureExt;
+use strum::Display;
 
-use crate::{contexts::ScoopContext, packages::manifest::Installer, scripts};
+use crate::{
+    contexts::ScoopContext,
+    packages::manifest::{Installer, Uninstaller},
+    scripts::{
+        self,
+        engine::{self, PowerShell, RunOptions, ScriptEngine},
+        prelude::Prelude,
+        PowershellScript, ScriptRunner,
+    },
+};
 
-use super::models::manifest::{InstallerRunner, TOrArrayOfTs};
+use super::{
+    dependencies::{self, Dependents},
+    models::manifest::{InstallConfig, InstallerRunner, StringArray, TOrArrayOfTs},
+    Manifest,
+};
 
 #[derive(Debug, thiserror::Error)]
 #[allow(missing_docs)]
@@ -16,29 +35,250 @@ pub enum Error {
     IO(#[from] std::io::Error),
     #[error("Installer script error: {0}")]
     Scripts(#[from] scripts::Error),
+    #[error("{stage} exited with {status}")]
+    Exit {
+        stage: Stage,
+        status: ExitStatus,
+        output: Output,
+    },
+    #[error("{} is not in the version directory", .0.display())]
+    OutsideVersionDir(PathBuf),
+    #[error("Refusing to uninstall: {0}")]
+    Dependencies(#[from] dependencies::Error),
+    #[error("Reading installed apps: {0}")]
+    Packages(#[from] Box<super::Error>),
 }
 
 /// Installer result type
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+#[strum(serialize_all = "snake_case")]
+/// A stage of the install or uninstall lifecycle
+pub enum Stage {
+    /// The manifest's `pre_install` script
+    PreInstall,
+    /// The manifest's installer file or script
+    Installer,
+    /// The manifest's `post_install` script
+    PostInstall,
+    /// The manifest's `pre_uninstall` script
+    PreUninstall,
+    /// The manifest's uninstaller file or script
+    Uninstaller,
+    /// The manifest's `post_uninstall` script
+    PostUninstall,
+}
+
+#[derive(Debug, Clone)]
+/// The output of a single lifecycle stage
+pub struct StageOutput {
+    /// The stage that was run
+    pub stage: Stage,
+    /// The stage's output
+    pub output: Output,
+}
+
+#[derive(Debug, Clone, Default)]
+/// The output of an install or uninstall lifecycle
+pub struct LifecycleOutput {
+    /// The output of each stage that was run, in the order they were run
+    ///
+    /// Stages without a script or file are skipped
+    pub stages: Vec<StageOutput>,
+    /// The installer file that was removed after running, if any
+    pub removed: Option<PathBuf>,
+}
+
+impl LifecycleOutput {
+    #[must_use]
+    /// Get the output of a stage, if it was run
+    pub fn get(&self, stage: Stage) -> Option<&Output> {
+        self.stages
+            .iter()
+            .find(|output| output.stage == stage)
+            .map(|output| &output.output)
+    }
+
+    fn push(&mut self, stage: Stage, output: Option<Output>) {
+        if let Some(output) = output {
+            self.stages.push(StageOutput { stage, output });
+        }
+    }
+}
+
+/// Runs the scripts and files of an install or uninstall lifecycle
+#[derive(Debug, Clone, Default)]
+struct Lifecycle {
+    runner: Option<InstallerRunner>,
+    args: Vec<String>,
+    version_dir: Option<PathBuf>,
+    pre: Option<PowershellScript>,
+    post: Option<PowershellScript>,
+    prelude: Option<Prelude>,
+    engine: Option<Arc<dyn ScriptEngine>>,
+    options: RunOptions,
+}
+
+impl Lifecycle {
+    /// Resolve a file against the version directory, making sure it stays inside it
+    fn resolve(&self, file: &str) -> Result<PathBuf> {
+        let Some(version_dir) = &self.version_dir else {
+            return Ok(PathBuf::from(file));
+        };
+
+        let relative = PathBuf::from(file.replace('\\', "/"));
+        let mut depth = 0usize;
+        for component in relative.components() {
+            match component {
+                Component::Normal(_) => depth += 1,
+                Component::CurDir => {}
+                Component::ParentDir if depth > 0 => depth -= 1,
+                _ => return Err(Error::OutsideVersionDir(version_dir.join(file))),
+            }
+        }
+
+        Ok(version_dir.join(relative))
+    }
+
+    /// Get a runner for a script file, using the configured engine, or finding `PowerShell`
+    fn script_runner(&self, path: &Path) -> Result<ScriptRunner> {
+        let engine = match &self.engine {
+            Some(engine) => engine.clone(),
+            None => Arc::new(PowerShell::find()?),
+        };
+
+        Ok(ScriptRunner::with_engine(path, engine).with_options(self.options.clone()))
+    }
+
+    async fn run_script(
+        &self,
+        ctx: &impl ScoopContext,
+        script: Option<&PowershellScript>,
+    ) -> Result<Option<Output>> {
+        let Some(script) = script else {
+            return Ok(None);
+        };
+
+        let path = match &self.prelude {
+            Some(prelude) => prelude.apply(script).write_to(ctx.scripts_path())?,
+            None => script.write_to(ctx.scripts_path())?,
+        };
+
+        Ok(Some(self.script_runner(&path)?.run().await?))
+    }
+
+    /// Run the file or script, returning the path to the file if one was run
+    async fn run_main(
+        &self,
+        ctx: &impl ScoopContext,
+        stage: Stage,
+    ) -> Result<Option<(Output, Option<PathBuf>)>> {
+        match &self.runner {
+            None => Ok(None),
+            Some(InstallerRunner::Script(script)) => {
+                let output = self.run_script(ctx, Some(script)).await?;
+                Ok(output.map(|output| (output, None)))
+            }
+            Some(InstallerRunner::File(file)) => {
+                let path = self.resolve(file)?;
+
+                // Like Scoop, a vendor uninstaller that has already removed itself is skipped,
+                // so that the rest of the uninstall (and install rollbacks) can continue
+                if stage == Stage::Uninstaller && self.version_dir.is_some() && !path.exists() {
+                    warn!("Uninstaller {} is missing, skipping it", path.display());
+                    return Ok(None);
+                }
+
+                let is_script = path
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("ps1"));
+
+                let output = if is_script {
+                    self.script_runner(&path)?.run_with_args(&self.args).await?
+                } else {
+                    let mut command = tokio::process::Command::new(&path);
+                    command.args(&self.args);
+
+                    let mut options = self.options.clone();
+                    if options.cwd.is_none() {
+                        options.cwd.clone_from(&self.version_dir);
+                    }
+
+                    let output = engine::run_command(command, &options).await?;
+                    if !output.status.success() {
+                        return Err(Error::Exit {
+                            stage,
+                            status: output.status,
+                            output,
+                        });
+                    }
+
+                    output
+                };
+
+                Ok(Some((output, Some(path))))
+            }
+        }
+    }
+
+    async fn run(
+        &self,
+        ctx: &impl ScoopContext,
+        stages: [Stage; 3],
+        keep: bool,
+    ) -> Result<LifecycleOutput> {
+        let [pre, main, post] = stages;
+        let mut output = LifecycleOutput::default();
+
+        output.push(pre, self.run_script(ctx, self.pre.as_ref()).await?);
+
+        if let Some((main_output, file)) = self.run_main(ctx, main).await? {
+            output.push(main, Some(main_output));
+
+            // Only files in the version directory are removed, so files outside of it are never touched
+            if let Some(file) = file.filter(|_| !keep && self.version_dir.is_some()) {
+                if file.exists() {
+                    std::fs::remove_file(&file)?;
+                    output.removed = Some(file);
+                }
+            }
+        }
+
+        output.push(post, self.run_script(ctx, self.post.as_ref()).await?);
+
+        Ok(output)
+    }
+}
+
+#[derive(Debug, Clone)]
 #[must_use]
 /// An installer host
 ///
-/// This is used to run the installers
+/// This is used to run the installers, along with the manifest's `pre_install` and `post_install` scripts
 pub struct InstallerHost<'ctx, C: ScoopContext> {
     ctx: &'ctx C,
     installer: Installer,
-    runner: InstallerRunner,
+    lifecycle: Lifecycle,
 }
 
 impl<'ctx, C: ScoopContext> InstallerHost<'ctx, C> {
     /// Create a new installer host
     pub fn new(ctx: &'ctx C, installer: Installer, runner: InstallerRunner) -> Self {
+        Self::with_runner(ctx, installer, Some(runner))
+    }
+
+    fn with_runner(ctx: &'ctx C, installer: Installer, runner: Option<InstallerRunner>) -> Self {
+        let args = installer.args.clone().map(TOrArrayOfTs::to_vec);
+
         Self {
             ctx,
             installer,
-            runner,
+            lifecycle: Lifecycle {
+                runner,
+                args: args.unwrap_or_default(),
+                ..Lifecycle::default()
+            },
         }
     }
 
@@ -49,35 +289,297 @@ impl<'ctx, C: ScoopContext> InstallerHost<'ctx, C> {
         Some(Self::new(ctx, installer, runner))
     }
 
-    /// Run the installer
+    /// Create an installer host for an install config, including its `pre_install` and `post_install` scripts
     ///
-    /// # Errors
-    /// - If the installer could not be run
-    pub async fn run(self) -> Result<Output> {
-        let runner = self.runner;
-        let args = self.installer.args.clone().map(TOrArrayOfTs::to_vec);
+    /// The installer file is resolved against the version directory.
+    /// If the installer has arguments but no file, `default_file` (i.e the downloaded file's name) is run instead, like Scoop.
+    pub fn from_config(
+        ctx: &'ctx C,
+        config: &InstallConfig,
+        version_dir: impl Into<PathBuf>,
+        default_file: Option<&str>,
+    ) -> Self {
+        let installer = config.installer.clone().unwrap_or(Installer {
+            comment: None,
+            args: None,
+            file: None,
+            keep: None,
+            script: None,
+        });
+        let runner = installer.get_runner().or_else(|| {
+            installer
+                .args
+                .as_ref()
+                .and(default_file)
+                .map(|file| InstallerRunner::File(file.to_string()))
+        });
 
-        let output = match runner {
-            InstallerRunner::File(file) => {
-                let mut command = std::process::Command::new(file);
+        let mut host = Self::with_runner(ctx, installer, runner).with_version_dir(version_dir);
+        host.lifecycle.pre.clone_from(&config.pre_install);
+        host.lifecycle.post.clone_from(&config.post_install);
 
-                if let Some(ref args) = args {
-                    command.args(args)
-                } else {
-                    &mut command
-                }
-                .spawn()?
-                .wait_with_output()?
-            }
-            InstallerRunner::Script(script) => script.save(self.ctx)?.run().await?,
-        };
+        host
+    }
 
-        Ok(output)
+    /// Resolve the installer file against the given version directory
+    ///
+    /// Files outside the version directory are rejected, and the file is run in the version directory
+    pub fn with_version_dir(mut self, version_dir: impl Into<PathBuf>) -> Self {
+        self.lifecycle.version_dir = Some(version_dir.into());
+        self
+    }
+
+    /// Run the given script before the installer
+    pub fn with_pre_install(mut self, script: PowershellScript) -> Self {
+        self.lifecycle.pre = Some(script);
+        self
+    }
+
+    /// Run the given script after the installer
+    pub fn with_post_install(mut self, script: PowershellScript) -> Self {
+        self.lifecycle.post = Some(script);
+        self
+    }
+
+    /// Run scripts with the given engine, rather than `PowerShell`
+    pub fn with_engine(mut self, engine: Arc<dyn ScriptEngine>) -> Self {
+        self.lifecycle.engine = Some(engine);
+        self
+    }
+
+    /// Run the installer and scripts with the given options (i.e timeout, environment and output streaming)
+    ///
+    /// The installer file runs in the version directory, unless a working directory is given
+    pub fn with_options(mut self, options: RunOptions) -> Self {
+        self.lifecycle.options = options;
+        self
+    }
+
+    /// Define Scoop's variables and helper functions before the installer script and lifecycle scripts
+    ///
+    /// See [`PackageHandle::script_prelude`](crate::handles::packages::PackageHandle::script_prelude)
+    pub fn with_prelude(mut self, prelude: Prelude) -> Self {
+        self.lifecycle.prelude = Some(prelude);
+        self
+    }
+
+    #[cfg(feature = "manifest-hashes")]
+    /// Substitute the install-time variables into the installer arguments
+    pub fn with_variables(mut self, variables: &super::variables::InstallVariables) -> Self {
+        self.lifecycle
+            .args
+            .iter_mut()
+            .for_each(|arg| variables.substitute(arg));
+        self
+    }
+
+    #[must_use]
+    /// Get the installer
+    pub fn installer(&self) -> &Installer {
+        &self.installer
+    }
+
+    #[must_use]
+    /// Get the installer arguments, with any variables substituted
+    pub fn args(&self) -> &[String] {
+        &self.lifecycle.args
+    }
+
+    #[must_use]
+    /// Check if the installer file is kept after running
+    pub fn keep(&self) -> bool {
+        self.installer.keep.unwrap_or(false)
+    }
+
+    /// Run the `pre_install` script, the installer and the `post_install` script, in that order
+    ///
+    /// The installer file is removed after running, unless the installer sets `keep`,
+    /// or no version directory was given.
+    ///
+    /// # Errors
+    /// - If the installer file is outside the version directory
+    /// - If any script or the installer could not be run, or exited with a non-zero exit code
+    /// - If the installer file could not be removed
+    pub async fn run(self) -> Result<LifecycleOutput> {
+        self.lifecycle
+            .run(
+                self.ctx,
+                [Stage::PreInstall, Stage::Installer, Stage::PostInstall],
+                self.keep(),
+            )
+            .await
     }
 }
 
 impl<'ctx, C: ScoopContext> IntoFuture for InstallerHost<'ctx, C> {
-    type Output = Result<Output>;
+    type Output = Result<LifecycleOutput>;
+
+    type IntoFuture =
+        std::pin::Pin<Box<dyn std::future::Future<Output = Self::Output> + Send + 'ctx>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        self.run().boxed()
+    }
+}
+
+#[derive(Debug, Clone)]
+#[must_use]
+/// An uninstaller host
+///
+/// This is used to run the uninstallers, along with the manifest's `pre_uninstall` and `post_uninstall` scripts.
+/// Unlike installers, uninstaller files are never removed.
+pub struct UninstallerHost<'ctx, C: ScoopContext> {
+    ctx: &'ctx C,
+    uninstaller: Option<Uninstaller>,
+    lifecycle: Lifecycle,
+    dependents_of: Option<String>,
+}
+
+impl<'ctx, C: ScoopContext> UninstallerHost<'ctx, C> {
+    /// Create a new uninstaller host
+    pub fn new(ctx: &'ctx C, uninstaller: Uninstaller) -> Self {
+        let args = uninstaller.args.clone().map(TOrArrayOfTs::to_vec);
+
+        Self {
+            ctx,
+            lifecycle: Lifecycle {
+                runner: uninstaller.get_runner(),
+                args: args.unwrap_or_default(),
+                ..Lifecycle::default()
+            },
+            uninstaller: Some(uninstaller),
+            dependents_of: None,
+        }
+    }
+
+    /// Create an uninstaller host for an install config, including its `pre_uninstall` and `post_uninstall` scripts
+    ///
+    /// The uninstaller file is resolved against the version directory
+    pub fn from_config(
+        ctx: &'ctx C,
+        config: &InstallConfig,
+        version_dir: impl Into<PathBuf>,
+    ) -> Self {
+        let mut host = match config.uninstaller.clone() {
+            Some(uninstaller) => Self::new(ctx, uninstaller),
+            None => Self {
+                ctx,
+                uninstaller: None,
+                lifecycle: Lifecycle::default(),
+                dependents_of: None,
+            },
+        }
+        .with_version_dir(version_dir);
+
+        host.lifecycle.pre.clone_from(&config.pre_uninstall);
+        host.lifecycle.post.clone_from(&config.post_uninstall);
+
+        host
+    }
+
+    /// Resolve the uninstaller file against the given version directory
+    ///
+    /// Files outside the version directory are rejected, and the file is run in the version directory
+    pub fn with_version_dir(mut self, version_dir: impl Into<PathBuf>) -> Self {
+        self.lifecycle.version_dir = Some(version_dir.into());
+        self
+    }
+
+    /// Run the given script before the uninstaller
+    pub fn with_pre_uninstall(mut self, script: PowershellScript) -> Self {
+        self.lifecycle.pre = Some(script);
+        self
+    }
+
+    /// Run the given script after the uninstaller
+    pub fn with_post_uninstall(mut self, script: PowershellScript) -> Self {
+        self.lifecycle.post = Some(script);
+        self
+    }
+
+    /// Run scripts with the given engine, rather than `PowerShell`
+    pub fn with_engine(mut self, engine: Arc<dyn ScriptEngine>) -> Self {
+        self.lifecycle.engine = Some(engine);
+        self
+    }
+
+    /// Run the uninstaller and scripts with the given options (i.e timeout, environment and output streaming)
+    ///
+    /// The uninstaller file runs in the version directory, unless a working directory is given
+    pub fn with_options(mut self, options: RunOptions) -> Self {
+        self.lifecycle.options = options;
+        self
+    }
+
+    /// Define Scoop's variables and helper functions before the uninstaller script and lifecycle scripts
+    ///
+    /// See [`PackageHandle::script_prelude`](crate::handles::packages::PackageHandle::script_prelude)
+    pub fn with_prelude(mut self, prelude: Prelude) -> Self {
+        self.lifecycle.prelude = Some(prelude);
+        self
+    }
+
+    /// Refuse to uninstall the given app while any installed app depends on it
+    ///
+    /// The check runs before any script or the uninstaller
+    pub fn with_dependents_check(mut self, name: impl Into<String>) -> Self {
+        self.dependents_of = Some(name.into());
+        self
+    }
+
+    #[cfg(feature = "manifest-hashes")]
+    /// Substitute the install-time variables into the uninstaller arguments
+    pub fn with_variables(mut self, variables: &super::variables::InstallVariables) -> Self {
+        self.lifecycle
+            .args
+            .iter_mut()
+            .for_each(|arg| variables.substitute(arg));
+        self
+    }
+
+    #[must_use]
+    /// Get the uninstaller, if any
+    pub fn uninstaller(&self) -> Option<&Uninstaller> {
+        self.uninstaller.as_ref()
+    }
+
+    #[must_use]
+    /// Get the uninstaller arguments, with any variables substituted
+    pub fn args(&self) -> &[String] {
+        &self.lifecycle.args
+    }
+
+    /// Run the `pre_uninstall` script, the uninstaller and the `post_uninstall` script, in that order
+    ///
+    /// An uninstaller file that is missing from the version directory is skipped with a warning
+    ///
+    /// # Errors
+    /// - If the dependents check is enabled, and any installed app depends on the app
+    /// - If the uninstaller file is outside the version directory
+    /// - If any script or the uninstaller could not be run, or exited with a non-zero exit code
+    pub async fn run(self) -> Result<LifecycleOutput> {
+        if let Some(name) = &self.dependents_of {
+            Dependents::from_installed(self.ctx)
+                .map_err(Box::new)?
+                .ensure_none(name)?;
+        }
+
+        self.lifecycle
+            .run(
+                self.ctx,
+                [
+                    Stage::PreUninstall,
+                    Stage::Uninstaller,
+                    Stage::PostUninstall,
+                ],
+                true,
+            )
+            .await
+    }
+}
+
+impl<'ctx, C: ScoopContext> IntoFuture for UninstallerHost<'ctx, C> {
+    type Output = Result<LifecycleOutput>;
 
     type IntoFuture =
         std::pin::Pin<Box<dyn std::future::Future<Output = Self::Output> + Send + 'ctx>>;
@@ -105,11 +607,59 @@ impl Installer {
     }
 }
 
+impl Uninstaller {
+    #[must_use]
+    /// Get the uninstaller runner
+    pub fn get_runner(&self) -> Option<InstallerRunner> {
+        self.script
+            .clone()
+            .map(|script: StringArray| InstallerRunner::Script(script.into()))
+            .or_else(|| self.file.clone().map(InstallerRunner::File))
+    }
+
+    /// Get the uninstaller host for the uninstaller
+    pub fn host<C: ScoopContext>(self, ctx: &C) -> UninstallerHost<'_, C> {
+        UninstallerHost::new(ctx, self)
+    }
+}
+
+impl Manifest {
+    /// Get the installer host for the given architecture, including the `pre_install` and `post_install` scripts
+    ///
+    /// See [`InstallerHost::from_config`]
+    pub fn installer_host<'ctx, C: ScoopContext>(
+        &self,
+        ctx: &'ctx C,
+        arch: crate::Architecture,
+        version_dir: impl AsRef<Path>,
+        default_file: Option<&str>,
+    ) -> InstallerHost<'ctx, C> {
+        InstallerHost::from_config(
+            ctx,
+            &self.install_config(arch),
+            version_dir.as_ref(),
+            default_file,
+        )
+    }
+
+    /// Get the uninstaller host for the given architecture, including the `pre_uninstall` and `post_uninstall` scripts
+    ///
+    /// See [`UninstallerHost::from_config`]
+    pub fn uninstaller_host<'ctx, C: ScoopContext>(
+        &self,
+        ctx: &'ctx C,
+        arch: crate::Architecture,
+        version_dir: impl AsRef<Path>,
+    ) -> UninstallerHost<'ctx, C> {
+        UninstallerHost::from_config(ctx, &self.install_config(arch), version_dir.as_ref())
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use scripts::PowershellScript;
+    use serde_json::json;
 
-    use crate::contexts::User;
+    use crate::contexts::{testing::TestContext, User};
 
     use super::*;
 
@@ -128,8 +678,251 @@ mod tests {
         let host = installer.host(&ctx).unwrap();
 
         let output = host.await.unwrap();
+        let output = output.get(Stage::Installer).unwrap();
 
         assert_eq!(output.status.code(), Some(0));
         assert_eq!(output.stdout, b"Hello, world!\r\n");
     }
+
+    /// Write an installer that echoes its arguments
+    fn write_installer(version_dir: &Path) -> &'static str {
+        cfg_if::cfg_if! {
+            if #[cfg(windows)] {
+                std::fs::write(version_dir.join("setup.cmd"), "@echo off\r\necho installed %*\r\n").unwrap();
+                "setup.cmd"
+            } else {
+                use std::os::unix::fs::PermissionsExt;
+
+                let path = version_dir.join("setup.sh");
+                std::fs::write(&path, "#!/bin/sh\nprintf '%s\\n' \"installed $*\"\n").unwrap();
+                std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+                "setup.sh"
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_installer_file() {
+        let ctx = TestContext::new("installer-file");
+        let version_dir = ctx.apps_path().join("app").join("1.0.0");
+        std::fs::create_dir_all(&version_dir).unwrap();
+        let file = write_installer(&version_dir);
+
+        let manifest: Manifest = serde_json::from_value(json!({
+            "version": "1.0.0",
+            "installer": { "file": file, "args": ["/D=$dir"] },
+        }))
+        .unwrap();
+
+        let host = manifest.installer_host(&ctx, crate::Architecture::X64, &version_dir, None);
+        #[cfg(feature = "manifest-hashes")]
+        let host = host.with_variables(
+            &super::super::variables::InstallVariables::new(
+                "app",
+                "1.0.0",
+                crate::Architecture::X64,
+            )
+            .with_dir(r"C:\apps\app\current"),
+        );
+
+        let output = host.await.unwrap();
+
+        let stdout = String::from_utf8_lossy(&output.get(Stage::Installer).unwrap().stdout)
+            .trim()
+            .to_string();
+        assert!(stdout.starts_with("installed /D="));
+        #[cfg(feature = "manifest-hashes")]
+        assert_eq!(stdout, r"installed /D=C:\apps\app\current");
+        assert_eq!(output.stages.len(), 1);
+        assert_eq!(output.removed, Some(version_dir.join(file)));
+        assert!(!version_dir.join(file).exists());
+
+        // Kept installers are not removed
+        let file = write_installer(&version_dir);
+        let manifest: Manifest = serde_json::from_value(json!({
+            "version": "1.0.0",
+            "installer": { "file": file, "keep": true },
+            "uninstaller": { "file": file, "args": "--uninstall" },
+        }))
+        .unwrap();
+
+        let output = manifest
+            .installer_host(&ctx, crate::Architecture::X64, &version_dir, None)
+            .await
+            .unwrap();
+        assert_eq!(output.removed, None);
+        assert!(version_dir.join(file).exists());
+
+        let output = manifest
+            .uninstaller_host(&ctx, crate::Architecture::X64, &version_dir)
+            .await
+            .unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&output.get(Stage::Uninstaller).unwrap().stdout).trim(),
+            "installed --uninstall"
+        );
+        assert!(version_dir.join(file).exists());
+
+        ctx.cleanup();
+    }
+
+    #[tokio::test]
+    async fn test_lifecycle_order() {
+        let ctx = TestContext::new("installer-lifecycle");
+        let version_dir = ctx.apps_path().join("app").join("1.0.0");
+        let engine = crate::scripts::testing::TestEngine::new().fail_on("throw");
+
+        let manifest: Manifest = serde_json::from_value(json!({
+            "version": "1.0.0",
+            "pre_install": "Write-Host 'pre install'",
+            "installer": { "script": ["Write-Host 'installer'", "ensure $persist_dir"] },
+            "post_install": "Write-Host 'post install'",
+            "pre_uninstall": "Write-Host 'pre uninstall'",
+            "uninstaller": { "script": "Write-Host 'uninstaller'" },
+            "post_uninstall": "throw 'post uninstall'",
+        }))
+        .unwrap();
+        let prelude = Prelude::new().with_string("dir", version_dir.display().to_string());
+
+        let output = manifest
+            .installer_host(&ctx, crate::Architecture::X64, &version_dir, None)
+            .with_engine(Arc::new(engine.clone()))
+            .with_prelude(prelude.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            output
+                .stages
+                .iter()
+                .map(|stage| stage.stage)
+                .collect::<Vec<_>>(),
+            [Stage::PreInstall, Stage::Installer, Stage::PostInstall]
+        );
+        let runs = engine.runs();
+        assert!(runs[0].script.starts_with(&prelude.to_source()));
+        assert!(runs[0].script.ends_with("Write-Host 'pre install'"));
+        assert!(runs[1]
+            .script
+            .ends_with("Write-Host 'installer'\nensure $persist_dir"));
+        assert!(runs[2].script.ends_with("Write-Host 'post install'"));
+
+        let result = manifest
+            .uninstaller_host(&ctx, crate::Architecture::X64, &version_dir)
+            .with_engine(Arc::new(engine.clone()))
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(Error::Scripts(scripts::Error::PowershellExit(..)))
+        ));
+        let runs = engine.runs();
+        assert_eq!(runs.len(), 6);
+        assert_eq!(runs[3].script, "Write-Host 'pre uninstall'");
+        assert_eq!(runs[4].script, "Write-Host 'uninstaller'");
+
+        ctx.cleanup();
+    }
+
+    #[tokio::test]
+    async fn test_missing_uninstaller() {
+        let ctx = TestContext::new("installer-missing-uninstaller");
+        let version_dir = ctx.apps_path().join("app").join("1.0.0");
+        let engine = crate::scripts::testing::TestEngine::new();
+
+        let manifest: Manifest = serde_json::from_value(json!({
+            "version": "1.0.0",
+            "pre_uninstall": "Write-Host 'pre uninstall'",
+            "uninstaller": { "file": "uninstall.exe", "args": "/S" },
+            "post_uninstall": "Write-Host 'post uninstall'",
+        }))
+        .unwrap();
+
+        let output = manifest
+            .uninstaller_host(&ctx, crate::Architecture::X64, &version_dir)
+            .with_engine(Arc::new(engine.clone()))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            output
+                .stages
+                .iter()
+                .map(|stage| stage.stage)
+                .collect::<Vec<_>>(),
+            [Stage::PreUninstall, Stage::PostUninstall]
+        );
+        assert_eq!(output.removed, None);
+        assert_eq!(engine.runs().len(), 2);
+
+        ctx.cleanup();
+    }
+
+    #[tokio::test]
+    async fn test_installer_outside_version_dir() {
+        let ctx = TestContext::new("installer-outside");
+        let version_dir = ctx.apps_path().join("app").join("1.0.0");
+
+        let manifest: Manifest = serde_json::from_value(json!({
+            "version": "1.0.0",
+            "installer": { "file": "..\\..\\other\\setup.exe" },
+        }))
+        .unwrap();
+
+        assert!(matches!(
+            manifest
+                .installer_host(&ctx, crate::Architecture::X64, &version_dir, None)
+                .await,
+            Err(Error::OutsideVersionDir(_))
+        ));
+
+        // Without an installer or scripts, nothing is run
+        let manifest: Manifest = serde_json::from_value(json!({ "version": "1.0.0" })).unwrap();
+        let output = manifest
+            .uninstaller_host(&ctx, crate::Architecture::X64, &version_dir)
+            .await
+            .unwrap();
+        assert!(output.stages.is_empty());
+
+        ctx.cleanup();
+    }
+
+    #[tokio::test]
+    async fn test_uninstall_with_dependents() {
+        let ctx = TestContext::new("uninstaller-dependents");
+        let engine = crate::scripts::testing::TestEngine::new();
+
+        ctx.write_installed_manifest("app", &json!({ "version": "1.0.0", "depends": "runtime" }));
+        let runtime = json!({
+            "version": "1.0.0",
+            "uninstaller": { "script": "Write-Host 'uninstaller'" },
+        });
+        let current = ctx.write_installed_manifest("runtime", &runtime);
+        let manifest: Manifest = serde_json::from_value(runtime).unwrap();
+
+        let result = manifest
+            .uninstaller_host(&ctx, crate::Architecture::X64, &current)
+            .with_engine(Arc::new(engine.clone()))
+            .with_dependents_check("runtime")
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(Error::Dependencies(dependencies::Error::HasDependents { ref dependents, .. }))
+                if dependents == &["app"]
+        ));
+        assert!(engine.runs().is_empty());
+
+        // Once the dependent app is removed, the uninstaller runs
+        std::fs::remove_dir_all(ctx.apps_path().join("app")).unwrap();
+        manifest
+            .uninstaller_host(&ctx, crate::Architecture::X64, &current)
+            .with_engine(Arc::new(engine.clone()))
+            .with_dependents_check("runtime")
+            .await
+            .unwrap();
+        assert_eq!(engine.runs().len(), 1);
+
+        ctx.cleanup();
+    }
 }
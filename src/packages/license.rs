@@ -0,0 +1,952 @@
+//! Package licenses
+//!
+//! Parses license identifiers as SPDX expressions, including Scoop's custom values,
+//! and evaluates them against allow and deny lists
+
+use std::fmt::Display;
+
+use serde::{Serialize, Serializer};
+
+use crate::{buckets::Bucket, contexts::ScoopContext};
+
+use super::{models::manifest::PackageLicense, Manifest, NamedManifest, Result};
+
+#[derive(Debug, thiserror::Error, Clone, PartialEq, Eq)]
+#[allow(missing_docs)]
+/// License expression errors
+pub enum Error {
+    #[error("License expression is empty")]
+    Empty,
+    #[error("Unexpected `{0}` in license expression")]
+    UnexpectedToken(String),
+    #[error("Unexpected end of license expression")]
+    UnexpectedEnd,
+    #[error("Unknown license exception: {0}")]
+    UnknownException(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// A single license
+pub enum License {
+    /// An SPDX license, normalised to its current identifier
+    Spdx {
+        /// The SPDX license identifier
+        id: String,
+        /// Whether later versions of the license may be used (i.e `Apache-2.0+`)
+        ///
+        /// GNU licenses use their `-or-later` identifiers instead
+        or_later: bool,
+        /// The SPDX license exception (i.e `Classpath-exception-2.0`)
+        exception: Option<String>,
+    },
+    /// Scoop's `Freeware`
+    Freeware,
+    /// Scoop's `Proprietary`
+    Proprietary,
+    /// Scoop's `Shareware`
+    Shareware,
+    /// Scoop's `Public Domain`
+    PublicDomain,
+    /// Scoop's `Unknown`, or a missing license
+    Unknown,
+    /// Any other license, such as a URL or an unrecognised identifier
+    Other(String),
+}
+
+impl License {
+    /// Parse a single license identifier, normalising deprecated and imprecise SPDX identifiers
+    fn from_identifier(identifier: &str) -> Self {
+        match identifier.to_lowercase().replace(['-', '_'], " ").as_str() {
+            "freeware" => return Self::Freeware,
+            "proprietary" => return Self::Proprietary,
+            "shareware" => return Self::Shareware,
+            "public domain" | "publicdomain" => return Self::PublicDomain,
+            "unknown" => return Self::Unknown,
+            _ => {}
+        }
+
+        let (name, plus) = match identifier.strip_suffix('+') {
+            Some(name) => (name, true),
+            None => (identifier, false),
+        };
+
+        let Some(id) = spdx::license_id(name)
+            .or_else(|| {
+                spdx::identifiers::LICENSES
+                    .iter()
+                    .find(|(id, ..)| id.eq_ignore_ascii_case(name))
+                    .and_then(|(id, ..)| spdx::license_id(id))
+            })
+            .or_else(|| {
+                spdx::imprecise_license_id(name)
+                    .filter(|(_, len)| *len == name.len())
+                    .map(|(id, _)| id)
+            })
+        else {
+            return Self::Other(identifier.to_string());
+        };
+
+        if let Some((replacement, exception)) = DEPRECATED_WITH_EXCEPTIONS
+            .iter()
+            .find(|(deprecated, ..)| *deprecated == id.name)
+            .map(|(_, replacement, exception)| (replacement, exception))
+        {
+            return Self::Spdx {
+                id: (*replacement).to_string(),
+                or_later: false,
+                exception: Some((*exception).to_string()),
+            };
+        }
+
+        if let Some(replacement) = DEPRECATED
+            .iter()
+            .find(|(deprecated, _)| *deprecated == id.name)
+            .map(|(_, replacement)| replacement)
+        {
+            return Self::Spdx {
+                id: (*replacement).to_string(),
+                or_later: plus,
+                exception: None,
+            };
+        }
+
+        // GNU licenses moved to explicit `-only` and `-or-later` identifiers
+        let is_gnu = ["GPL-", "LGPL-", "AGPL-", "GFDL-"]
+            .iter()
+            .any(|prefix| id.name.starts_with(prefix));
+        if is_gnu && (id.is_deprecated() || plus) {
+            let base = id
+                .name
+                .trim_end_matches('+')
+                .trim_end_matches("-only")
+                .trim_end_matches("-or-later");
+            let or_later = plus || id.name.ends_with('+') || id.name.ends_with("-or-later");
+            let suffix = if or_later { "or-later" } else { "only" };
+
+            if let Some(id) = spdx::license_id(&format!("{base}-{suffix}")) {
+                return Self::Spdx {
+                    id: id.name.to_string(),
+                    or_later: false,
+                    exception: None,
+                };
+            }
+        }
+
+        Self::Spdx {
+            id: id.name.to_string(),
+            or_later: plus,
+            exception: None,
+        }
+    }
+
+    #[must_use]
+    /// Get the license's identifier, without any exception
+    pub fn id(&self) -> &str {
+        match self {
+            License::Spdx { id, .. } | License::Other(id) => id,
+            License::Freeware => "Freeware",
+            License::Proprietary => "Proprietary",
+            License::Shareware => "Shareware",
+            License::PublicDomain => "Public Domain",
+            License::Unknown => "Unknown",
+        }
+    }
+
+    #[must_use]
+    /// Check if the license is an OSI approved SPDX license
+    pub fn is_osi_approved(&self) -> bool {
+        matches!(self, License::Spdx { id, .. } if spdx::license_id(id).is_some_and(spdx::LicenseId::is_osi_approved))
+    }
+
+    /// Check if the license matches a license in a policy list
+    ///
+    /// Exceptions and `+` are ignored, so `GPL-2.0-only` matches `GPL-2.0-only WITH Classpath-exception-2.0`
+    fn matches(&self, other: &License) -> bool {
+        match (self, other) {
+            (License::Spdx { id, .. }, License::Spdx { id: other, .. })
+            | (License::Other(id), License::Other(other)) => id.eq_ignore_ascii_case(other),
+            _ => self == other,
+        }
+    }
+}
+
+/// Deprecated SPDX identifiers, and their replacements
+const DEPRECATED: &[(&str, &str)] = &[
+    ("BSD-2-Clause-FreeBSD", "BSD-2-Clause"),
+    ("BSD-2-Clause-NetBSD", "BSD-2-Clause"),
+    ("Nunit", "zlib-acknowledgement"),
+    ("StandardML-NJ", "SMLNJ"),
+];
+
+/// Deprecated SPDX identifiers that are now expressed with an exception
+const DEPRECATED_WITH_EXCEPTIONS: &[(&str, &str, &str)] = &[
+    ("eCos-2.0", "GPL-2.0-or-later", "eCos-exception-2.0"),
+    (
+        "GPL-2.0-with-autoconf-exception",
+        "GPL-2.0-only",
+        "Autoconf-exception-2.0",
+    ),
+    (
+        "GPL-2.0-with-bison-exception",
+        "GPL-2.0-or-later",
+        "Bison-exception-2.2",
+    ),
+    (
+        "GPL-2.0-with-classpath-exception",
+        "GPL-2.0-only",
+        "Classpath-exception-2.0",
+    ),
+    (
+        "GPL-2.0-with-font-exception",
+        "GPL-2.0-only",
+        "Font-exception-2.0",
+    ),
+    (
+        "GPL-2.0-with-GCC-exception",
+        "GPL-2.0-or-later",
+        "GCC-exception-2.0",
+    ),
+    (
+        "GPL-3.0-with-autoconf-exception",
+        "GPL-3.0-only",
+        "Autoconf-exception-3.0",
+    ),
+    (
+        "GPL-3.0-with-GCC-exception",
+        "GPL-3.0-only",
+        "GCC-exception-3.1",
+    ),
+    ("wxWindows", "GPL-2.0-or-later", "WxWindows-exception-3.1"),
+];
+
+impl Display for License {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            License::Spdx {
+                id,
+                or_later,
+                exception,
+            } => {
+                write!(f, "{id}")?;
+                if *or_later {
+                    write!(f, "+")?;
+                }
+                if let Some(exception) = exception {
+                    write!(f, " WITH {exception}")?;
+                }
+
+                Ok(())
+            }
+            _ => write!(f, "{}", self.id()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// A license expression
+pub enum Expression {
+    /// A single license
+    License(License),
+    /// All of the licenses apply
+    And(Vec<Expression>),
+    /// Any of the licenses may be chosen
+    Or(Vec<Expression>),
+}
+
+impl Expression {
+    /// Parse a license expression
+    ///
+    /// Supports SPDX's `AND`, `OR`, `WITH`, `+` and parentheses,
+    /// as well as Scoop's `,` (all licenses apply) and `|` (dual licensed) separators.
+    /// Identifiers separated by spaces are treated as a single identifier (i.e `Public Domain`).
+    ///
+    /// # Errors
+    /// - The expression is empty
+    /// - The expression has unbalanced parentheses or misplaced operators
+    /// - A `WITH` exception is not a known SPDX exception
+    pub fn parse(expression: &str) -> Result<Self, Error> {
+        let tokens = tokenize(expression);
+        if tokens.is_empty() {
+            return Err(Error::Empty);
+        }
+
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+        };
+        let expression = parser.parse_or()?;
+
+        match parser.tokens.get(parser.position) {
+            Some(token) => Err(Error::UnexpectedToken(token.to_string())),
+            None => Ok(expression),
+        }
+    }
+
+    #[must_use]
+    /// List every license in the expression
+    pub fn licenses(&self) -> Vec<&License> {
+        match self {
+            Expression::License(license) => vec![license],
+            Expression::And(expressions) | Expression::Or(expressions) => {
+                expressions.iter().flat_map(Expression::licenses).collect()
+            }
+        }
+    }
+
+    fn evaluate(&self, allowed: &impl Fn(&License) -> bool) -> bool {
+        match self {
+            Expression::License(license) => allowed(license),
+            Expression::And(expressions) => expressions.iter().all(|e| e.evaluate(allowed)),
+            Expression::Or(expressions) => expressions.iter().any(|e| e.evaluate(allowed)),
+        }
+    }
+}
+
+impl std::str::FromStr for Expression {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (expressions, operator) = match self {
+            Expression::License(license) => return license.fmt(f),
+            Expression::And(expressions) => (expressions, " AND "),
+            Expression::Or(expressions) => (expressions, " OR "),
+        };
+
+        for (i, expression) in expressions.iter().enumerate() {
+            if i > 0 {
+                write!(f, "{operator}")?;
+            }
+
+            if matches!(expression, Expression::License(_)) {
+                write!(f, "{expression}")?;
+            } else {
+                write!(f, "({expression})")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Serialize for Expression {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    With,
+    Identifier(String),
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Open => write!(f, "("),
+            Token::Close => write!(f, ")"),
+            Token::And => write!(f, "AND"),
+            Token::Or => write!(f, "OR"),
+            Token::With => write!(f, "WITH"),
+            Token::Identifier(identifier) => write!(f, "{identifier}"),
+        }
+    }
+}
+
+fn tokenize(expression: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+
+    let spaced = expression
+        .replace('(', " ( ")
+        .replace(')', " ) ")
+        .replace(',', " , ")
+        .replace('|', " | ");
+
+    for word in spaced.split_whitespace() {
+        let token = match word {
+            "(" => Token::Open,
+            ")" => Token::Close,
+            "," => Token::And,
+            "|" => Token::Or,
+            _ if word.eq_ignore_ascii_case("and") => Token::And,
+            _ if word.eq_ignore_ascii_case("or") => Token::Or,
+            _ if word.eq_ignore_ascii_case("with") => Token::With,
+            _ => {
+                // Consecutive words are a single identifier (i.e `Public Domain`)
+                if let Some(Token::Identifier(identifier)) = tokens.last_mut() {
+                    identifier.push(' ');
+                    identifier.push_str(word);
+                    continue;
+                }
+
+                Token::Identifier(word.to_string())
+            }
+        };
+
+        tokens.push(token);
+    }
+
+    tokens
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek_is(&self, token: &Token) -> bool {
+        self.tokens.get(self.position) == Some(token)
+    }
+
+    fn parse_or(&mut self) -> Result<Expression, Error> {
+        let mut expressions = vec![self.parse_and()?];
+
+        while self.peek_is(&Token::Or) {
+            self.position += 1;
+            expressions.push(self.parse_and()?);
+        }
+
+        Ok(if expressions.len() == 1 {
+            expressions.remove(0)
+        } else {
+            Expression::Or(expressions)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<Expression, Error> {
+        let mut expressions = vec![self.parse_term()?];
+
+        while self.peek_is(&Token::And) {
+            self.position += 1;
+            expressions.push(self.parse_term()?);
+        }
+
+        Ok(if expressions.len() == 1 {
+            expressions.remove(0)
+        } else {
+            Expression::And(expressions)
+        })
+    }
+
+    fn parse_term(&mut self) -> Result<Expression, Error> {
+        match self.next().ok_or(Error::UnexpectedEnd)? {
+            Token::Open => {
+                let expression = self.parse_or()?;
+
+                match self.next() {
+                    Some(Token::Close) => Ok(expression),
+                    Some(token) => Err(Error::UnexpectedToken(token.to_string())),
+                    None => Err(Error::UnexpectedEnd),
+                }
+            }
+            Token::Identifier(identifier) => {
+                let mut license = License::from_identifier(&identifier);
+
+                if self.peek_is(&Token::With) {
+                    self.position += 1;
+
+                    let Some(Token::Identifier(exception)) = self.next() else {
+                        return Err(Error::UnexpectedEnd);
+                    };
+                    let exception =
+                        spdx::exception_id(&exception).ok_or(Error::UnknownException(exception))?;
+
+                    if let License::Spdx {
+                        exception: ref mut license_exception,
+                        ..
+                    } = license
+                    {
+                        *license_exception = Some(exception.name.to_string());
+                    }
+                }
+
+                Ok(Expression::License(license))
+            }
+            token => Err(Error::UnexpectedToken(token.to_string())),
+        }
+    }
+}
+
+impl PackageLicense {
+    #[must_use]
+    /// Get the license identifier, if any
+    pub fn identifier(&self) -> Option<&str> {
+        match self {
+            PackageLicense::License(license) => Some(&license.identifier),
+            PackageLicense::String(license) => Some(license),
+            PackageLicense::Object(license) => license.identifier.as_deref(),
+        }
+    }
+
+    #[must_use]
+    /// Get the license url, if any
+    pub fn url(&self) -> Option<&str> {
+        match self {
+            PackageLicense::License(license) => license.url.as_deref(),
+            PackageLicense::String(_) => None,
+            PackageLicense::Object(license) => license.url.as_deref(),
+        }
+    }
+
+    /// Parse the license identifier as an expression
+    ///
+    /// A missing identifier is parsed as [`License::Unknown`]
+    ///
+    /// # Errors
+    /// - The identifier is not a valid expression
+    pub fn expression(&self) -> Result<Expression, Error> {
+        match self.identifier() {
+            Some(identifier) => Expression::parse(identifier),
+            None => Ok(Expression::License(License::Unknown)),
+        }
+    }
+}
+
+impl Manifest {
+    /// Get the manifest's license expression
+    ///
+    /// Missing licenses are [`License::Unknown`]
+    ///
+    /// # Errors
+    /// - The license identifier is not a valid expression
+    pub fn license_expression(&self) -> Result<Expression, Error> {
+        match &self.license {
+            Some(license) => license.expression(),
+            None => Ok(Expression::License(License::Unknown)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "verdict", rename_all = "snake_case")]
+/// The result of evaluating a license against a [`Policy`]
+pub enum Verdict {
+    /// The license is allowed
+    Allowed,
+    /// The license is not allowed
+    Denied {
+        /// The licenses in the expression that are not allowed
+        #[serde(serialize_with = "serialize_licenses")]
+        licenses: Vec<License>,
+    },
+    /// The license could not be evaluated, so it is not allowed
+    ///
+    /// This is the case for invalid license expressions and unreadable manifests
+    Failed {
+        /// Why the license could not be evaluated
+        reason: String,
+    },
+}
+
+fn serialize_licenses<S: Serializer>(
+    licenses: &[License],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(licenses.iter().map(ToString::to_string))
+}
+
+impl Verdict {
+    #[must_use]
+    /// Check if the license is allowed
+    pub fn is_allowed(&self) -> bool {
+        matches!(self, Verdict::Allowed)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+/// A package's license evaluation
+pub struct Evaluation {
+    /// The package name
+    pub name: String,
+    /// The bucket the manifest was loaded from, if evaluating a bucket
+    pub bucket: Option<String>,
+    /// The package's license, if it could be parsed
+    pub license: Option<Expression>,
+    #[serde(flatten)]
+    /// The policy's verdict
+    pub verdict: Verdict,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// A license policy, made up of allowed and denied licenses
+///
+/// A license is allowed if it is not denied, and either the allow list is empty or it is in the allow list.
+/// Expressions are allowed if the licenses that must apply are allowed, choosing any allowed license for `OR`.
+pub struct Policy {
+    allow: Vec<License>,
+    deny: Vec<License>,
+}
+
+impl Policy {
+    #[must_use]
+    /// Create a new policy that allows all licenses
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    /// Allow the given licenses
+    ///
+    /// Once any license is allowed, all other licenses are denied
+    pub fn allow(mut self, licenses: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.allow.extend(
+            licenses
+                .into_iter()
+                .map(|license| License::from_identifier(license.as_ref())),
+        );
+        self
+    }
+
+    #[must_use]
+    /// Deny the given licenses
+    pub fn deny(mut self, licenses: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.deny.extend(
+            licenses
+                .into_iter()
+                .map(|license| License::from_identifier(license.as_ref())),
+        );
+        self
+    }
+
+    #[must_use]
+    /// Check if a single license is allowed
+    pub fn allows(&self, license: &License) -> bool {
+        let denied = self.deny.iter().any(|denied| license.matches(denied));
+        let allowed =
+            self.allow.is_empty() || self.allow.iter().any(|allowed| license.matches(allowed));
+
+        allowed && !denied
+    }
+
+    #[must_use]
+    /// Evaluate a license expression
+    pub fn evaluate(&self, expression: &Expression) -> Verdict {
+        if expression.evaluate(&|license| self.allows(license)) {
+            return Verdict::Allowed;
+        }
+
+        let mut licenses = vec![];
+        for license in expression.licenses() {
+            if !self.allows(license) && !licenses.contains(license) {
+                licenses.push(license.clone());
+            }
+        }
+
+        Verdict::Denied { licenses }
+    }
+
+    #[must_use]
+    /// Evaluate a manifest's license
+    ///
+    /// Invalid license expressions are never allowed
+    pub fn evaluate_manifest(&self, manifest: &Manifest) -> Verdict {
+        match manifest.license_expression() {
+            Ok(license) => self.evaluate(&license),
+            Err(error) => Verdict::Failed {
+                reason: format!(
+                    "Invalid license \"{}\": {error}",
+                    manifest
+                        .license
+                        .as_ref()
+                        .and_then(PackageLicense::identifier)
+                        .unwrap_or_default()
+                ),
+            },
+        }
+    }
+
+    fn evaluation(&self, manifest: &NamedManifest, bucket: Option<&str>) -> Evaluation {
+        Evaluation {
+            name: manifest.name().to_string(),
+            bucket: bucket.map(String::from),
+            license: manifest.license_expression().ok(),
+            verdict: self.evaluate_manifest(manifest),
+        }
+    }
+
+    /// Evaluate the licenses of all installed apps
+    ///
+    /// Apps whose manifests cannot be read are reported as [`Verdict::Failed`]
+    ///
+    /// # Errors
+    /// - Reading the apps directory fails
+    pub fn evaluate_installed(&self, ctx: &impl ScoopContext) -> Result<Vec<Evaluation>> {
+        Ok(ctx
+            .installed_apps()?
+            .into_iter()
+            .map(|path| {
+                match NamedManifest::from_path(path.join("current").join("manifest.json")) {
+                    Ok(manifest) => self.evaluation(&manifest, None),
+                    Err(error) => Evaluation {
+                        name: path
+                            .file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy()
+                            .to_string(),
+                        bucket: None,
+                        license: None,
+                        verdict: Verdict::Failed {
+                            reason: format!("Reading manifest: {error}"),
+                        },
+                    },
+                }
+            })
+            .collect())
+    }
+
+    /// Evaluate the licenses of all manifests in a bucket
+    ///
+    /// Manifests that cannot be read are reported as [`Verdict::Failed`]
+    ///
+    /// # Errors
+    /// - Listing the bucket's manifests fails
+    pub fn evaluate_bucket(&self, bucket: &Bucket) -> Result<Vec<Evaluation>> {
+        let bucket_name = bucket.name();
+
+        Ok(bucket
+            .list_package_paths()?
+            .into_iter()
+            .map(|path| match NamedManifest::from_path(&path) {
+                Ok(manifest) => self.evaluation(&manifest, Some(&bucket_name)),
+                Err(error) => Evaluation {
+                    name: path
+                        .file_stem()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .to_string(),
+                    bucket: Some(bucket_name.to_string()),
+                    license: None,
+                    verdict: Verdict::Failed {
+                        reason: format!("Reading manifest: {error}"),
+                    },
+                },
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::contexts::testing::TestContext;
+
+    fn spdx(id: &str) -> License {
+        License::Spdx {
+            id: id.to_string(),
+            or_later: false,
+            exception: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_expressions() {
+        let cases = [
+            ("MIT", "MIT"),
+            ("mit", "MIT"),
+            ("MIT OR Apache-2.0", "MIT OR Apache-2.0"),
+            ("MIT|Apache-2.0", "MIT OR Apache-2.0"),
+            ("GPL-3.0-only,MIT", "GPL-3.0-only AND MIT"),
+            ("GPL-2.0", "GPL-2.0-only"),
+            ("GPL-2.0+", "GPL-2.0-or-later"),
+            ("LGPL-2.1-only+", "LGPL-2.1-or-later"),
+            ("Apache-2.0+", "Apache-2.0+"),
+            ("StandardML-NJ", "SMLNJ"),
+            (
+                "GPL-2.0-with-classpath-exception",
+                "GPL-2.0-only WITH Classpath-exception-2.0",
+            ),
+            (
+                "(MIT or BSD-3-Clause) and GPL-2.0-only with Classpath-exception-2.0",
+                "(MIT OR BSD-3-Clause) AND GPL-2.0-only WITH Classpath-exception-2.0",
+            ),
+            ("Freeware", "Freeware"),
+            ("Public Domain", "Public Domain"),
+            ("shareware|Proprietary", "Shareware OR Proprietary"),
+            ("Custom License", "Custom License"),
+        ];
+
+        for (expression, expected) in cases {
+            assert_eq!(
+                Expression::parse(expression).unwrap().to_string(),
+                expected,
+                "parsing {expression}"
+            );
+        }
+
+        assert_eq!(Expression::parse(" "), Err(Error::Empty));
+        assert_eq!(Expression::parse("(MIT"), Err(Error::UnexpectedEnd));
+        assert_eq!(
+            Expression::parse("MIT OR"),
+            Err(Error::UnexpectedEnd),
+            "dangling operator"
+        );
+        assert_eq!(
+            Expression::parse("MIT)"),
+            Err(Error::UnexpectedToken(")".to_string()))
+        );
+        assert_eq!(
+            Expression::parse("MIT WITH Not-An-Exception"),
+            Err(Error::UnknownException("Not-An-Exception".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_policy() {
+        let policy = Policy::new().deny(["GPL-3.0-only", "Proprietary"]);
+
+        let evaluate = |expression: &str| policy.evaluate(&Expression::parse(expression).unwrap());
+
+        assert!(evaluate("MIT").is_allowed());
+        assert!(evaluate("GPL-3.0-only OR MIT").is_allowed());
+        assert!(evaluate("Freeware").is_allowed());
+        assert_eq!(
+            evaluate("GPL-3.0,MIT"),
+            Verdict::Denied {
+                licenses: vec![spdx("GPL-3.0-only")]
+            }
+        );
+        assert_eq!(
+            evaluate("proprietary"),
+            Verdict::Denied {
+                licenses: vec![License::Proprietary]
+            }
+        );
+
+        let policy = Policy::new()
+            .allow(["MIT", "Apache-2.0", "GPL-2.0-only"])
+            .deny(["Apache-2.0"]);
+        let evaluate = |expression: &str| policy.evaluate(&Expression::parse(expression).unwrap());
+
+        assert!(evaluate("MIT OR Unknown").is_allowed());
+        assert!(evaluate("GPL-2.0 WITH Classpath-exception-2.0").is_allowed());
+        assert_eq!(
+            evaluate("Apache-2.0 OR Freeware"),
+            Verdict::Denied {
+                licenses: vec![spdx("Apache-2.0"), License::Freeware]
+            }
+        );
+    }
+
+    #[test]
+    fn test_evaluate_installed() {
+        let ctx = TestContext::new("license-policy");
+
+        for (name, license) in [
+            ("mit", json!("MIT")),
+            (
+                "proprietary",
+                json!({ "identifier": "Proprietary", "url": "https://example.com" }),
+            ),
+            ("unlicensed", serde_json::Value::Null),
+            ("vendor", json!("Proprietary, Copyright (c) Vendor")),
+        ] {
+            ctx.write_installed_manifest(name, &json!({ "version": "1.0.0", "license": license }));
+        }
+        let broken = ctx.write_installed_manifest("broken", &json!({}));
+        std::fs::write(broken.join("manifest.json"), "{").unwrap();
+
+        let policy = Policy::new().deny(["Proprietary", "Unknown"]);
+        let mut evaluations = policy.evaluate_installed(&ctx).unwrap();
+        evaluations.sort_by(|a, b| a.name.cmp(&b.name));
+
+        // Unreadable manifests and invalid licenses fail closed
+        let vendor = evaluations.pop().unwrap();
+        let broken = evaluations.remove(0);
+        for (evaluation, name) in [(&vendor, "vendor"), (&broken, "broken")] {
+            assert_eq!(evaluation.name, name);
+            assert_eq!(evaluation.license, None);
+            assert!(matches!(evaluation.verdict, Verdict::Failed { .. }));
+            assert!(!evaluation.verdict.is_allowed());
+        }
+        assert!(matches!(
+            &vendor.verdict,
+            Verdict::Failed { reason } if reason.starts_with("Invalid license \"Proprietary, Copyright (c) Vendor\"")
+        ));
+
+        assert_eq!(
+            serde_json::to_value(&evaluations).unwrap(),
+            json!([
+                { "name": "mit", "bucket": null, "license": "MIT", "verdict": "allowed" },
+                {
+                    "name": "proprietary",
+                    "bucket": null,
+                    "license": "Proprietary",
+                    "verdict": "denied",
+                    "licenses": ["Proprietary"],
+                },
+                {
+                    "name": "unlicensed",
+                    "bucket": null,
+                    "license": "Unknown",
+                    "verdict": "denied",
+                    "licenses": ["Unknown"],
+                },
+            ])
+        );
+
+        ctx.cleanup();
+    }
+
+    #[test]
+    fn test_evaluate_bucket() {
+        let ctx = TestContext::new("license-bucket");
+
+        ctx.write_bucket_manifest(
+            "main",
+            "mit",
+            &json!({ "version": "1.0.0", "license": "MIT" }),
+        );
+        ctx.write_bucket_manifest("main", "broken", &json!({}));
+        std::fs::write(
+            ctx.buckets_path()
+                .join("main")
+                .join("bucket")
+                .join("broken.json"),
+            "{",
+        )
+        .unwrap();
+
+        let bucket = Bucket::from_name(&ctx, "main").unwrap();
+        let policy = Policy::new().deny(["Proprietary"]);
+        let mut evaluations = policy.evaluate_bucket(&bucket).unwrap();
+        evaluations.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let [broken, mit] = evaluations.as_slice() else {
+            panic!("expected two evaluations, got {evaluations:?}");
+        };
+
+        assert_eq!(broken.name, "broken");
+        assert_eq!(broken.bucket.as_deref(), Some("main"));
+        assert_eq!(broken.license, None);
+        assert!(matches!(
+            &broken.verdict,
+            Verdict::Failed { reason } if reason.starts_with("Reading manifest")
+        ));
+
+        assert_eq!(
+            serde_json::to_value(mit).unwrap(),
+            json!({ "name": "mit", "bucket": "main", "license": "MIT", "verdict": "allowed" })
+        );
+
+        ctx.cleanup();
+    }
+}
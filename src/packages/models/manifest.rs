@@ -5,7 +5,10 @@
 // Thanks to quicktype.io for saving me a lot of time.
 // The names are a bit weird at times but I'll work on that in future.
 
-use std::{collections::HashMap, fmt::Display};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+};
 
 use itertools::Itertools as _;
 use serde::{Deserialize, Serialize};
@@ -13,17 +16,13 @@ use serde_with::skip_serializing_none;
 
 use crate::{scripts::PowershellScript, version::Version, Architecture};
 
-#[allow(clippy::unsafe_derive_deserialize)]
 #[skip_serializing_none]
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
 /// The manifest for a package
+///
+/// Manifests do not know their own name or bucket.
+/// See [`NamedManifest`](crate::packages::NamedManifest) and [`BucketManifest`](crate::packages::BucketManifest)
 pub struct Manifest {
-    /// This must be manually set
-    #[serde(skip)]
-    bucket: Option<String>,
-    /// This must be manually set
-    #[serde(skip)]
-    name: Option<String>,
     /// A comment.
     #[serde(rename = "##")]
     pub empty: Option<StringArray>,
@@ -64,65 +63,11 @@ pub struct Manifest {
     pub suggest: Option<Suggest>,
     /// The version of the package
     pub version: Version,
-    /// The environment variables to add to PATH
-    pub env_add_path: Option<StringArray>,
-    /// The environment variables to set
-    pub env_set: Option<HashMap<String, Option<serde_json::Value>>>,
     #[serde(flatten)]
     /// The install configuration
     pub install_config: InstallConfig,
 }
 
-impl Manifest {
-    #[must_use]
-    /// Get the name of the manifest
-    ///
-    /// # Safety
-    /// This field is manually set, and by default is uninitialized. This may cause undefined behavior.
-    ///
-    /// Use [`Manifest::name_opt`] or,
-    /// to ensure that this function returns properly, use the [`CreateManifest`] trait to set the name,
-    /// or create the manifest, rather than other methods that might fail to set the name.
-    pub unsafe fn name(&self) -> &str {
-        unsafe { self.name.as_ref().unwrap_unchecked() }
-    }
-
-    #[must_use]
-    /// Get the name of the manifest, or [`None`] if it is not set
-    pub fn name_opt(&self) -> Option<&str> {
-        self.name.as_deref()
-    }
-
-    /// Set the name of the manifest
-    pub fn set_name(&mut self, name: impl Into<String>) {
-        self.name = Some(name.into());
-    }
-
-    #[must_use]
-    /// Get the bucket the manifest is from
-    ///
-    /// # Safety
-    /// This field is manually set, and by default is uninitialized. This may cause undefined behavior.
-    ///
-    /// Use [`Manifest::bucket_opt`] or,
-    /// to ensure that this function returns properly, use the [`CreateManifest`] trait to set the bucket,
-    /// or create the manifest, rather than other methods that might fail to set the bucket.
-    pub unsafe fn bucket(&self) -> &str {
-        unsafe { self.bucket.as_ref().unwrap_unchecked() }
-    }
-
-    #[must_use]
-    /// Get the bucket the manifest is from, or [`None`] if it is not set
-    pub fn bucket_opt(&self) -> Option<&str> {
-        self.bucket.as_deref()
-    }
-
-    /// Set the bucket the manifest is from
-    pub fn set_bucket(&mut self, bucket: impl Into<String>) {
-        self.bucket = Some(bucket.into());
-    }
-}
-
 #[skip_serializing_none]
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 /// Manifest architecture specific configuration
@@ -149,6 +94,16 @@ impl std::ops::Index<Architecture> for ManifestArchitecture {
     }
 }
 
+impl std::ops::IndexMut<Architecture> for ManifestArchitecture {
+    fn index_mut(&mut self, index: Architecture) -> &mut Self::Output {
+        match index {
+            Architecture::Arm64 => &mut self.arm64,
+            Architecture::X64 => &mut self.x64,
+            Architecture::X86 => &mut self.x86,
+        }
+    }
+}
+
 #[skip_serializing_none]
 #[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
 /// The install configuration
@@ -157,6 +112,10 @@ pub struct InstallConfig {
     pub bin: Option<AliasArray<String>>,
     /// The checkver configuration
     pub checkver: Option<Checkver>,
+    /// The directories to add to PATH
+    pub env_add_path: Option<StringArray>,
+    /// The environment variables to set
+    pub env_set: Option<HashMap<String, Option<serde_json::Value>>>,
     /// The directories to extract to
     pub extract_dir: Option<StringArray>,
     #[cfg(feature = "manifest-hashes")]
@@ -316,12 +275,18 @@ pub struct Psmodule {
     pub name: Option<String>,
 }
 
-#[skip_serializing_none]
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
-pub struct Suggest {}
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(transparent)]
+/// Suggested packages, by the feature they provide
+///
+/// Each feature maps to alternative packages, any of which satisfies the suggestion
+/// (i.e `"JDK": ["java/openjdk", "java/temurin-jdk"]`)
+pub struct Suggest(
+    pub BTreeMap<String, TOrArrayOfTs<crate::packages::reference::manifest::Reference>>,
+);
 
 #[skip_serializing_none]
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
 #[serde(untagged)]
 pub enum AliasArray<T> {
     NestedArray(TOrArrayOfTs<T>),
@@ -381,7 +346,7 @@ pub enum Checkver {
 }
 
 #[skip_serializing_none]
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
 #[serde(untagged)]
 // TODO: Implement serializing manually so as to enable serializing null if it is an empty array
 pub enum TOrArrayOfTs<T> {
@@ -477,6 +442,149 @@ impl<T: Display> Display for TOrArrayOfTs<T> {
 
 pub type StringArray = TOrArrayOfTs<String>;
 
+// `TOrArrayOfTs` and `AliasArray` are deserialized manually rather than as untagged enums,
+// so that errors point at the offending element rather than "did not match any variant"
+mod de {
+    use std::marker::PhantomData;
+
+    use serde::{
+        de::{self, value, IntoDeserializer},
+        Deserialize, Deserializer,
+    };
+
+    use super::{AliasArray, TOrArrayOfTs};
+
+    struct SingleOrSeqVisitor<T>(PhantomData<T>);
+
+    macro_rules! forward_single {
+        ($($method:ident($ty:ty)),* $(,)?) => {
+            $(
+                fn $method<E: de::Error>(self, v: $ty) -> Result<Self::Value, E> {
+                    T::deserialize(v.into_deserializer()).map(TOrArrayOfTs::Single)
+                }
+            )*
+        };
+    }
+
+    impl<'de, T: Deserialize<'de>> de::Visitor<'de> for SingleOrSeqVisitor<T> {
+        type Value = TOrArrayOfTs<T>;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            formatter.write_str("a single value or an array of values")
+        }
+
+        forward_single!(
+            visit_bool(bool),
+            visit_i64(i64),
+            visit_u64(u64),
+            visit_f64(f64),
+            visit_str(&str),
+            visit_string(String),
+        );
+
+        fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<Self::Value, E> {
+            T::deserialize(value::BorrowedStrDeserializer::new(v)).map(TOrArrayOfTs::Single)
+        }
+
+        fn visit_map<A: de::MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+            T::deserialize(value::MapAccessDeserializer::new(map)).map(TOrArrayOfTs::Single)
+        }
+
+        fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut items = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+
+            while let Some(item) = seq.next_element()? {
+                items.push(item);
+            }
+
+            Ok(TOrArrayOfTs::Array(items))
+        }
+    }
+
+    impl<'de, T: Deserialize<'de>> Deserialize<'de> for TOrArrayOfTs<T> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_any(SingleOrSeqVisitor(PhantomData))
+        }
+    }
+
+    struct AliasArrayVisitor<T>(PhantomData<T>);
+
+    impl<'de, T: Deserialize<'de>> de::Visitor<'de> for AliasArrayVisitor<T> {
+        type Value = AliasArray<T>;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            formatter.write_str(
+                "a single value, an array of values, or an array of values and [value, alias] pairs",
+            )
+        }
+
+        fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut items: Vec<TOrArrayOfTs<T>> =
+                Vec::with_capacity(seq.size_hint().unwrap_or_default());
+
+            while let Some(item) = seq.next_element()? {
+                items.push(item);
+            }
+
+            if items
+                .iter()
+                .all(|item| matches!(item, TOrArrayOfTs::Single(_)))
+            {
+                Ok(AliasArray::NestedArray(TOrArrayOfTs::Array(
+                    items.into_iter().filter_map(TOrArrayOfTs::single).collect(),
+                )))
+            } else {
+                Ok(AliasArray::AliasArray(items))
+            }
+        }
+    }
+
+    impl<'de, T: Deserialize<'de>> Deserialize<'de> for AliasArray<T> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct Either<T>(PhantomData<T>);
+
+            // Anything other than an array is a single value
+            impl<'de, T: Deserialize<'de>> de::Visitor<'de> for Either<T> {
+                type Value = AliasArray<T>;
+
+                fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    AliasArrayVisitor::<T>(PhantomData).expecting(formatter)
+                }
+
+                fn visit_seq<A: de::SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+                    AliasArrayVisitor(PhantomData).visit_seq(seq)
+                }
+
+                fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                    SingleOrSeqVisitor(PhantomData)
+                        .visit_str(v)
+                        .map(AliasArray::NestedArray)
+                }
+
+                fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<Self::Value, E> {
+                    SingleOrSeqVisitor(PhantomData)
+                        .visit_borrowed_str(v)
+                        .map(AliasArray::NestedArray)
+                }
+
+                fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+                    SingleOrSeqVisitor(PhantomData)
+                        .visit_string(v)
+                        .map(AliasArray::NestedArray)
+                }
+
+                fn visit_map<A: de::MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                    SingleOrSeqVisitor(PhantomData)
+                        .visit_map(map)
+                        .map(AliasArray::NestedArray)
+                }
+            }
+
+            deserializer.deserialize_any(Either(PhantomData))
+        }
+    }
+}
+
 #[skip_serializing_none]
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(untagged)]
@@ -534,8 +642,8 @@ impl std::fmt::Display for PackageLicense {
 }
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct LicenseObject {
-    identifier: Option<String>,
-    url: Option<String>,
+    pub identifier: Option<String>,
+    pub url: Option<String>,
 }
 
 #[skip_serializing_none]
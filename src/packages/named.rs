@@ -0,0 +1,205 @@
+//! Manifests that carry their identity
+//!
+//! Manifests do not contain their own name or bucket,
+//! so manifests loaded from a known location are wrapped in [`Named`] or [`BucketManifest`],
+//! and APIs that need a manifest's name or bucket take those types.
+
+use std::{
+    ops::{Deref, DerefMut},
+    path::Path,
+};
+
+use serde::Serialize;
+
+use crate::buckets::Bucket;
+
+use super::{CreateManifest, InstallManifest, Manifest, Result};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A manifest with its package name
+///
+/// Dereferences to the inner manifest, and serializes as the inner manifest
+pub struct Named<T> {
+    name: String,
+    inner: T,
+}
+
+/// A package manifest with its package name
+pub type NamedManifest = Named<Manifest>;
+
+/// An install manifest with its package name
+pub type NamedInstallManifest = Named<InstallManifest>;
+
+impl<T> Named<T> {
+    /// Attach a package name to a manifest
+    pub fn new(name: impl Into<String>, inner: T) -> Self {
+        Self {
+            name: name.into(),
+            inner,
+        }
+    }
+
+    #[must_use]
+    /// Get the package name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[must_use]
+    /// Get the inner manifest
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    #[must_use]
+    /// Discard the package name, returning the inner manifest
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: CreateManifest> Named<T> {
+    /// Load a manifest from a path, taking the package name from the path
+    ///
+    /// The name is the file name without its extension (i.e `bucket/app.json`),
+    /// unless the file is an installed app's `manifest.json` or `install.json`,
+    /// in which case the name is the app's directory name (i.e `apps/app/current/manifest.json`)
+    ///
+    /// # Errors
+    /// - The file does not exist
+    /// - The file was not a valid manifest
+    /// - The name could not be determined from the path
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let name = name_from_path(path).ok_or(super::Error::MissingFileName)?;
+
+        Ok(Self::new(name, T::from_path(path)?))
+    }
+}
+
+impl NamedManifest {
+    #[must_use]
+    /// Attach the bucket the manifest was loaded from
+    pub fn with_bucket(self, bucket: &Bucket) -> BucketManifest {
+        BucketManifest {
+            bucket: bucket.name().to_string(),
+            manifest: self,
+        }
+    }
+}
+
+impl<T> Deref for Named<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<T> DerefMut for Named<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+impl<T: Serialize> Serialize for Named<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.inner.serialize(serializer)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A package manifest with its package name and the bucket it was loaded from
+///
+/// Dereferences to the [`NamedManifest`], and serializes as the inner manifest
+pub struct BucketManifest {
+    bucket: String,
+    manifest: NamedManifest,
+}
+
+impl BucketManifest {
+    /// Attach a package name and bucket to a manifest
+    pub fn new(bucket: impl Into<String>, name: impl Into<String>, manifest: Manifest) -> Self {
+        Self {
+            bucket: bucket.into(),
+            manifest: Named::new(name, manifest),
+        }
+    }
+
+    #[must_use]
+    /// Get the name of the bucket the manifest was loaded from
+    pub fn bucket(&self) -> &str {
+        &self.bucket
+    }
+
+    #[must_use]
+    /// Discard the bucket, returning the named manifest
+    pub fn into_named(self) -> NamedManifest {
+        self.manifest
+    }
+
+    #[must_use]
+    /// Discard the bucket and package name, returning the inner manifest
+    pub fn into_inner(self) -> Manifest {
+        self.manifest.into_inner()
+    }
+}
+
+impl Deref for BucketManifest {
+    type Target = NamedManifest;
+
+    fn deref(&self) -> &Self::Target {
+        &self.manifest
+    }
+}
+
+impl DerefMut for BucketManifest {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.manifest
+    }
+}
+
+impl From<BucketManifest> for NamedManifest {
+    fn from(manifest: BucketManifest) -> Self {
+        manifest.into_named()
+    }
+}
+
+impl Serialize for BucketManifest {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.manifest.serialize(serializer)
+    }
+}
+
+fn name_from_path(path: &Path) -> Option<String> {
+    let name = path.file_stem()?.to_string_lossy();
+
+    if name == "manifest" || name == "install" {
+        // Installed apps are laid out as `apps/<name>/<version or current>/<manifest>`
+        let app_dir = path.parent()?.parent()?;
+
+        Some(app_dir.file_name()?.to_string_lossy().to_string())
+    } else {
+        Some(name.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn test_name_from_path() {
+        let cases = [
+            ("buckets/main/bucket/app.json", "app"),
+            ("apps/app/current/manifest.json", "app"),
+            ("apps/app/1.0.0/install.json", "app"),
+        ];
+
+        for (path, name) in cases {
+            assert_eq!(name_from_path(&PathBuf::from(path)).as_deref(), Some(name));
+        }
+    }
+}
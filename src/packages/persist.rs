@@ -0,0 +1,361 @@
+//! Persisted app data
+//!
+//! Keeps the files and directories listed in a manifest's `persist` field in the context's persist directory,
+//! and links them into each version directory, like Scoop's `persist_data`
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use super::{
+    models::manifest::{AliasArray, TOrArrayOfTs},
+    Manifest,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+/// A persisted file or directory
+pub struct PersistEntry {
+    /// The path in the version directory
+    pub source: String,
+    /// The path in the app's persist directory
+    ///
+    /// Defaults to the source path
+    pub target: String,
+}
+
+impl PersistEntry {
+    #[must_use]
+    /// Create a persist entry that keeps the source at the same path in the persist directory
+    pub fn new(source: impl Into<String>) -> Self {
+        let source = trim_separators(&source.into());
+
+        Self {
+            target: source.clone(),
+            source,
+        }
+    }
+
+    /// Parse a single item of a manifest's `persist` field
+    ///
+    /// Returns [`None`] for an empty array
+    fn from_item(item: TOrArrayOfTs<String>) -> Option<Self> {
+        let mut parts = item.to_vec().into_iter();
+
+        let mut entry = Self::new(parts.next()?);
+        if let Some(target) = parts.next().filter(|target| !target.is_empty()) {
+            entry.target = trim_separators(&target);
+        }
+
+        Some(entry)
+    }
+
+    #[must_use]
+    /// Get the path to the source in the given version directory
+    pub fn source_path(&self, version_dir: impl AsRef<Path>) -> PathBuf {
+        version_dir.as_ref().join(&self.source)
+    }
+
+    #[must_use]
+    /// Get the path to the target in the given persist directory
+    pub fn target_path(&self, persist_dir: impl AsRef<Path>) -> PathBuf {
+        persist_dir.as_ref().join(&self.target)
+    }
+
+    /// Persist the entry, and link it into the version directory
+    ///
+    /// - If the target already exists, the source is renamed to `<source>.original` and the target is kept
+    /// - Otherwise, if the source exists, it is moved to the target
+    /// - Otherwise, an empty directory is created as the target.
+    ///   Like Scoop, files that may not exist should be created before persisting (i.e in `pre_install`)
+    ///
+    /// Directories are linked with junctions on Windows, and files with hard links.
+    /// Elsewhere, both are linked with symlinks.
+    ///
+    /// # Errors
+    /// - Moving the source failed
+    /// - Creating the target failed
+    /// - Linking the target failed
+    pub fn persist(
+        &self,
+        version_dir: impl AsRef<Path>,
+        persist_dir: impl AsRef<Path>,
+    ) -> std::io::Result<()> {
+        let source = self.source_path(version_dir);
+        let target = self.target_path(persist_dir);
+
+        if target.exists() {
+            if source.exists() {
+                let original = PathBuf::from(format!("{}.original", source.display()));
+                remove_any(&original)?;
+                std::fs::rename(&source, original)?;
+            }
+        } else if source.exists() {
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            move_path(&source, &target)?;
+        } else {
+            std::fs::create_dir_all(&target)?;
+        }
+
+        if let Some(parent) = source.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        link(&target, &source)
+    }
+
+    /// Remove the entry's link from the version directory, leaving the persisted data in place
+    ///
+    /// This must be done before removing a version directory, so the persisted data is not removed with it
+    ///
+    /// # Errors
+    /// - Removing the link failed
+    pub fn unlink(&self, version_dir: impl AsRef<Path>) -> std::io::Result<()> {
+        let source = self.source_path(version_dir);
+
+        let Ok(metadata) = source.symlink_metadata() else {
+            return Ok(());
+        };
+
+        // Hard links are indistinguishable from regular files, but removing a file never touches the persisted data
+        if metadata.is_symlink() || metadata.is_file() {
+            remove_link(&source)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn trim_separators(path: &str) -> String {
+    path.trim_end_matches(['/', '\\']).to_string()
+}
+
+/// Move a file or directory, falling back to copying if it cannot be renamed (i.e across drives)
+fn move_path(from: &Path, to: &Path) -> std::io::Result<()> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    copy_path(from, to)?;
+    remove_any(from)
+}
+
+fn copy_path(from: &Path, to: &Path) -> std::io::Result<()> {
+    if from.is_dir() {
+        std::fs::create_dir_all(to)?;
+        for entry in from.read_dir()? {
+            let entry = entry?;
+            copy_path(&entry.path(), &to.join(entry.file_name()))?;
+        }
+    } else {
+        std::fs::copy(from, to)?;
+    }
+
+    Ok(())
+}
+
+fn remove_any(path: &Path) -> std::io::Result<()> {
+    match path.symlink_metadata() {
+        Ok(metadata) if metadata.is_dir() => std::fs::remove_dir_all(path),
+        Ok(_) => remove_link(path),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Remove a file, or a file or directory link, without touching its target
+pub(super) fn remove_link(path: &Path) -> std::io::Result<()> {
+    // Directory links (i.e junctions) on Windows must be removed as directories
+    std::fs::remove_file(path).or_else(|_| std::fs::remove_dir(path))
+}
+
+#[cfg(windows)]
+pub(super) fn link(target: &Path, source: &Path) -> std::io::Result<()> {
+    if target.is_dir() {
+        junction::create(target, source)
+    } else {
+        std::fs::hard_link(target, source)
+    }
+}
+
+#[cfg(not(windows))]
+pub(super) fn link(target: &Path, source: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, source)
+}
+
+impl AliasArray<String> {
+    #[must_use]
+    /// Resolve the persist entries, as Scoop does for the `persist` field
+    pub fn persist_entries(&self) -> Vec<PersistEntry> {
+        match self {
+            AliasArray::NestedArray(TOrArrayOfTs::Single(source)) => {
+                vec![PersistEntry::new(source.clone())]
+            }
+            AliasArray::NestedArray(TOrArrayOfTs::Array(sources)) => {
+                sources.iter().cloned().map(PersistEntry::new).collect()
+            }
+            AliasArray::AliasArray(items) => items
+                .iter()
+                .cloned()
+                .filter_map(PersistEntry::from_item)
+                .collect(),
+        }
+    }
+}
+
+impl Manifest {
+    #[must_use]
+    /// Get the persisted files and directories
+    pub fn persist_entries(&self) -> Vec<PersistEntry> {
+        self.persist
+            .as_ref()
+            .map(AliasArray::persist_entries)
+            .unwrap_or_default()
+    }
+
+    /// Persist the manifest's data, and link it into the version directory
+    ///
+    /// See [`PersistEntry::persist`] for details
+    ///
+    /// # Errors
+    /// - Persisting any entry failed
+    pub fn persist_data(
+        &self,
+        version_dir: impl AsRef<Path>,
+        persist_dir: impl AsRef<Path>,
+    ) -> std::io::Result<()> {
+        let (version_dir, persist_dir) = (version_dir.as_ref(), persist_dir.as_ref());
+
+        let entries = self.persist_entries();
+        if !entries.is_empty() {
+            std::fs::create_dir_all(persist_dir)?;
+        }
+
+        entries
+            .iter()
+            .try_for_each(|entry| entry.persist(version_dir, persist_dir))
+    }
+
+    /// Remove the links to the manifest's persisted data from the version directory
+    ///
+    /// If `purge` is `true`, the persist directory is removed as well
+    ///
+    /// # Errors
+    /// - Removing any link failed
+    /// - Removing the persist directory failed
+    pub fn unpersist_data(
+        &self,
+        version_dir: impl AsRef<Path>,
+        persist_dir: impl AsRef<Path>,
+        purge: bool,
+    ) -> std::io::Result<()> {
+        let version_dir = version_dir.as_ref();
+
+        self.persist_entries()
+            .iter()
+            .try_for_each(|entry| entry.unlink(version_dir))?;
+
+        let persist_dir = persist_dir.as_ref();
+        if purge && persist_dir.exists() {
+            std::fs::remove_dir_all(persist_dir)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::contexts::{testing::TestContext, ScoopContext};
+
+    #[test]
+    fn test_persist_entries() {
+        let manifest: Manifest = serde_json::from_value(json!({
+            "version": "1.0.0",
+            "persist": ["data/", ["conf\\app.ini", "app.ini"], ["logs"]],
+        }))
+        .unwrap();
+
+        assert_eq!(
+            manifest.persist_entries(),
+            [
+                PersistEntry::new("data"),
+                PersistEntry {
+                    source: "conf\\app.ini".to_string(),
+                    target: "app.ini".to_string(),
+                },
+                PersistEntry::new("logs"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_persist_data() {
+        let ctx = TestContext::new("persist");
+        let manifest: Manifest = serde_json::from_value(json!({
+            "version": "1.0.0",
+            "persist": ["data", ["app.ini", "config/app.ini"], "cache"],
+        }))
+        .unwrap();
+
+        let app_dir = ctx.apps_path().join("app");
+        let persist_dir = ctx.persist_path().join("app");
+
+        // First install moves the data into the persist directory
+        let v1 = app_dir.join("1.0.0");
+        std::fs::create_dir_all(v1.join("data")).unwrap();
+        std::fs::write(v1.join("data").join("db"), "v1 db").unwrap();
+        std::fs::write(v1.join("app.ini"), "v1 config").unwrap();
+
+        manifest.persist_data(&v1, &persist_dir).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(persist_dir.join("data").join("db")).unwrap(),
+            "v1 db"
+        );
+        assert_eq!(
+            std::fs::read_to_string(persist_dir.join("config").join("app.ini")).unwrap(),
+            "v1 config"
+        );
+        assert!(persist_dir.join("cache").is_dir());
+        assert!(v1.join("cache").is_dir());
+
+        std::fs::write(v1.join("app.ini"), "user config").unwrap();
+
+        // Updates keep the persisted data, and the new version's defaults as `.original`
+        let v2 = app_dir.join("2.0.0");
+        std::fs::create_dir_all(&v2).unwrap();
+        std::fs::write(v2.join("app.ini"), "v2 config").unwrap();
+
+        manifest.unpersist_data(&v1, &persist_dir, false).unwrap();
+        std::fs::remove_dir_all(&v1).unwrap();
+        manifest.persist_data(&v2, &persist_dir).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(v2.join("app.ini")).unwrap(),
+            "user config"
+        );
+        assert_eq!(
+            std::fs::read_to_string(v2.join("app.ini.original")).unwrap(),
+            "v2 config"
+        );
+        assert_eq!(
+            std::fs::read_to_string(v2.join("data").join("db")).unwrap(),
+            "v1 db"
+        );
+
+        // Uninstalling without purging keeps the persisted data
+        manifest.unpersist_data(&v2, &persist_dir, false).unwrap();
+        assert!(!v2.join("data").exists());
+        assert!(!v2.join("app.ini").exists());
+        assert!(persist_dir.join("data").join("db").exists());
+
+        manifest.unpersist_data(&v2, &persist_dir, true).unwrap();
+        assert!(!persist_dir.exists());
+
+        ctx.cleanup();
+    }
+}
@@ -0,0 +1,270 @@
+//! `PowerShell` modules
+//!
+//! Links apps with a `psmodule` block into the context's modules directory, like Scoop's `install_psmodule`
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::contexts::ScoopContext;
+
+use super::{
+    persist::{link, remove_link},
+    NamedManifest,
+};
+
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+/// `PowerShell` module errors
+pub enum Error {
+    #[error("Invalid manifest: The 'name' property is missing from 'psmodule'")]
+    MissingName,
+    #[error("{} already exists{}", .path.display(), .owner.as_ref().map(|owner| format!(" and belongs to {owner}")).unwrap_or_default())]
+    Conflict {
+        path: PathBuf,
+        owner: Option<String>,
+    },
+    #[error("Linking module failed: {0}")]
+    IOError(#[from] std::io::Error),
+}
+
+/// `PowerShell` module result type
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+/// A change to `PSModulePath` required for the context's modules to be found
+pub struct PathChange {
+    /// The current value
+    pub previous: Option<String>,
+    /// The value with the context's modules directory added
+    pub value: String,
+}
+
+impl PathChange {
+    #[must_use]
+    /// Get the change required for `PSModulePath` to include the context's modules directory
+    ///
+    /// `current` should be the value of `PSModulePath` in the context's scope (i.e the user or machine environment).
+    /// Returns [`None`] if the modules directory is already included.
+    ///
+    /// Note that Windows `PowerShell` does not add the default user modules directory
+    /// (`%USERPROFILE%\Documents\WindowsPowerShell\Modules`) once the user's `PSModulePath` is set,
+    /// so, like Scoop, callers may want to pass it as the current value when the user's `PSModulePath` is unset.
+    pub fn for_context(ctx: &impl ScoopContext, current: Option<&str>) -> Option<Self> {
+        let modules_path = ctx.modules_path().display().to_string();
+        let normalize = |path: &str| path.trim_end_matches(['\\', '/']).to_lowercase();
+
+        let included = current.is_some_and(|current| {
+            current
+                .split(';')
+                .any(|path| normalize(path) == normalize(&modules_path))
+        });
+        if included {
+            return None;
+        }
+
+        let value = match current.filter(|current| !current.is_empty()) {
+            Some(current) => format!("{modules_path};{current}"),
+            None => modules_path,
+        };
+
+        Some(Self {
+            previous: current.map(String::from),
+            value,
+        })
+    }
+}
+
+#[must_use]
+/// Find what already occupies a module's path in the context's modules directory, if anything
+///
+/// Returns [`Error::Conflict`] with the app that the existing module links to, if any
+pub fn conflict(ctx: &impl ScoopContext, module_name: &str) -> Option<Error> {
+    let path = ctx.modules_path().join(module_name);
+    path.symlink_metadata().ok()?;
+
+    Some(Error::Conflict {
+        owner: owner(ctx, &path),
+        path,
+    })
+}
+
+/// Get the name of the app a module links to
+fn owner(ctx: &impl ScoopContext, path: &Path) -> Option<String> {
+    let target = std::fs::read_link(path).ok()?;
+    // Junction targets are verbatim paths (i.e `\\?\C:\...`) on Windows
+    let target = dunce::simplified(&target);
+    let apps_path = ctx.apps_path();
+
+    let app = target
+        .strip_prefix(&apps_path)
+        .or_else(|_| {
+            // The apps path may be canonicalized, while the link target is not, or vice versa
+            target.strip_prefix(dunce::canonicalize(&apps_path).unwrap_or(apps_path.clone()))
+        })
+        .ok()?
+        .components()
+        .next()?;
+
+    Some(app.as_os_str().to_string_lossy().to_string())
+}
+
+impl NamedManifest {
+    #[must_use]
+    /// Get the name of the manifest's `PowerShell` module, if it has one
+    pub fn psmodule_name(&self) -> Option<&str> {
+        self.psmodule.as_ref()?.name.as_deref()
+    }
+
+    /// Link the app's directory into the context's modules directory, if the manifest has a `psmodule` block
+    ///
+    /// An existing link for this app is replaced. Returns the module's path, if any.
+    ///
+    /// This does not change `PSModulePath`. Use [`PathChange::for_context`] to find the required change.
+    ///
+    /// # Errors
+    /// - The `psmodule` block has no name
+    /// - The module's path is already used by another app or module
+    /// - Linking the module failed
+    pub fn install_psmodule(
+        &self,
+        ctx: &impl ScoopContext,
+        app_dir: impl AsRef<Path>,
+    ) -> Result<Option<PathBuf>> {
+        if self.psmodule.is_none() {
+            return Ok(None);
+        }
+        let module_name = self.psmodule_name().ok_or(Error::MissingName)?;
+
+        match conflict(ctx, module_name) {
+            Some(Error::Conflict {
+                owner: Some(owner),
+                path,
+            }) if owner == self.name() => {
+                remove_link(&path)?;
+            }
+            Some(conflict) => return Err(conflict),
+            None => {}
+        }
+
+        let modules_path = ctx.modules_path();
+        std::fs::create_dir_all(&modules_path)?;
+
+        let path = modules_path.join(module_name);
+        link(app_dir.as_ref(), &path)?;
+
+        Ok(Some(path))
+    }
+
+    /// Remove the app's link from the context's modules directory, if the manifest has a `psmodule` block
+    ///
+    /// Modules that do not link to this app are left in place
+    ///
+    /// # Errors
+    /// - The `psmodule` block has no name
+    /// - Removing the link failed
+    pub fn uninstall_psmodule(&self, ctx: &impl ScoopContext) -> Result<()> {
+        if self.psmodule.is_none() {
+            return Ok(());
+        }
+        let module_name = self.psmodule_name().ok_or(Error::MissingName)?;
+
+        let path = ctx.modules_path().join(module_name);
+        if owner(ctx, &path).as_deref() == Some(self.name()) {
+            remove_link(&path)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{contexts::testing::TestContext, packages::Manifest};
+
+    fn manifest(name: &str, psmodule: &serde_json::Value) -> NamedManifest {
+        let manifest: Manifest = serde_json::from_value(json!({
+            "version": "1.0.0",
+            "psmodule": psmodule,
+        }))
+        .unwrap();
+
+        NamedManifest::new(name, manifest)
+    }
+
+    #[test]
+    fn test_install_psmodule() {
+        let ctx = TestContext::new("psmodule");
+        let app = manifest("posh-app", &json!({ "name": "PoshApp" }));
+        let other = manifest("other-app", &json!({ "name": "PoshApp" }));
+
+        let app_dir = ctx.apps_path().join("posh-app").join("1.0.0");
+        std::fs::create_dir_all(&app_dir).unwrap();
+        std::fs::write(app_dir.join("PoshApp.psd1"), "@{}").unwrap();
+
+        let path = app.install_psmodule(&ctx, &app_dir).unwrap().unwrap();
+        assert_eq!(path, ctx.modules_path().join("PoshApp"));
+        assert!(path.join("PoshApp.psd1").exists());
+
+        // Reinstalling replaces the app's own link
+        app.install_psmodule(&ctx, &app_dir).unwrap();
+
+        let Err(Error::Conflict { owner, .. }) = other.install_psmodule(&ctx, &app_dir) else {
+            panic!("expected a conflict");
+        };
+        assert_eq!(owner.as_deref(), Some("posh-app"));
+
+        other.uninstall_psmodule(&ctx).unwrap();
+        assert!(path.exists());
+
+        app.uninstall_psmodule(&ctx).unwrap();
+        assert!(!path.exists());
+        assert!(app_dir.join("PoshApp.psd1").exists());
+
+        assert!(matches!(
+            manifest("unnamed", &json!({})).install_psmodule(&ctx, &app_dir),
+            Err(Error::MissingName)
+        ));
+        assert_eq!(
+            manifest("none", &serde_json::Value::Null)
+                .install_psmodule(&ctx, &app_dir)
+                .unwrap(),
+            None
+        );
+
+        ctx.cleanup();
+    }
+
+    #[test]
+    fn test_path_change() {
+        let ctx = TestContext::new("psmodule-path");
+        let modules_path = ctx.modules_path().display().to_string();
+
+        assert_eq!(
+            PathChange::for_context(&ctx, Some(r"C:\Modules")),
+            Some(PathChange {
+                previous: Some(r"C:\Modules".to_string()),
+                value: format!(r"{modules_path};C:\Modules"),
+            })
+        );
+        assert_eq!(
+            PathChange::for_context(&ctx, None),
+            Some(PathChange {
+                previous: None,
+                value: modules_path.clone(),
+            })
+        );
+        assert_eq!(
+            PathChange::for_context(
+                &ctx,
+                Some(&format!(r"C:\Modules;{}\", modules_path.to_uppercase()))
+            ),
+            None
+        );
+
+        ctx.cleanup();
+    }
+}
@@ -7,9 +7,8 @@ use itertools::Itertools;
 use crate::{
     buckets::Bucket,
     contexts::ScoopContext,
-    hacks::let_chain,
     handles::{self, packages::PackageHandle},
-    packages::{CreateManifest, Manifest},
+    packages::{BucketManifest, CreateManifest, Manifest, NamedManifest},
     requests::Client,
 };
 
@@ -110,54 +109,64 @@ impl Reference {
     /// - If the app dir cannot be read
     /// - If the bucket is not valid
     /// - If the bucket is not found
-    pub async fn manifest(&self, ctx: &impl ScoopContext) -> Result<Manifest, Error> {
+    pub async fn manifest(&self, ctx: &impl ScoopContext) -> Result<NamedManifest, Error> {
         // TODO: Map output to fix version
 
-        let mut manifest = if {
-            cfg_if::cfg_if! {
-                if #[cfg(feature = "manifest-hashes")] {
-                    matches!(self.manifest, manifest::Reference::File(_) | manifest::Reference::Url(_))
-                } else {
-                    matches!(self.manifest, manifest::Reference::File(_))
-                }
-            }
-        } {
-            let mut manifest = match &self.manifest {
-                manifest::Reference::File(path) => Manifest::from_path(path)?,
-                #[cfg(feature = "manifest-hashes")]
-                manifest::Reference::Url(url) => {
-                    let manifest_string = Client::asynchronous()
-                        .get(url.to_string())
-                        .send()
-                        .await?
-                        .text()
-                        .await?;
-
-                    Manifest::from_str(manifest_string)?
-                }
-                _ => unreachable!(),
-            };
+        let name = self.name().ok_or(Error::MissingAppName)?;
 
-            manifest.set_name(self.name().ok_or(Error::MissingAppName)?);
+        let manifest = match &self.manifest {
+            manifest::Reference::File(path) => Manifest::from_path(path)?,
+            #[cfg(feature = "manifest-hashes")]
+            manifest::Reference::Url(url) => {
+                let manifest_string = Client::asynchronous()
+                    .get(url.to_string())
+                    .send()
+                    .await?
+                    .text()
+                    .await?;
+
+                Manifest::from_str(manifest_string)
+                    .map_err(|e| crate::packages::Error::ParsingManifest(url.to_string(), e))?
+            }
+            manifest::Reference::BucketNamePair { bucket, .. } => {
+                let manifest = Bucket::from_name(ctx, bucket)?.get_manifest(name)?;
 
-            Ok(manifest)
-        } else if let Some(bucket_name) = self.bucket() {
-            let bucket = Bucket::from_name(ctx, bucket_name)?;
+                return self.versioned(ctx, manifest).await;
+            }
+            manifest::Reference::Name(_) => {
+                let manifest = Bucket::list_all(ctx)?
+                    .into_iter()
+                    .find_map(|bucket| bucket.get_manifest(&name).ok())
+                    .ok_or(Error::NotFound)?;
 
-            Ok(bucket.get_manifest(self.name().ok_or(Error::MissingAppName)?)?)
-        } else {
-            Ok(Bucket::list_all(ctx)?
-                .into_iter()
-                .find_map(|bucket| bucket.get_manifest(self.name()?).ok())
-                .ok_or(Error::NotFound)?)
+                return self.versioned(ctx, manifest).await;
+            }
         };
 
+        #[cfg_attr(not(feature = "manifest-hashes"), allow(unused_mut))]
+        let mut manifest = NamedManifest::new(name, manifest);
+
         #[cfg(feature = "manifest-hashes")]
-        let_chain!(let Ok(manifest) = manifest.as_mut(); let Some(version) = &self.version; {
-            manifest.set_version(ctx,version.clone()).await?;
-        });
+        if let Some(version) = &self.version {
+            manifest.set_version(ctx, version.clone()).await?;
+        }
+
+        Ok(manifest)
+    }
+
+    /// Set the version of a bucket manifest, if the reference has a version
+    async fn versioned(
+        &self,
+        #[cfg_attr(not(feature = "manifest-hashes"), allow(unused_variables))] ctx: &impl ScoopContext,
+        #[cfg_attr(not(feature = "manifest-hashes"), allow(unused_mut))]
+        mut manifest: BucketManifest,
+    ) -> Result<NamedManifest, Error> {
+        #[cfg(feature = "manifest-hashes")]
+        if let Some(version) = &self.version {
+            manifest.set_version(ctx, version.clone()).await?;
+        }
 
-        manifest
+        Ok(manifest.into_named())
     }
 
     /// Find the first installed manifest for the package
@@ -187,10 +196,10 @@ impl Reference {
     /// - Failed to parse the manifest
     /// - Package was missing a name
     /// - Listing the installed apps failed
-    pub fn first_installed(&self, ctx: &impl ScoopContext) -> Result<Manifest, Error> {
+    pub fn first_installed(&self, ctx: &impl ScoopContext) -> Result<NamedManifest, Error> {
         let app_path = self.first_installed_path(ctx)?;
 
-        Ok(Manifest::from_path(
+        Ok(NamedManifest::from_path(
             app_path.join("current").join("manifest.json"),
         )?)
     }
@@ -199,7 +208,7 @@ impl Reference {
     /// Find the first matching manifest in local buckets
     ///
     /// Returns [`None`] if no matching manifest is found
-    pub fn first(&self, ctx: &impl ScoopContext) -> Option<Manifest> {
+    pub fn first(&self, ctx: &impl ScoopContext) -> Option<BucketManifest> {
         let Ok(buckets) = Bucket::list_all(ctx) else {
             return None;
         };
@@ -253,21 +262,26 @@ impl Reference {
     /// - If the app dir cannot be read
     /// - If any of the buckets are not valid
     /// - If any of the buckets are not found
-    pub async fn list_manifests(&self, ctx: &impl ScoopContext) -> Result<Vec<Manifest>, Error> {
-        futures::future::try_join_all(
-            self.list_manifest_paths(ctx)
-                .into_iter()
-                .map(Manifest::from_path)
-                .map(|manifest| async {
-                    let mut manifest = manifest?;
-                    #[cfg(feature = "manifest-hashes")]
-                    if let Some(version) = &self.version {
-                        manifest.set_version(ctx, version.clone()).await?;
-                    }
+    pub async fn list_manifests(
+        &self,
+        ctx: &impl ScoopContext,
+    ) -> Result<Vec<NamedManifest>, Error> {
+        let name = self.name().ok_or(Error::MissingAppName)?;
 
-                    Ok::<Manifest, Error>(manifest)
-                }),
-        )
+        futures::future::try_join_all(self.list_manifest_paths(ctx).into_iter().map(|path| {
+            let name = &name;
+
+            async move {
+                // Manifest paths are laid out as `<bucket>/bucket/<name>.json`
+                let bucket_path = path
+                    .parent()
+                    .and_then(std::path::Path::parent)
+                    .ok_or(Error::NotFound)?;
+                let manifest = Bucket::from_path(bucket_path)?.get_manifest(name)?;
+
+                self.versioned(ctx, manifest).await
+            }
+        }))
         .await
     }
 
@@ -0,0 +1,154 @@
+//! Package shortcuts
+//!
+//! Resolves a manifest's `shortcuts` field into the Start Menu shortcuts Scoop creates for it
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::Architecture;
+
+use super::{
+    models::manifest::{AliasArray, TOrArrayOfTs},
+    Manifest,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+/// A Start Menu shortcut
+pub struct Shortcut {
+    /// The path to the shortcut's target, relative to the app directory
+    pub target: String,
+    /// The shortcut name, which may include subdirectories of the Start Menu folder
+    ///
+    /// Defaults to the target's file name without its extension
+    pub name: String,
+    /// The arguments passed to the target
+    pub args: Option<String>,
+    /// The path to the shortcut's icon, relative to the app directory
+    pub icon: Option<String>,
+}
+
+impl Shortcut {
+    /// Parse a single item of a manifest's `shortcuts` field
+    ///
+    /// Returns [`None`] for an empty array
+    fn from_item(item: TOrArrayOfTs<String>) -> Option<Self> {
+        let mut parts = item.to_vec().into_iter();
+
+        let target = parts.next()?;
+        let name = parts
+            .next()
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| {
+                Path::new(&target.replace('\\', "/"))
+                    .file_stem()
+                    .map_or_else(|| target.clone(), |stem| stem.to_string_lossy().to_string())
+            });
+        let args = parts.next().filter(|args| !args.is_empty());
+        let icon = parts.next().filter(|icon| !icon.is_empty());
+
+        Some(Self {
+            target,
+            name,
+            args,
+            icon,
+        })
+    }
+
+    #[must_use]
+    /// Get the path to the shortcut's target, installed in the given app directory
+    pub fn target_path(&self, app_dir: impl AsRef<Path>) -> PathBuf {
+        app_dir.as_ref().join(&self.target)
+    }
+
+    #[must_use]
+    /// Get the path to the shortcut's icon, if any, installed in the given app directory
+    pub fn icon_path(&self, app_dir: impl AsRef<Path>) -> Option<PathBuf> {
+        self.icon.as_ref().map(|icon| app_dir.as_ref().join(icon))
+    }
+}
+
+impl AliasArray<String> {
+    #[must_use]
+    /// Resolve the shortcuts, as Scoop does for the `shortcuts` field
+    pub fn shortcuts(&self) -> Vec<Shortcut> {
+        match self {
+            // A single array is a single shortcut, rather than a list of targets
+            AliasArray::NestedArray(item) => {
+                Shortcut::from_item(item.clone()).into_iter().collect()
+            }
+            AliasArray::AliasArray(items) => items
+                .iter()
+                .cloned()
+                .filter_map(Shortcut::from_item)
+                .collect(),
+        }
+    }
+}
+
+impl Manifest {
+    #[must_use]
+    /// Get the Start Menu shortcuts for a given architecture
+    pub fn shortcut_entries(&self, arch: Architecture) -> Vec<Shortcut> {
+        self.install_config(arch)
+            .shortcuts
+            .map(|shortcuts| shortcuts.shortcuts())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_shortcut_entries() {
+        let manifest: Manifest = serde_json::from_value(json!({
+            "version": "1.0.0",
+            "shortcuts": [
+                ["app.exe", "App"],
+                ["bin\\tool.exe", "Tools\\Tool", "--verbose", "icons\\tool.ico"],
+                ["other.exe", "", ""],
+            ],
+            "architecture": {
+                "32bit": { "shortcuts": [["app32.exe", "App (32-bit)"]] },
+            },
+        }))
+        .unwrap();
+
+        assert_eq!(
+            manifest.shortcut_entries(Architecture::X64),
+            [
+                Shortcut {
+                    target: "app.exe".to_string(),
+                    name: "App".to_string(),
+                    args: None,
+                    icon: None,
+                },
+                Shortcut {
+                    target: "bin\\tool.exe".to_string(),
+                    name: "Tools\\Tool".to_string(),
+                    args: Some("--verbose".to_string()),
+                    icon: Some("icons\\tool.ico".to_string()),
+                },
+                Shortcut {
+                    target: "other.exe".to_string(),
+                    name: "other".to_string(),
+                    args: None,
+                    icon: None,
+                },
+            ]
+        );
+        assert_eq!(
+            manifest.shortcut_entries(Architecture::X86),
+            [Shortcut {
+                target: "app32.exe".to_string(),
+                name: "App (32-bit)".to_string(),
+                args: None,
+                icon: None,
+            }]
+        );
+    }
+}
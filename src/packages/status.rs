@@ -0,0 +1,264 @@
+//! Installed app status
+//!
+//! Reports whether installed apps are up to date with their buckets, like `scoop status`.
+
+use std::{cmp::Ordering, path::Path};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::{buckets::Bucket, contexts::ScoopContext, version::Version};
+
+use super::{is_installed, reference, InstallManifest, Named, NamedManifest, Result};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+/// The state of an installed app
+pub enum State {
+    /// The installed version matches the bucket's version
+    UpToDate,
+    /// The bucket has a different version
+    Outdated {
+        /// The bucket's version
+        latest: Version,
+    },
+    /// The bucket has a different version, but the app is held
+    Held {
+        /// The bucket's version
+        latest: Version,
+    },
+    /// The app's bucket, or the app's manifest in the bucket, no longer exists
+    Removed,
+    /// The app was installed from a url or file, so it cannot be checked against a bucket
+    External,
+    /// The app's install failed, so it has no `install.json` or `manifest.json`
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+/// The status of an installed app
+pub struct Status {
+    /// The app name
+    pub name: String,
+    /// The installed version, if the installed manifest could be read
+    pub version: Option<Version>,
+    /// The bucket, url or file the app was installed from, if known
+    pub source: Option<String>,
+    #[serde(flatten)]
+    /// The app's state
+    pub state: State,
+    /// Dependencies of the installed manifest that are not installed
+    pub missing_dependencies: Vec<reference::manifest::Reference>,
+}
+
+impl Status {
+    /// Get the status of every installed app
+    ///
+    /// # Errors
+    /// - Reading the apps directory fails
+    pub fn list_all(ctx: &impl ScoopContext) -> Result<Vec<Self>> {
+        let installed_apps = ctx.installed_apps()?;
+
+        Ok({
+            cfg_if::cfg_if! {
+                if #[cfg(feature = "rayon")] {
+                    installed_apps.par_iter()
+                } else {
+                    installed_apps.iter()
+                }
+            }
+        }
+        .map(|path| Self::from_app_path(ctx, path))
+        .collect())
+    }
+
+    #[must_use]
+    /// Get the status of an installed app by name
+    pub fn for_app(ctx: &impl ScoopContext, name: impl AsRef<str>) -> Self {
+        Self::from_app_path(ctx, ctx.apps_path().join(name.as_ref()))
+    }
+
+    fn from_app_path(ctx: &impl ScoopContext, path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let current = path.join("current");
+
+        let install_manifest =
+            Named::<InstallManifest>::from_path(current.join("install.json")).ok();
+        let manifest = NamedManifest::from_path(current.join("manifest.json")).ok();
+
+        let version = manifest.as_ref().map(|manifest| manifest.version.clone());
+        let source = install_manifest
+            .as_ref()
+            .and_then(|install| install.bucket.clone().or_else(|| install.url.clone()));
+
+        let (Some(install_manifest), Some(manifest)) = (install_manifest, manifest) else {
+            return Self {
+                name,
+                version,
+                source,
+                state: State::Failed,
+                missing_dependencies: vec![],
+            };
+        };
+
+        let missing_dependencies = manifest
+            .depends()
+            .into_iter()
+            .filter(|dependency| {
+                dependency
+                    .clone()
+                    .into_package_ref()
+                    .name()
+                    .is_some_and(|name| !is_installed(ctx, name, None::<&str>))
+            })
+            .collect();
+
+        let state = if let Some(bucket) = &install_manifest.bucket {
+            let latest = Bucket::from_name(ctx, bucket)
+                .ok()
+                .and_then(|bucket| bucket.get_manifest(&name).ok());
+
+            match latest {
+                None => State::Removed,
+                Some(latest) if manifest.version.scoop_cmp(&latest.version) == Ordering::Equal => {
+                    State::UpToDate
+                }
+                Some(latest) if install_manifest.hold == Some(true) => State::Held {
+                    latest: latest.into_inner().version,
+                },
+                Some(latest) => State::Outdated {
+                    latest: latest.into_inner().version,
+                },
+            }
+        } else {
+            State::External
+        };
+
+        Self {
+            name,
+            version,
+            source,
+            state,
+            missing_dependencies,
+        }
+    }
+
+    #[must_use]
+    /// Check if the app is outdated, including if it is held
+    pub fn is_outdated(&self) -> bool {
+        matches!(self.state, State::Outdated { .. } | State::Held { .. })
+    }
+
+    #[must_use]
+    /// Check if any of the app's dependencies are not installed
+    pub fn is_missing_dependencies(&self) -> bool {
+        !self.missing_dependencies.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::contexts::testing::TestContext;
+
+    #[test]
+    fn test_status() {
+        let ctx = TestContext::new("status");
+
+        for (name, version) in [
+            ("current", "1.0.0"),
+            ("old", "2.0.0"),
+            ("held", "2.0.0"),
+            ("nightly", "nightly"),
+        ] {
+            ctx.write_bucket_manifest("main", name, &json!({ "version": version }));
+        }
+
+        let apps = [
+            (
+                "current",
+                Some(json!({ "bucket": "main" })),
+                json!({ "version": "1.0.0", "depends": ["missing", "old"] }),
+                State::UpToDate,
+            ),
+            (
+                "old",
+                Some(json!({ "bucket": "main" })),
+                json!({ "version": "1.0.0" }),
+                State::Outdated {
+                    latest: Version::new("2.0.0"),
+                },
+            ),
+            (
+                "held",
+                Some(json!({ "bucket": "main", "hold": true })),
+                json!({ "version": "1.0.0" }),
+                State::Held {
+                    latest: Version::new("2.0.0"),
+                },
+            ),
+            (
+                "nightly",
+                Some(json!({ "bucket": "main" })),
+                json!({ "version": "nightly-20240101" }),
+                State::UpToDate,
+            ),
+            (
+                "removed",
+                Some(json!({ "bucket": "main" })),
+                json!({ "version": "1.0.0" }),
+                State::Removed,
+            ),
+            (
+                "external",
+                Some(json!({ "url": "https://example.com/app.json" })),
+                json!({ "version": "1.0.0" }),
+                State::External,
+            ),
+            ("failed", None, json!({ "version": "1.0.0" }), State::Failed),
+        ];
+
+        for (name, install, manifest, _) in &apps {
+            let current = ctx.write_installed_manifest(name, manifest);
+
+            if let Some(install) = install {
+                std::fs::write(current.join("install.json"), install.to_string()).unwrap();
+            }
+        }
+
+        let statuses = Status::list_all(&ctx).unwrap();
+        assert_eq!(statuses.len(), apps.len());
+
+        for (name, _, manifest, state) in apps {
+            let status = statuses.iter().find(|status| status.name == name).unwrap();
+
+            assert_eq!(status.state, state, "state of {name}");
+            assert_eq!(
+                status.version.as_ref().map(Version::as_str),
+                manifest["version"].as_str()
+            );
+        }
+
+        let current = Status::for_app(&ctx, "current");
+        assert!(current.is_missing_dependencies());
+        assert_eq!(
+            serde_json::to_value(&current).unwrap(),
+            json!({
+                "name": "current",
+                "version": "1.0.0",
+                "source": "main",
+                "state": "up_to_date",
+                "missing_dependencies": ["missing"],
+            })
+        );
+
+        ctx.cleanup();
+    }
+}
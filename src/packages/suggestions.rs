@@ -0,0 +1,129 @@
+//! Suggested packages
+//!
+//! Reports which of a manifest's `suggest` features are not provided by any installed app, like Scoop's `show_suggestions`.
+
+use std::{collections::HashSet, ffi::OsStr};
+
+use serde::Serialize;
+
+use crate::contexts::ScoopContext;
+
+use super::{models::manifest::Suggest, reference::manifest::Reference, Manifest};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+/// A suggested feature that no installed app provides
+pub struct UnmetSuggestion {
+    /// The feature name (i.e `JDK`)
+    pub feature: String,
+    /// The suggested packages, any of which would satisfy the suggestion
+    pub alternatives: Vec<Reference>,
+}
+
+impl Suggest {
+    #[must_use]
+    /// List the features that are not provided by any of the given installed app names
+    ///
+    /// Suggestions are matched by app name, ignoring their bucket
+    pub fn unmet(&self, installed: &HashSet<String>) -> Vec<UnmetSuggestion> {
+        self.0
+            .iter()
+            .filter_map(|(feature, alternatives)| {
+                let alternatives = alternatives.clone().to_vec();
+
+                let fulfilled = alternatives.iter().any(|alternative| {
+                    alternative
+                        .clone()
+                        .into_package_ref()
+                        .name()
+                        .is_some_and(|name| installed.contains(&name))
+                });
+
+                (!fulfilled).then(|| UnmetSuggestion {
+                    feature: feature.clone(),
+                    alternatives,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Manifest {
+    /// List the suggested features that are not provided by any app installed in the given contexts
+    ///
+    /// Like Scoop, pass both the user and global contexts (i.e as [`AnyContext`](crate::contexts::AnyContext)s)
+    /// to consider apps installed in either scope
+    ///
+    /// # Errors
+    /// - Reading any context's apps directory fails
+    pub fn unmet_suggestions<C: ScoopContext>(
+        &self,
+        contexts: &[C],
+    ) -> std::io::Result<Vec<UnmetSuggestion>> {
+        let Some(suggest) = &self.suggest else {
+            return Ok(vec![]);
+        };
+
+        let mut installed = HashSet::new();
+        for ctx in contexts {
+            installed.extend(
+                ctx.installed_apps()?
+                    .iter()
+                    .filter_map(|path| path.file_name().and_then(OsStr::to_str))
+                    .map(String::from),
+            );
+        }
+
+        Ok(suggest.unmet(&installed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::contexts::testing::TestContext;
+
+    #[test]
+    fn test_unmet_suggestions() {
+        let manifest: Manifest = serde_json::from_value(json!({
+            "version": "1.0.0",
+            "suggest": {
+                "JDK": ["java/openjdk", "java/temurin-jdk"],
+                "vcredist": "extras/vcredist2022",
+                "Git": "git",
+            },
+        }))
+        .unwrap();
+
+        let user = TestContext::new("suggest-user");
+        let global = TestContext::new("suggest-global");
+        std::fs::create_dir_all(user.apps_path().join("temurin-jdk")).unwrap();
+        std::fs::create_dir_all(global.apps_path().join("git")).unwrap();
+
+        let unmet = manifest
+            .unmet_suggestions(&[user.clone(), global.clone()])
+            .unwrap();
+
+        assert_eq!(
+            unmet,
+            [UnmetSuggestion {
+                feature: "vcredist".to_string(),
+                alternatives: vec!["extras/vcredist2022".parse().unwrap()],
+            }]
+        );
+
+        // Only apps in the user scope
+        let unmet = manifest.unmet_suggestions(&[user.clone()]).unwrap();
+        assert_eq!(
+            unmet
+                .iter()
+                .map(|suggestion| suggestion.feature.as_str())
+                .collect::<Vec<_>>(),
+            ["Git", "vcredist"]
+        );
+
+        user.cleanup();
+        global.cleanup();
+    }
+}
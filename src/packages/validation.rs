@@ -0,0 +1,393 @@
+//! Manifest parsing and validation helpers
+//!
+//! Parsing errors report the JSON path of the value that failed to parse,
+//! and, with the `manifest-validation` feature,
+//! manifests can be validated against the Scoop JSON schema referenced by their `$schema` field.
+
+use std::fmt::Display;
+
+use serde::de::DeserializeOwned;
+
+#[derive(Debug, thiserror::Error)]
+/// An error parsing a manifest, including the path to the value that failed to parse
+pub struct ParseError {
+    path: Option<String>,
+    #[source]
+    source: serde_json::Error,
+}
+
+impl ParseError {
+    #[must_use]
+    /// Get the JSON path to the value that failed to parse (i.e `architecture.64bit.hash[1]`)
+    ///
+    /// Returns `None` if the error occurred at the root of the document,
+    /// for example if the document is not valid JSON.
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    #[must_use]
+    /// Get the underlying [`serde_json`] error
+    pub fn inner(&self) -> &serde_json::Error {
+        &self.source
+    }
+
+    /// Locate an error that occurred within a flattened struct
+    ///
+    /// Errors within `#[serde(flatten)]` fields lose their path, as serde buffers flattened fields.
+    /// If this error stopped at the given section (`None` for the root of the document),
+    /// the section is deserialized again as the flattened type `F` to find the full path.
+    pub(crate) fn locate_flattened<F: DeserializeOwned>(
+        mut self,
+        contents: &str,
+        section: Option<&str>,
+    ) -> Self {
+        if self.path.as_deref() != section {
+            return self;
+        }
+
+        let Ok(value) =
+            serde_json::from_str::<serde_json::Value>(contents.trim_start_matches('\u{feff}'))
+        else {
+            return self;
+        };
+
+        let value = match section {
+            Some(section) => value.get(section).cloned().unwrap_or_default(),
+            None => value,
+        };
+
+        if let Err(e) = serde_path_to_error::deserialize::<_, F>(value) {
+            if let Some(path) = known_path(e.path()) {
+                self.path = Some(match section {
+                    Some(section) if path.starts_with('[') => format!("{section}{path}"),
+                    Some(section) => format!("{section}.{path}"),
+                    None => path,
+                });
+            }
+        }
+
+        self
+    }
+
+    #[must_use]
+    /// Get the line the error occurred on
+    pub fn line(&self) -> usize {
+        self.source.line()
+    }
+
+    #[must_use]
+    /// Get the column the error occurred on
+    pub fn column(&self) -> usize {
+        self.source.column()
+    }
+}
+
+impl From<serde_json::Error> for ParseError {
+    fn from(source: serde_json::Error) -> Self {
+        Self { path: None, source }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(path) = &self.path {
+            write!(f, "at `{path}`: {}", self.source)
+        } else {
+            self.source.fmt(f)
+        }
+    }
+}
+
+/// Parse a manifest, tracking the path to any value that fails to parse
+///
+/// Leading byte order marks are ignored.
+///
+/// # Errors
+/// - The contents are not valid JSON
+/// - The contents do not match the expected shape
+pub fn parse<T: DeserializeOwned>(contents: &str) -> Result<T, ParseError> {
+    let trimmed = contents.trim_start_matches('\u{feff}');
+
+    let mut deserializer = serde_json::Deserializer::from_str(trimmed);
+
+    let value = serde_path_to_error::deserialize(&mut deserializer).map_err(|e| ParseError {
+        path: known_path(e.path()),
+        source: e.into_inner(),
+    })?;
+
+    deserializer
+        .end()
+        .map_err(|source| ParseError { path: None, source })?;
+
+    Ok(value)
+}
+
+/// Get the path as a string, if any part of it is known
+fn known_path(path: &serde_path_to_error::Path) -> Option<String> {
+    let path = path.to_string();
+
+    if path == "." || path == "?" {
+        None
+    } else {
+        Some(path)
+    }
+}
+
+#[cfg(feature = "manifest-validation")]
+pub use schema::*;
+
+#[cfg(feature = "manifest-validation")]
+mod schema {
+    use std::fmt::Display;
+
+    use jsonschema::{paths::PathChunk, JSONSchema};
+    use serde_json::Value;
+
+    use crate::requests::Client;
+
+    /// The Scoop manifest schema, used when a manifest does not reference a schema
+    pub const DEFAULT_SCHEMA_URL: &str =
+        "https://raw.githubusercontent.com/ScoopInstaller/Scoop/master/schema.json";
+
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    /// Schema validation errors
+    pub enum Error {
+        #[error("Invalid JSON: {0}")]
+        InvalidJson(#[from] serde_json::Error),
+        #[error("Fetching schema: {0}")]
+        Reqwest(#[from] reqwest::Error),
+        #[error("Invalid schema: {0}")]
+        InvalidSchema(String),
+    }
+
+    /// Schema validation result type
+    pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    /// A single schema violation in a manifest
+    pub struct Violation {
+        /// The JSON path to the offending value (i.e `architecture.64bit.hash[1]`)
+        ///
+        /// This is empty if the violation is at the root of the manifest
+        pub path: String,
+        /// A description of the violation
+        pub message: String,
+    }
+
+    impl Display for Violation {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            if self.path.is_empty() {
+                write!(f, "{}", self.message)
+            } else {
+                write!(f, "at `{}`: {}", self.path, self.message)
+            }
+        }
+    }
+
+    /// A compiled manifest schema
+    pub struct Schema {
+        inner: JSONSchema,
+    }
+
+    impl std::fmt::Debug for Schema {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("Schema").finish_non_exhaustive()
+        }
+    }
+
+    impl Schema {
+        /// Compile the given JSON schema
+        ///
+        /// # Errors
+        /// - The schema is not a valid JSON schema
+        pub fn new(schema: &Value) -> Result<Self> {
+            let inner =
+                JSONSchema::compile(schema).map_err(|e| Error::InvalidSchema(e.to_string()))?;
+
+            Ok(Self { inner })
+        }
+
+        /// Download and compile the schema at the given url
+        ///
+        /// # Errors
+        /// - The schema could not be downloaded
+        /// - The schema is not a valid JSON schema
+        pub async fn fetch(url: &str) -> Result<Self> {
+            let schema = Client::asynchronous()
+                .get(url)
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await?;
+
+            Self::new(&serde_json::from_str(&schema)?)
+        }
+
+        /// Download and compile the schema referenced by the manifest's `$schema` field
+        ///
+        /// Falls back to [`DEFAULT_SCHEMA_URL`] if the manifest does not reference a schema
+        ///
+        /// # Errors
+        /// - The schema could not be downloaded
+        /// - The schema is not a valid JSON schema
+        pub async fn for_manifest(manifest: &Value) -> Result<Self> {
+            let url = manifest
+                .get("$schema")
+                .and_then(Value::as_str)
+                .unwrap_or(DEFAULT_SCHEMA_URL);
+
+            Self::fetch(url).await
+        }
+
+        #[must_use]
+        /// Validate the manifest against the schema, returning all violations
+        pub fn validate(&self, manifest: &Value) -> Vec<Violation> {
+            match self.inner.validate(manifest) {
+                Ok(()) => vec![],
+                Err(errors) => errors
+                    .map(|error| Violation {
+                        path: display_path(error.instance_path.iter()),
+                        message: error.to_string(),
+                    })
+                    .collect(),
+            }
+        }
+    }
+
+    /// Validate a manifest against the Scoop JSON schema referenced by its `$schema` field
+    ///
+    /// # Errors
+    /// - The manifest is not valid JSON
+    /// - The schema could not be downloaded or compiled
+    pub async fn validate(contents: &str) -> Result<Vec<Violation>> {
+        let manifest: Value = serde_json::from_str(contents.trim_start_matches('\u{feff}'))?;
+
+        let schema = Schema::for_manifest(&manifest).await?;
+
+        Ok(schema.validate(&manifest))
+    }
+
+    fn display_path<'a>(chunks: impl Iterator<Item = &'a PathChunk>) -> String {
+        let mut path = String::new();
+
+        for chunk in chunks {
+            match chunk {
+                PathChunk::Index(index) => path += &format!("[{index}]"),
+                PathChunk::Property(property) => {
+                    if !path.is_empty() {
+                        path.push('.');
+                    }
+                    path += property;
+                }
+                PathChunk::Keyword(keyword) => {
+                    if !path.is_empty() {
+                        path.push('.');
+                    }
+                    path += keyword;
+                }
+            }
+        }
+
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::packages::{CreateManifest, InstallManifest, Manifest};
+
+    #[test]
+    fn test_parse_error_path() {
+        const MANIFEST: &str = r#"{
+            "version": "1.0.0",
+            "architecture": {
+                "64bit": {
+                    "url": "https://example.com/app.zip",
+                    "hash": ["ca0a5f3e0bd5b9a5d4bc1a1f47d42d7a20d0b7dfc4e4ea4ee8e6d3b4d5d3f7b0", 5]
+                }
+            }
+        }"#;
+
+        let error = Manifest::from_str(MANIFEST.to_string()).unwrap_err();
+
+        assert_eq!(error.path(), Some("architecture.64bit.hash[1]"));
+        assert!(error.to_string().contains("expected a string"));
+    }
+
+    #[test]
+    fn test_parse_error_alias_array_path() {
+        const MANIFEST: &str = r#"{
+            "version": "1.0.0",
+            "bin": ["app.exe", ["app.exe", 1]]
+        }"#;
+
+        let error = Manifest::from_str(MANIFEST.to_string()).unwrap_err();
+
+        assert_eq!(error.path(), Some("bin[1][1]"));
+    }
+
+    #[test]
+    fn test_parse_error_autoupdate_path() {
+        const MANIFEST: &str = r#"{
+            "version": "1.0.0",
+            "autoupdate": {
+                "url": ["https://example.com/$version.zip", false]
+            }
+        }"#;
+
+        let error = Manifest::from_str(MANIFEST.to_string()).unwrap_err();
+
+        assert_eq!(error.path(), Some("autoupdate.url[1]"));
+    }
+
+    #[test]
+    fn test_parse_error_install_manifest() {
+        let error = InstallManifest::from_str(r#"{"bucket": 1}"#.to_string()).unwrap_err();
+
+        assert_eq!(error.path(), Some("bucket"));
+    }
+
+    #[test]
+    fn test_parse_error_syntax() {
+        let error = parse::<Manifest>("{").unwrap_err();
+
+        assert_eq!(error.path(), None);
+    }
+
+    #[cfg(feature = "manifest-validation")]
+    #[test]
+    fn test_schema_violations() {
+        let schema = Schema::new(&serde_json::json!({
+            "type": "object",
+            "properties": {
+                "architecture": {
+                    "type": "object",
+                    "properties": {
+                        "64bit": {
+                            "type": "object",
+                            "properties": {
+                                "hash": {
+                                    "type": "array",
+                                    "items": { "type": "string" }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let violations = schema.validate(&serde_json::json!({
+            "architecture": { "64bit": { "hash": ["abc", 5] } }
+        }));
+
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].path, "architecture.64bit.hash[1]");
+    }
+}
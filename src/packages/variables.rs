@@ -0,0 +1,230 @@
+//! Install-time variables
+//!
+//! Substitutes the variables Scoop provides while installing an app (`$dir`, `$persist_dir`, `$version`, etc.)
+//! into the manifest fields that Scoop substitutes them into
+
+use std::path::Path;
+
+use crate::{
+    hash::substitutions::{Substitute, SubstitutionMap},
+    Architecture,
+};
+
+use super::{
+    models::manifest::{InstallConfig, Installer, Uninstaller},
+    Manifest,
+};
+
+#[derive(Debug, Clone)]
+/// The variables available while installing, updating or uninstalling an app
+///
+/// Paths are substituted as displayed, and `$global` as `True` or `False`, matching Scoop's `PowerShell` output
+pub struct InstallVariables {
+    map: SubstitutionMap,
+}
+
+impl InstallVariables {
+    #[must_use]
+    /// Create the variables for an app
+    ///
+    /// Sets `$app`, `$version` and `$architecture`, and `$global` to `False`
+    pub fn new(app: impl Into<String>, version: impl Into<String>, arch: Architecture) -> Self {
+        let mut map = SubstitutionMap::new();
+
+        map.insert("$app".into(), app.into());
+        map.insert("$version".into(), version.into());
+        map.insert("$architecture".into(), arch.to_string());
+
+        Self { map }.with_global(false)
+    }
+
+    fn with(mut self, key: &str, value: impl Into<String>) -> Self {
+        self.map.insert(key.into(), value.into());
+        self
+    }
+
+    fn with_path(self, key: &str, path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().display().to_string();
+        self.with(key, path)
+    }
+
+    #[must_use]
+    /// Set `$dir`, the app's directory
+    ///
+    /// This is the `current` directory, unless junctions are disabled
+    pub fn with_dir(self, dir: impl AsRef<Path>) -> Self {
+        self.with_path("$dir", dir)
+    }
+
+    #[must_use]
+    /// Set `$original_dir`, the app's version directory
+    pub fn with_original_dir(self, original_dir: impl AsRef<Path>) -> Self {
+        self.with_path("$original_dir", original_dir)
+    }
+
+    #[must_use]
+    /// Set `$persist_dir`, the app's persist directory
+    pub fn with_persist_dir(self, persist_dir: impl AsRef<Path>) -> Self {
+        self.with_path("$persist_dir", persist_dir)
+    }
+
+    #[must_use]
+    /// Set `$bucketsdir`, the context's buckets directory
+    pub fn with_buckets_dir(self, buckets_dir: impl AsRef<Path>) -> Self {
+        self.with_path("$bucketsdir", buckets_dir)
+    }
+
+    #[must_use]
+    /// Set `$global`, whether the app is installed globally
+    pub fn with_global(self, global: bool) -> Self {
+        self.with("$global", if global { "True" } else { "False" })
+    }
+
+    #[must_use]
+    /// Set `$cmd`, the Scoop command being run (i.e `install`, `update` or `uninstall`)
+    pub fn with_cmd(self, cmd: impl Into<String>) -> Self {
+        self.with("$cmd", cmd)
+    }
+
+    #[must_use]
+    /// Get the value of a variable
+    pub fn get(&self, variable: &str) -> Option<&str> {
+        self.map.get(variable).map(String::as_str)
+    }
+
+    /// Substitute the variables into a string
+    pub fn substitute(&self, string: &mut String) {
+        self.map.substitute(string, false);
+    }
+
+    #[must_use]
+    /// Substitute the variables into a copy of a string
+    pub fn substituted(&self, string: impl Into<String>) -> String {
+        let mut string = string.into();
+        self.substitute(&mut string);
+        string
+    }
+
+    /// Substitute the variables into the fields of an install config that Scoop substitutes them into
+    ///
+    /// These are `installer.args`, `uninstaller.args`, `env_set`, `env_add_path` and `shortcuts`
+    pub fn apply_to_config(&self, config: &mut InstallConfig) {
+        if let Some(Installer {
+            args: Some(args), ..
+        }) = &mut config.installer
+        {
+            args.substitute(&self.map, false);
+        }
+        if let Some(Uninstaller {
+            args: Some(args), ..
+        }) = &mut config.uninstaller
+        {
+            args.substitute(&self.map, false);
+        }
+
+        config.env_set.substitute(&self.map, false);
+        config.env_add_path.substitute(&self.map, false);
+        config.shortcuts.substitute(&self.map, false);
+    }
+
+    /// Substitute the variables into the fields of a manifest that Scoop substitutes them into
+    ///
+    /// These are `notes`, and the fields listed in [`InstallVariables::apply_to_config`],
+    /// both at the root of the manifest and in each architecture
+    pub fn apply(&self, manifest: &mut Manifest) {
+        manifest.notes.substitute(&self.map, false);
+
+        self.apply_to_config(&mut manifest.install_config);
+
+        if let Some(architecture) = &mut manifest.architecture {
+            for arch in [Architecture::Arm64, Architecture::X64, Architecture::X86] {
+                if let Some(config) = &mut architecture[arch] {
+                    self.apply_to_config(config);
+                }
+            }
+        }
+    }
+}
+
+impl Manifest {
+    #[must_use]
+    /// Get a copy of the manifest with the install-time variables substituted
+    ///
+    /// See [`InstallVariables::apply`] for the substituted fields
+    pub fn with_variables(&self, variables: &InstallVariables) -> Self {
+        let mut manifest = self.clone();
+        variables.apply(&mut manifest);
+        manifest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_apply_variables() {
+        let manifest: Manifest = serde_json::from_value(json!({
+            "version": "1.2.3",
+            "notes": ["Config is in $persist_dir", "Run $app from $dir ($architecture, global: $global)"],
+            "installer": { "args": ["/S", "/D=$dir"], "file": "$dir\\setup.exe" },
+            "uninstaller": { "args": "/uninstall $original_dir", "file": "uninstall.exe" },
+            "env_set": { "APP_HOME": "$dir", "APP_BUCKETS": "$bucketsdir" },
+            "env_add_path": "$dir\\bin",
+            "shortcuts": [["app.exe", "App", "--data $persist_dir"]],
+            "architecture": {
+                "64bit": {
+                    "installer": { "args": "/cmd=$cmd /version=$version" },
+                },
+            },
+        }))
+        .unwrap();
+
+        let variables = InstallVariables::new("app", "1.2.3", Architecture::X64)
+            .with_dir(r"C:\Scoop\apps\app\current")
+            .with_original_dir(r"C:\Scoop\apps\app\1.2.3")
+            .with_persist_dir(r"C:\Scoop\persist\app")
+            .with_buckets_dir(r"C:\Scoop\buckets")
+            .with_cmd("install");
+
+        let substituted = serde_json::to_value(manifest.with_variables(&variables)).unwrap();
+
+        assert_eq!(
+            substituted["notes"],
+            json!([
+                r"Config is in C:\Scoop\persist\app",
+                r"Run app from C:\Scoop\apps\app\current (64bit, global: False)",
+            ])
+        );
+        assert_eq!(
+            substituted["installer"],
+            json!({
+                "args": ["/S", r"/D=C:\Scoop\apps\app\current"],
+                // Only the arguments are substituted
+                "file": "$dir\\setup.exe",
+            })
+        );
+        assert_eq!(
+            substituted["uninstaller"]["args"],
+            json!(r"/uninstall C:\Scoop\apps\app\1.2.3")
+        );
+        assert_eq!(
+            substituted["env_set"],
+            json!({ "APP_HOME": r"C:\Scoop\apps\app\current", "APP_BUCKETS": r"C:\Scoop\buckets" })
+        );
+        assert_eq!(
+            substituted["env_add_path"],
+            json!(r"C:\Scoop\apps\app\current\bin")
+        );
+        assert_eq!(
+            substituted["shortcuts"],
+            json!([["app.exe", "App", r"--data C:\Scoop\persist\app"]])
+        );
+        assert_eq!(
+            substituted["architecture"]["64bit"]["installer"]["args"],
+            json!("/cmd=install /version=1.2.3")
+        );
+    }
+}
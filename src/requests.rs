@@ -10,6 +10,9 @@ use reqwest::header::HeaderMap;
 
 use crate::config;
 
+#[cfg(test)]
+pub(crate) mod testing;
+
 /// User agent for sfsu
 pub const USER_AGENT: &str = {
     use const_format::formatcp;
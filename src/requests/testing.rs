@@ -0,0 +1,43 @@
+//! A local HTTP server, for use in tests
+
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+};
+
+/// Serve the given paths and bodies over HTTP, returning the base url
+///
+/// Paths are matched exactly, including any query string. Unknown paths return a 404
+pub fn serve(routes: &'static [(&'static str, &'static str)]) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bound local listener");
+    let base_url = format!(
+        "http://{}",
+        listener.local_addr().expect("local listener address")
+    );
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else {
+                continue;
+            };
+
+            let mut buf = [0; 4096];
+            let len = stream.read(&mut buf).unwrap_or_default();
+            let request = String::from_utf8_lossy(&buf[..len]);
+            let path = request.split_whitespace().nth(1).unwrap_or_default();
+
+            let response = match routes.iter().find(|(route, _)| *route == path) {
+                Some((_, body)) => format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                ),
+                None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    .to_string(),
+            };
+
+            _ = stream.write_all(response.as_bytes());
+        }
+    });
+
+    base_url
+}
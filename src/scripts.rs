@@ -15,14 +15,22 @@
 //! ```
 
 use std::{
+    ffi::OsString,
     path::{Path, PathBuf},
     process::{ExitStatus, Output},
+    sync::Arc,
+    time::Duration,
 };
 
-use tokio::process::Command;
-
 use crate::{contexts::ScoopContext, packages::models::manifest::TOrArrayOfTs};
 
+use engine::{PowerShell, RunOptions, ScriptEngine};
+
+pub mod engine;
+pub mod prelude;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
 #[derive(Debug, thiserror::Error)]
 #[allow(missing_docs)]
 /// Errors that can occur when running a script
@@ -33,6 +41,8 @@ pub enum Error {
     FindPowershell(#[from] which::Error),
     #[error("Running script: {0}")]
     IOError(#[from] std::io::Error),
+    #[error("Script timed out after {0:?}")]
+    Timeout(Duration),
 }
 
 /// A Powershell script runner result
@@ -84,6 +94,16 @@ impl PowershellScript {
     /// # Errors
     /// - The script could not be written to the directory
     pub fn save_to(&self, directory: impl AsRef<Path>) -> Result<ScriptRunner> {
+        ScriptRunner::from_path(self.write_to(directory)?)
+    }
+
+    /// Write the script to a directory, and return the path, without finding an engine to run it
+    ///
+    /// The file will be named `<script-hash>.ps1`, and will not be overwritten if it already exists
+    ///
+    /// # Errors
+    /// - The script could not be written to the directory
+    pub fn write_to(&self, directory: impl AsRef<Path>) -> Result<PathBuf> {
         let hash = blake3::hash(self.script.as_bytes());
 
         let file_path = directory.as_ref().join(format!("{hash}.ps1"));
@@ -92,7 +112,7 @@ impl PowershellScript {
             std::fs::write(&file_path, self.script.as_bytes())?;
         }
 
-        ScriptRunner::from_path(file_path)
+        Ok(file_path)
     }
 }
 
@@ -128,26 +148,21 @@ impl crate::hash::substitutions::Substitute for PowershellScript {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 #[must_use]
 /// A script runner
 ///
-/// This is used to run scripts in a powershell environment
+/// This is used to run scripts with a [`ScriptEngine`], which defaults to [`PowerShell`]
 pub struct ScriptRunner {
     path: PathBuf,
-    powershell_path: PathBuf,
+    engine: Arc<dyn ScriptEngine>,
+    options: RunOptions,
 }
 
 impl ScriptRunner {
     /// Create a new script runner
     pub fn new(path: impl AsRef<Path>, powershell_path: impl AsRef<Path>) -> Self {
-        let path = path.as_ref().to_path_buf();
-        let powershell_path = powershell_path.as_ref().to_path_buf();
-
-        Self {
-            path,
-            powershell_path,
-        }
+        Self::with_engine(path, Arc::new(PowerShell::new(powershell_path.as_ref())))
     }
 
     /// Create a new script runner, getting powershell from the system path
@@ -155,31 +170,56 @@ impl ScriptRunner {
     /// # Errors
     /// - If powershell is not found in the system path
     pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
-        let path = path.as_ref().to_path_buf();
+        Ok(Self::with_engine(path, Arc::new(PowerShell::find()?)))
+    }
+
+    /// Create a new script runner, using the given engine
+    pub fn with_engine(path: impl AsRef<Path>, engine: Arc<dyn ScriptEngine>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            engine,
+            options: RunOptions::default(),
+        }
+    }
 
-        let powershell_path = which::which("pwsh").or_else(|_| which::which("powershell"))?;
+    /// Run the script with the given options (i.e timeout, working directory, environment and output streaming)
+    pub fn with_options(mut self, options: RunOptions) -> Self {
+        self.options = options;
+        self
+    }
 
-        Ok(Self {
-            path,
-            powershell_path,
-        })
+    #[must_use]
+    /// Get the path to the script
+    pub fn path(&self) -> &Path {
+        &self.path
     }
 
     /// Run a script
     ///
     /// # Errors
-    /// - If powershell exited with a non-zero exit code
-    /// - If powershell could not be found in the system path
-    /// - If the script could not be written to the path
+    /// - If the script exited with a non-zero exit code
+    /// - If the script could not be run
+    /// - If the script timed out
     pub async fn run(&self) -> Result<Output> {
-        let output = Command::new(&self.powershell_path)
-            .arg("-NoProfile")
-            .arg("-ExecutionPolicy")
-            .arg("Bypass")
-            .arg("-File")
-            .arg(&self.path)
-            .output()
-            .await?;
+        self.run_with_args(std::iter::empty::<&str>()).await
+    }
+
+    /// Run a script with the given arguments
+    ///
+    /// # Errors
+    /// - If the script exited with a non-zero exit code
+    /// - If the script could not be run
+    /// - If the script timed out
+    pub async fn run_with_args(
+        &self,
+        args: impl IntoIterator<Item = impl AsRef<std::ffi::OsStr>>,
+    ) -> Result<Output> {
+        let args = args
+            .into_iter()
+            .map(|arg| arg.as_ref().to_os_string())
+            .collect::<Vec<OsString>>();
+
+        let output = self.engine.run(&self.path, &args, &self.options).await?;
 
         if !output.status.success() {
             return Err(Error::PowershellExit(output.status, output));
@@ -223,7 +263,7 @@ mod ser_de {
 
 #[cfg(test)]
 mod tests {
-    use crate::contexts::User;
+    use crate::contexts::{testing::TestContext, User};
 
     use super::*;
 
@@ -240,4 +280,39 @@ mod tests {
         assert_eq!(output.status.code(), Some(0));
         assert_eq!(output.stdout, b"Hello, world!\r\n");
     }
+
+    #[tokio::test]
+    async fn test_script_engine() {
+        let ctx = TestContext::new("engine");
+        let dir = ctx.path();
+
+        let engine = testing::TestEngine::new().fail_on("throw");
+        let path = PowershellScript::new("Write-Host 'Hello, world!'")
+            .write_to(dir)
+            .unwrap();
+
+        let output = ScriptRunner::with_engine(&path, Arc::new(engine.clone()))
+            .with_options(RunOptions::new().with_cwd(dir))
+            .run_with_args(["-Verbose"])
+            .await
+            .unwrap();
+        assert_eq!(output.stdout, b"Write-Host 'Hello, world!'\n");
+
+        let failing = PowershellScript::new("throw 'oops'").write_to(dir).unwrap();
+        assert!(matches!(
+            ScriptRunner::with_engine(&failing, Arc::new(engine.clone()))
+                .run()
+                .await,
+            Err(Error::PowershellExit(..))
+        ));
+
+        let runs = engine.runs();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].path, path);
+        assert_eq!(runs[0].args, ["-Verbose"]);
+        assert_eq!(runs[0].cwd.as_deref(), Some(dir));
+        assert_eq!(runs[1].script, "throw 'oops'");
+
+        ctx.cleanup();
+    }
 }
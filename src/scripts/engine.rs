@@ -0,0 +1,309 @@
+//! Script engines
+//!
+//! A [`ScriptEngine`] runs script files. [`PowerShell`] is used by default,
+//! but other engines can be plugged into [`ScriptRunner`](super::ScriptRunner) and the installer hosts.
+
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::{Output, Stdio},
+    sync::Arc,
+    time::Duration,
+};
+
+use futures::{future::BoxFuture, FutureExt};
+use strum::Display;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    process::Command,
+};
+
+use super::{Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+#[strum(serialize_all = "lowercase")]
+/// An output stream of a script
+pub enum Stream {
+    /// The standard output stream
+    Stdout,
+    /// The standard error stream
+    Stderr,
+}
+
+type OutputFn = dyn Fn(Stream, &str) + Send + Sync;
+
+/// A callback receiving each line a script writes, without its line ending
+pub type OutputCallback = Arc<OutputFn>;
+
+#[derive(Clone, Default)]
+#[must_use]
+/// Options for a single script run
+pub struct RunOptions {
+    /// The maximum time the script may run for, after which it is killed
+    pub timeout: Option<Duration>,
+    /// The working directory
+    ///
+    /// Defaults to the current working directory
+    pub cwd: Option<PathBuf>,
+    /// Environment variables to set, in addition to the inherited environment
+    pub env: BTreeMap<String, String>,
+    /// A callback receiving each line of output, as it is written
+    pub output: Option<OutputCallback>,
+}
+
+impl std::fmt::Debug for RunOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RunOptions")
+            .field("timeout", &self.timeout)
+            .field("cwd", &self.cwd)
+            .field("env", &self.env)
+            .field("output", &self.output.as_ref().map(|_| "<callback>"))
+            .finish()
+    }
+}
+
+impl RunOptions {
+    /// Create the default run options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Kill the script if it runs for longer than the given duration
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Run the script in the given working directory
+    pub fn with_cwd(mut self, cwd: impl Into<PathBuf>) -> Self {
+        self.cwd = Some(cwd.into());
+        self
+    }
+
+    /// Set an environment variable for the script
+    pub fn with_env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.insert(name.into(), value.into());
+        self
+    }
+
+    /// Stream each line of output to the given callback, as it is written
+    pub fn with_output(mut self, callback: impl Fn(Stream, &str) + Send + Sync + 'static) -> Self {
+        self.output = Some(Arc::new(callback));
+        self
+    }
+}
+
+/// An engine that runs script files
+///
+/// Engines return the script's output whatever its exit status,
+/// leaving it to the caller to decide whether a non-zero exit code is an error
+pub trait ScriptEngine: std::fmt::Debug + Send + Sync {
+    /// Run a script file with the given arguments and options
+    ///
+    /// # Errors
+    /// - The script could not be run
+    /// - The script timed out
+    fn run<'a>(
+        &'a self,
+        script: &'a Path,
+        args: &'a [OsString],
+        options: &'a RunOptions,
+    ) -> BoxFuture<'a, Result<Output>>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Runs scripts with `PowerShell`
+pub struct PowerShell {
+    path: PathBuf,
+}
+
+impl PowerShell {
+    #[must_use]
+    /// Use the `PowerShell` executable at the given path
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Find `PowerShell` in the system path, preferring `pwsh` over Windows `PowerShell`
+    ///
+    /// # Errors
+    /// - If powershell is not found in the system path
+    pub fn find() -> Result<Self> {
+        let path = which::which("pwsh").or_else(|_| which::which("powershell"))?;
+
+        Ok(Self::new(path))
+    }
+
+    #[must_use]
+    /// Get the path to the `PowerShell` executable
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl ScriptEngine for PowerShell {
+    fn run<'a>(
+        &'a self,
+        script: &'a Path,
+        args: &'a [OsString],
+        options: &'a RunOptions,
+    ) -> BoxFuture<'a, Result<Output>> {
+        let mut command = Command::new(&self.path);
+        command
+            .arg("-NoProfile")
+            .arg("-ExecutionPolicy")
+            .arg("Bypass")
+            .arg("-File")
+            .arg(script)
+            .args(args);
+
+        run_command(command, options).boxed()
+    }
+}
+
+/// Run a command with the given options, streaming its output line by line
+///
+/// The command is killed if it times out, or if the returned future is dropped
+///
+/// # Errors
+/// - The command could not be run
+/// - The command timed out
+pub async fn run_command(mut command: Command, options: &RunOptions) -> Result<Output> {
+    if let Some(cwd) = &options.cwd {
+        command.current_dir(cwd);
+    }
+
+    command
+        .envs(&options.env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let mut child = command.spawn()?;
+    let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+        unreachable!("stdout and stderr are piped");
+    };
+
+    let callback = options.output.as_deref();
+    let run = async {
+        let (stdout, stderr, status) = futures::join!(
+            read_lines(stdout, Stream::Stdout, callback),
+            read_lines(stderr, Stream::Stderr, callback),
+            child.wait(),
+        );
+
+        Ok::<_, std::io::Error>(Output {
+            status: status?,
+            stdout: stdout?,
+            stderr: stderr?,
+        })
+    };
+
+    let Some(timeout) = options.timeout else {
+        return Ok(run.await?);
+    };
+
+    let result = tokio::time::timeout(timeout, run).await;
+    if let Ok(output) = result {
+        Ok(output?)
+    } else {
+        child.kill().await?;
+        Err(Error::Timeout(timeout))
+    }
+}
+
+/// Read a stream to the end, passing each line to the callback
+async fn read_lines(
+    reader: impl AsyncRead + Unpin,
+    stream: Stream,
+    callback: Option<&OutputFn>,
+) -> std::io::Result<Vec<u8>> {
+    let mut reader = BufReader::new(reader);
+    let mut output = vec![];
+
+    loop {
+        let start = output.len();
+        if reader.read_until(b'\n', &mut output).await? == 0 {
+            break;
+        }
+
+        if let Some(callback) = callback {
+            let line = String::from_utf8_lossy(&output[start..]);
+            callback(stream, line.trim_end_matches(['\r', '\n']));
+        }
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[cfg(unix)]
+    fn shell(script: &str) -> Command {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(script);
+        command
+    }
+
+    #[cfg(windows)]
+    fn shell(script: &str) -> Command {
+        let mut command = Command::new("cmd");
+        command.arg("/C").arg(script);
+        command
+    }
+
+    #[tokio::test]
+    async fn test_run_command_streams_output() {
+        let lines = Arc::new(Mutex::new(vec![]));
+        let options = RunOptions::new()
+            .with_env("SPRINKLES_TEST_VALUE", "hello")
+            .with_cwd(std::env::temp_dir())
+            .with_output({
+                let lines = lines.clone();
+                move |stream, line| lines.lock().unwrap().push((stream, line.to_string()))
+            });
+
+        #[cfg(unix)]
+        let command = shell("echo $SPRINKLES_TEST_VALUE; echo world 1>&2");
+        #[cfg(windows)]
+        let command = shell("echo %SPRINKLES_TEST_VALUE%& echo world 1>&2");
+
+        let output = run_command(command, &options).await.unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "hello");
+        assert_eq!(String::from_utf8_lossy(&output.stderr).trim(), "world");
+
+        let mut lines = lines.lock().unwrap().clone();
+        lines.sort_by_key(|(stream, _)| *stream == Stream::Stderr);
+        assert_eq!(
+            lines
+                .iter()
+                .map(|(stream, line)| (*stream, line.trim()))
+                .collect::<Vec<_>>(),
+            [(Stream::Stdout, "hello"), (Stream::Stderr, "world")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_command_timeout() {
+        let options = RunOptions::new().with_timeout(Duration::from_millis(100));
+
+        #[cfg(unix)]
+        let command = shell("sleep 5");
+        #[cfg(windows)]
+        let command = shell("ping -n 6 127.0.0.1 > NUL");
+
+        let started = std::time::Instant::now();
+        let result = run_command(command, &options).await;
+
+        assert!(matches!(result, Err(Error::Timeout(_))));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
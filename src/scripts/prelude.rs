@@ -0,0 +1,198 @@
+//! Script preludes
+//!
+//! Manifest scripts run inside Scoop's own `PowerShell` session,
+//! so they expect Scoop's variables (`$dir`, `$version`, `$manifest`, etc.) and helper functions (`info`, `ensure`, etc.) to exist.
+//! A [`Prelude`] defines them before the script body.
+
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use super::PowershellScript;
+
+/// A minimal shim of the Scoop helper functions commonly used by manifest scripts
+const HELPERS: &str = r#"function abort($msg, [int] $exit_code = 1) { Write-Host $msg -ForegroundColor Red; exit $exit_code }
+function error($msg) { Write-Host "ERROR $msg" -ForegroundColor DarkRed }
+function warn($msg) { Write-Host "WARN  $msg" -ForegroundColor DarkYellow }
+function info($msg) { Write-Host "INFO  $msg" -ForegroundColor DarkGray }
+function success($msg) { Write-Host $msg -ForegroundColor DarkGreen }
+function ensure($dir) {
+    if (!(Test-Path -Path $dir)) { New-Item -Path $dir -ItemType Directory | Out-Null }
+    Convert-Path -Path $dir
+}
+function fullpath($path) { $ExecutionContext.SessionState.Path.GetUnresolvedProviderPathFromPSPath($path) }
+function Test-CommandAvailable([string] $Command) { [bool](Get-Command $Command -ErrorAction SilentlyContinue) }"#;
+
+#[must_use]
+/// Quote a string as a `PowerShell` single-quoted string literal
+///
+/// Single-quoted strings are not expanded, so only quote characters need escaping.
+/// `PowerShell` treats the typographic single quotes as quotes too, so they are escaped as well.
+pub fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+
+    quoted.push('\'');
+    for c in value.chars() {
+        if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}') {
+            quoted.push(c);
+        }
+        quoted.push(c);
+    }
+    quoted.push('\'');
+
+    quoted
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[must_use]
+/// Defines variables and helper functions before a script body
+///
+/// # Example
+/// ```
+/// # use sprinkles::scripts::{prelude::Prelude, PowershellScript};
+/// let prelude = Prelude::new()
+///     .with_string("app", "sfsu")
+///     .with_bool("global", false);
+///
+/// let script = prelude.apply(&PowershellScript::new("Write-Host $app"));
+///
+/// assert_eq!(script.as_str(), "$app = 'sfsu'\n$global = $false\n\nWrite-Host $app");
+/// ```
+pub struct Prelude {
+    variables: Vec<(String, String)>,
+    helpers: bool,
+}
+
+impl Prelude {
+    /// Create an empty prelude
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn with_expression(mut self, name: impl Into<String>, expression: String) -> Self {
+        let name = name.into();
+
+        match self
+            .variables
+            .iter_mut()
+            .find(|(existing, _)| existing == &name)
+        {
+            Some((_, existing)) => *existing = expression,
+            None => self.variables.push((name, expression)),
+        }
+
+        self
+    }
+
+    /// Define a string variable
+    pub fn with_string(self, name: impl Into<String>, value: impl AsRef<str>) -> Self {
+        self.with_expression(name, quote(value.as_ref()))
+    }
+
+    /// Define a boolean variable
+    pub fn with_bool(self, name: impl Into<String>, value: bool) -> Self {
+        self.with_expression(name, if value { "$true" } else { "$false" }.to_string())
+    }
+
+    /// Define a variable as an object, converted from its JSON representation
+    ///
+    /// # Errors
+    /// - The value could not be serialized to JSON
+    pub fn with_json(
+        self,
+        name: impl Into<String>,
+        value: &impl Serialize,
+    ) -> serde_json::Result<Self> {
+        let json = serde_json::to_string(value)?;
+
+        Ok(self.with_expression(name, format!("{} | ConvertFrom-Json", quote(&json))))
+    }
+
+    /// Include the shim of Scoop's helper functions (`info`, `warn`, `error`, `abort`, `success`, `ensure`, `fullpath`)
+    pub fn with_helpers(mut self, helpers: bool) -> Self {
+        self.helpers = helpers;
+        self
+    }
+
+    #[must_use]
+    /// Get the `PowerShell` expression a variable is defined as, if any
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.variables
+            .iter()
+            .find(|(existing, _)| existing == name)
+            .map(|(_, expression)| expression.as_str())
+    }
+
+    #[must_use]
+    /// Get the prelude as `PowerShell` source
+    pub fn to_source(&self) -> String {
+        let mut source = String::new();
+
+        for (name, expression) in &self.variables {
+            // Braced variable names allow any characters, except unescaped braces
+            if name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                _ = writeln!(source, "${name} = {expression}");
+            } else {
+                let name = name.replace('`', "``").replace('}', "`}");
+                _ = writeln!(source, "${{{name}}} = {expression}");
+            }
+        }
+
+        if self.helpers {
+            source.push_str(HELPERS);
+            source.push('\n');
+        }
+
+        source
+    }
+
+    #[must_use]
+    /// Prepend the prelude to a script
+    pub fn apply(&self, script: &PowershellScript) -> PowershellScript {
+        let source = self.to_source();
+        if source.is_empty() {
+            return script.clone();
+        }
+
+        PowershellScript::new(format!("{source}\n{}", script.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("C:\\Scoop\\apps"), "'C:\\Scoop\\apps'");
+        assert_eq!(quote("it's $dir"), "'it''s $dir'");
+        assert_eq!(quote("\u{2019}"), "'\u{2019}\u{2019}'");
+    }
+
+    #[test]
+    fn test_prelude_source() {
+        let prelude = Prelude::new()
+            .with_string("dir", r"C:\Users\O'Brien\scoop\apps\app\current")
+            .with_bool("global", true)
+            .with_string("my-var", "value")
+            .with_json("manifest", &serde_json::json!({ "version": "1.0.0" }))
+            .unwrap()
+            .with_string("dir", r"C:\Scoop\apps\app\current");
+
+        assert_eq!(prelude.get("global"), Some("$true"));
+        assert_eq!(
+            prelude.to_source(),
+            [
+                r"$dir = 'C:\Scoop\apps\app\current'",
+                "$global = $true",
+                "${my-var} = 'value'",
+                r#"$manifest = '{"version":"1.0.0"}' | ConvertFrom-Json"#,
+                "",
+            ]
+            .join("\n")
+        );
+
+        let source = prelude.with_helpers(true).to_source();
+        assert!(source.contains("function ensure($dir)"));
+    }
+}
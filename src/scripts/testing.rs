@@ -0,0 +1,115 @@
+//! A script engine that records scripts instead of running them, for use in tests
+//!
+//! This lets lifecycle scripts be tested without Powershell installed.
+//! Outside of this crate it requires the `testing` feature.
+
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::Output,
+    sync::{Arc, Mutex},
+};
+
+use futures::{future::BoxFuture, FutureExt};
+
+use super::{
+    engine::{RunOptions, ScriptEngine, Stream},
+    Result,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A recorded script run
+pub struct TestRun {
+    /// The path to the script file
+    pub path: PathBuf,
+    /// The script's contents
+    pub script: String,
+    /// The arguments passed to the script
+    pub args: Vec<OsString>,
+    /// The working directory
+    pub cwd: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default)]
+/// A script engine that records each script it is asked to run, without running it
+///
+/// Each script "writes" its first line to stdout, and succeeds unless it contains the failure marker
+pub struct TestEngine {
+    runs: Arc<Mutex<Vec<TestRun>>>,
+    fail_on: Option<String>,
+}
+
+impl TestEngine {
+    #[must_use]
+    /// Create a test engine
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    /// Fail any script containing the given text, with exit code 1
+    pub fn fail_on(mut self, marker: impl Into<String>) -> Self {
+        self.fail_on = Some(marker.into());
+        self
+    }
+
+    #[must_use]
+    /// Get the recorded runs, in the order they were run
+    ///
+    /// # Panics
+    /// - A script run panicked while recording, poisoning the lock
+    pub fn runs(&self) -> Vec<TestRun> {
+        self.runs.lock().expect("runs lock").clone()
+    }
+}
+
+#[cfg(unix)]
+fn exit_status(code: i32) -> std::process::ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+
+    std::process::ExitStatus::from_raw(code << 8)
+}
+
+#[cfg(windows)]
+fn exit_status(code: i32) -> std::process::ExitStatus {
+    use std::os::windows::process::ExitStatusExt;
+
+    std::process::ExitStatus::from_raw(code.unsigned_abs())
+}
+
+impl ScriptEngine for TestEngine {
+    fn run<'a>(
+        &'a self,
+        script: &'a Path,
+        args: &'a [OsString],
+        options: &'a RunOptions,
+    ) -> BoxFuture<'a, Result<Output>> {
+        async move {
+            let contents = std::fs::read_to_string(script)?;
+
+            let failed = self
+                .fail_on
+                .as_ref()
+                .is_some_and(|marker| contents.contains(marker));
+            let line = contents.lines().next().unwrap_or_default().to_string();
+
+            self.runs.lock().expect("runs lock").push(TestRun {
+                path: script.to_path_buf(),
+                script: contents,
+                args: args.to_vec(),
+                cwd: options.cwd.clone(),
+            });
+
+            if let Some(callback) = &options.output {
+                callback(Stream::Stdout, &line);
+            }
+
+            Ok(Output {
+                status: exit_status(i32::from(failed)),
+                stdout: format!("{line}\n").into_bytes(),
+                stderr: vec![],
+            })
+        }
+        .boxed()
+    }
+}
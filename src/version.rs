@@ -2,12 +2,14 @@
 
 use std::{
     borrow::Cow,
+    cmp::Ordering,
     fmt::{Display, Formatter},
     num::ParseIntError,
 };
 
 #[cfg(feature = "manifest-hashes")]
 use getset::Getters;
+use once_cell::sync::Lazy;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
@@ -19,6 +21,10 @@ use crate::hash::substitutions::SubstitutionMap;
 /// A struct representing a string version
 pub struct Version(String);
 
+static PART_SEPARATORS: Lazy<Regex> = Lazy::new(|| Regex::new(r"[._-]").unwrap());
+
+static LETTERS: Lazy<Regex> = Lazy::new(|| Regex::new("[a-zA-Z]+").unwrap());
+
 impl From<String> for Version {
     fn from(value: String) -> Self {
         Self(value)
@@ -26,10 +32,6 @@ impl From<String> for Version {
 }
 
 impl Version {
-    fn part_regex() -> Regex {
-        Regex::new(r"[._-]").unwrap()
-    }
-
     /// Create a new version string
     pub fn new(version: impl Into<String>) -> Self {
         Self(version.into())
@@ -44,25 +46,25 @@ impl Version {
     #[must_use]
     /// Get the version string with dots instead of separators
     pub fn dot_version(&self) -> Cow<'_, str> {
-        Self::part_regex().replace_all(&self.0, ".")
+        PART_SEPARATORS.replace_all(&self.0, ".")
     }
 
     #[must_use]
     /// Get the version string with underscores instead of separators
     pub fn underscore_version(&self) -> Cow<'_, str> {
-        Self::part_regex().replace_all(&self.0, "_")
+        PART_SEPARATORS.replace_all(&self.0, "_")
     }
 
     #[must_use]
     /// Get the version string with dashes instead of separators
     pub fn dash_version(&self) -> Cow<'_, str> {
-        Self::part_regex().replace_all(&self.0, "-")
+        PART_SEPARATORS.replace_all(&self.0, "-")
     }
 
     #[must_use]
     /// Get the version string with all separators removed
     pub fn clean_version(&self) -> Cow<'_, str> {
-        Self::part_regex().replace_all(&self.0, "")
+        PART_SEPARATORS.replace_all(&self.0, "")
     }
 
     #[cfg(feature = "manifest-hashes")]
@@ -149,6 +151,144 @@ impl Display for Version {
     }
 }
 
+impl Version {
+    #[must_use]
+    /// Compare versions like Scoop's `Compare-Version`
+    ///
+    /// Versions are split into parts on `-` (`+` is treated as `-`), then `.`, then `_`,
+    /// and runs of letters are split into their own parts (i.e `1.2b` is `1`, `2`, `b`).
+    /// Numeric parts are compared as numbers, and other parts are compared case insensitively.
+    ///
+    /// Pre-release parts (i.e parts containing `alpha`, `beta`, `rc` or `pre`) are older than a missing part,
+    /// which is older than any other part, so `1.1-beta` < `1.1` < `1.1-1` < `1.1.1`.
+    /// Numbers are older than other text, so `1.1-1` < `1.1-b`.
+    /// All `nightly` versions are equal.
+    ///
+    /// Unlike [`Ord::cmp`], versions with different strings may be equal (i.e `1.0-beta` and `1.0-BETA`)
+    pub fn scoop_cmp(&self, other: &Self) -> Ordering {
+        compare_parts(&self.0.replace('+', "-"), &other.0.replace('+', "-"), '-')
+    }
+}
+
+impl Ord for Version {
+    /// Compare versions using [`Version::scoop_cmp`]
+    ///
+    /// Versions that Scoop considers equal are ordered by their raw string,
+    /// so that the ordering is consistent with [`Eq`].
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.scoop_cmp(other).then_with(|| self.0.cmp(&other.0))
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Compare two versions split on the given delimiter, like Scoop's `Compare-Version`
+fn compare_parts(version: &str, other: &str, delimiter: char) -> Ordering {
+    fn split(version: &str, delimiter: char) -> Vec<String> {
+        LETTERS
+            .replace_all(version, |captures: &regex::Captures<'_>| {
+                format!("{delimiter}{}{delimiter}", &captures[0])
+            })
+            .split(delimiter)
+            .filter(|part| !part.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    if version.eq_ignore_ascii_case(other) {
+        return Ordering::Equal;
+    }
+
+    let parts = split(version, delimiter);
+    let other_parts = split(other, delimiter);
+
+    let is_nightly = |parts: &[String]| {
+        parts
+            .first()
+            .is_some_and(|part| part.eq_ignore_ascii_case("nightly"))
+    };
+    if is_nightly(&parts) && is_nightly(&other_parts) {
+        return Ordering::Equal;
+    }
+
+    for i in 0..parts.len().max(other_parts.len()) {
+        let part = parts.get(i).map(String::as_str);
+        let other_part = other_parts.get(i).map(String::as_str);
+
+        // Every kind of part has a fixed rank, so that the ordering stays transitive
+        let ordering = PartRank::of(part)
+            .cmp(&PartRank::of(other_part))
+            .then_with(|| match (part, other_part) {
+                (Some(part), Some(other_part)) => {
+                    if part.contains('.') || other_part.contains('.') {
+                        compare_parts(part, other_part, '.')
+                    } else if part.contains('_') || other_part.contains('_') {
+                        compare_parts(part, other_part, '_')
+                    } else {
+                        compare_part(part, other_part)
+                    }
+                }
+                _ => Ordering::Equal,
+            });
+
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    Ordering::Equal
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+/// The rank of a version part, from oldest to newest
+enum PartRank {
+    PreRelease,
+    Missing,
+    Release,
+}
+
+impl PartRank {
+    fn of(part: Option<&str>) -> Self {
+        let Some(part) = part else {
+            return Self::Missing;
+        };
+        let part = part.to_ascii_lowercase();
+
+        if ["alpha", "beta", "rc", "pre"]
+            .iter()
+            .any(|pre_release| part.contains(pre_release))
+        {
+            Self::PreRelease
+        } else {
+            Self::Release
+        }
+    }
+}
+
+/// Compare a single version part
+///
+/// Numbers are compared numerically, and are older than any other text
+fn compare_part(part: &str, other: &str) -> Ordering {
+    let is_numeric = |part: &str| part.bytes().all(|byte| byte.is_ascii_digit());
+
+    match (is_numeric(part), is_numeric(other)) {
+        (true, true) => {
+            // Compare by length then digits, to avoid overflowing on very long numbers (i.e dates and hashes)
+            let part = part.trim_start_matches('0');
+            let other = other.trim_start_matches('0');
+
+            part.len().cmp(&other.len()).then_with(|| part.cmp(other))
+        }
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        (false, false) => part.to_ascii_lowercase().cmp(&other.to_ascii_lowercase()),
+    }
+}
+
 impl TryFrom<&Version> for semver::Version {
     type Error = semver::Error;
 
@@ -238,4 +378,157 @@ mod tests {
 
         assert_eq!(v1, Version::new(1, 2, 3));
     }
+
+    #[test]
+    fn test_compare_versions() {
+        use std::cmp::Ordering::{self, Equal, Greater, Less};
+
+        const CASES: &[(&str, &str, Ordering)] = &[
+            // Numeric parts
+            ("1.0", "1.0", Equal),
+            ("1.4", "1.3.0", Greater),
+            ("1.2.3", "1.10.0", Less),
+            ("1.1", "1.1.1", Less),
+            ("1.01", "1.1", Equal),
+            ("20160116", "20160117", Less),
+            (
+                "20240101123456789012345",
+                "20240101123456789012344",
+                Greater,
+            ),
+            // Mixed alphanumeric parts
+            ("1.1.1a", "1.1.1b", Less),
+            ("1.1.1b", "1.1.1", Greater),
+            ("1.2b", "1.2.b", Equal),
+            ("1.0-BETA", "1.0-beta", Equal),
+            // Separators
+            ("1.1.1_8", "1.1.1", Greater),
+            ("1.1.1_8", "1.1.1_10", Less),
+            ("1.0.0+build.1", "1.0.0", Greater),
+            ("1.0.0+1", "1.0.0-1", Equal),
+            // Pre-releases
+            ("1.1-alpha", "1.1", Less),
+            ("1.1", "1.1-beta", Greater),
+            ("1.1.1-rc1", "1.1.1", Less),
+            ("1.1-pre", "1.1-alpha", Greater),
+            ("1.0.0-alpha", "1.0.0-beta", Less),
+            ("2.0.0-beta.2", "2.0.0-beta.10", Less),
+            ("2.0.0-rc.1", "2.0.0-beta.10", Greater),
+            ("1.1", "1.1-1", Less),
+            ("1.1-alpha", "1.1-1", Less),
+            ("1.1-1", "1.1-b", Less),
+            // Special values
+            ("nightly", "nightly-20190801", Equal),
+            ("nightly-20200101", "nightly-20190801", Equal),
+        ];
+
+        for (version, other, expected) in CASES {
+            let (version, other) = (super::Version::new(*version), super::Version::new(*other));
+
+            assert_eq!(
+                version.scoop_cmp(&other),
+                *expected,
+                "comparing {version} to {other}"
+            );
+            assert_eq!(
+                other.scoop_cmp(&version),
+                expected.reverse(),
+                "comparing {other} to {version}"
+            );
+
+            if *expected == Equal {
+                assert_eq!(version.cmp(&other), version.as_str().cmp(other.as_str()));
+            } else {
+                assert_eq!(
+                    version.cmp(&other),
+                    *expected,
+                    "ordering {version} and {other}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_compare_versions_transitive() {
+        use std::cmp::Ordering::{Equal, Greater, Less};
+
+        let versions = [
+            "1.1",
+            "1.1-1",
+            "1.1-2",
+            "1.1-alpha",
+            "1.1-ALPHA",
+            "1.1-beta.2",
+            "1.1-rc1",
+            "1.1-b",
+            "1.1.0",
+            "1.1.1",
+            "1.1.1-pre",
+            "1.1_1",
+            "1.1+build",
+            "1.1a",
+            "1.2",
+            "1.2-1",
+            "1.2-beta",
+            "1.10",
+            "nightly",
+            "nightly-20200101",
+        ]
+        .map(super::Version::new);
+
+        for a in &versions {
+            for b in &versions {
+                assert_eq!(
+                    a.scoop_cmp(b),
+                    b.scoop_cmp(a).reverse(),
+                    "comparing {a} and {b}"
+                );
+
+                for c in &versions {
+                    let (ab, bc, ac) = (a.scoop_cmp(b), b.scoop_cmp(c), a.scoop_cmp(c));
+
+                    if ab != Greater && bc != Greater {
+                        assert_ne!(ac, Greater, "{a} <= {b} <= {c}, but {a} > {c}");
+                    }
+                    if ab == Equal && bc == Equal {
+                        assert_eq!(ac, Equal, "{a} == {b} == {c}, but {a} != {c}");
+                    }
+                    if ab == Less && bc == Less {
+                        assert_eq!(ac, Less, "{a} < {b} < {c}, but {a} >= {c}");
+                    }
+                }
+            }
+        }
+
+        let mut sorted = versions.clone();
+        sorted.sort();
+        assert!(sorted.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn test_sort_versions() {
+        let mut versions = [
+            "1.10.0",
+            "1.2.0",
+            "1.2.0-beta",
+            "1.2.0-alpha",
+            "1.2.0.1",
+            "1.9",
+        ]
+        .map(super::Version::new);
+
+        versions.sort();
+
+        assert_eq!(
+            versions.map(|version| version.to_string()),
+            [
+                "1.2.0-alpha",
+                "1.2.0-beta",
+                "1.2.0",
+                "1.2.0.1",
+                "1.9",
+                "1.10.0"
+            ]
+        );
+    }
 }